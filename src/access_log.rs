@@ -0,0 +1,46 @@
+use std::time::Instant;
+
+use actix_web::Error;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use log::info;
+use serde_json::json;
+
+use crate::request_id::RequestId;
+
+pub(crate) const ACCESS_LOG_TARGET: &str = "gmgr::access";
+
+/// Logs one JSON object per request under the `gmgr::access` target, with
+/// the pin id the route matched so writes can be traced to their pin.
+pub(crate) async fn json_access_log(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let path = req.path().to_string();
+
+    let res = next.call(req).await;
+    let (status, pin_id) = match &res {
+        Ok(res) => (
+            res.status().as_u16(),
+            res.request()
+                .match_info()
+                .get("pin_id")
+                .and_then(|id| id.parse::<u32>().ok()),
+        ),
+        Err(e) => (e.as_response_error().status_code().as_u16(), None),
+    };
+    let line = json!({
+        "request_id": RequestId::current().map(|RequestId(id)| id),
+        "method": method,
+        "path": path,
+        "pin_id": pin_id,
+        "status": status,
+        "latency_ms": started.elapsed().as_secs_f64() * 1000.0,
+    });
+    info!(target: ACCESS_LOG_TARGET, "{line}");
+
+    res
+}
@@ -0,0 +1,51 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use log::warn;
+use parking_lot::Mutex;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::AppError;
+use crate::gpio::PinId;
+
+/// One applied write or control operation.
+#[derive(Debug, Serialize)]
+pub(crate) struct AuditRecord {
+    pub timestamp_ms: u64,
+    pub op: &'static str,
+    pub pin_id: PinId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    pub new: Value,
+    pub request_id: Option<String>,
+    /// Which configured key was used, as `api_keys[index]`, never the key.
+    pub api_key: Option<String>,
+}
+
+/// Append-only JSON lines file of audit records.
+pub(crate) struct AuditLog {
+    file: Mutex<File>,
+}
+
+impl AuditLog {
+    pub(crate) fn open<P: AsRef<Path>>(path: P) -> Result<Self, AppError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| AppError::Config(format!("failed to open audit log: {e}")))?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub(crate) fn append(&self, record: &AuditRecord) {
+        let line = serde_json::to_string(record).unwrap_or_default();
+        if let Err(e) = writeln!(self.file.lock(), "{line}") {
+            warn!("failed to append to audit log: {e}");
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{Error, web};
+
+use crate::error::AppError;
+use crate::gpio::GpioBackend;
+use crate::routes::AppState;
+
+pub(crate) const API_KEY_HEADER: &str = "x-api-key";
+
+/// Rejects requests without a configured `X-API-Key` when auth is enabled.
+pub(crate) async fn require_api_key<B: GpioBackend + 'static>(
+    state: web::Data<AppState<B>>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let auth = &state.manager.config().auth;
+    if auth.is_enabled() {
+        let key = req
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|v| v.to_str().ok());
+        if !key.is_some_and(|key| auth.api_keys.iter().any(|k| k == key)) {
+            let err = AppError::PermissionDenied("missing or invalid api key".into());
+            return Ok(req.error_response(err).map_into_right_body());
+        }
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
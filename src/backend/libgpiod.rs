@@ -1,57 +1,77 @@
 use log::warn;
+use std::os::unix::io::AsRawFd;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
-use std::thread::{JoinHandle, yield_now};
+use std::thread::JoinHandle;
 use std::time::Duration;
 
 use libgpiod::{chip::Chip, line, line::EventClock, request};
-use parking_lot::{FairMutex, RwLock as PLRwLock, RwLockUpgradableReadGuard};
+use parking_lot::{FairMutex, Mutex as PLMutex, RwLock as PLRwLock, RwLockUpgradableReadGuard};
 use rustc_hash::FxHashMap;
 
-use crate::config::{EdgeDetect, PinConfig};
+use super::pwm::SoftPwm;
+use super::retry::RetryPolicy;
+use crate::config::{EdgeDetect, EventClockSource, PinConfig};
 use crate::error::AppError;
-use crate::gpio::{EdgeEvent, EventHandler, GpioBackend, GpioState, PinSettings};
-
-const LIBGPIOD_BACKEND_EVENT_BUFFER_CAPACITY: usize = 64;
-const LIBGPIOD_BACKEND_EVENT_WAIT_TIMEOUT_MS: Duration = Duration::from_millis(10);
+use crate::gpio::{
+    Bias, ChipInfo, EdgeEvent, EventHandler, GpioBackend, GpioState, LineInfo, PinId, PinSettings,
+};
 
 pub struct LibgpiodBackend {
-    pins: PLRwLock<FxHashMap<u32, RwLock<PinHandle>>>, // keyed by pin id
+    chips: PLRwLock<FxHashMap<String, Arc<FairMutex<ChipLines>>>>, // keyed by chip path
+    pins: PLRwLock<FxHashMap<PinId, RwLock<PinHandle>>>,           // keyed by pin id
+    // kept apart from the handles, which are dropped when a pin is disabled
+    settings_versions: PLMutex<FxHashMap<PinId, u64>>,
+    // serializes set_settings, whose line requests run on the blocking pool
+    settings_update: tokio::sync::Mutex<()>,
+    poller: EdgePoller,
+    event_buffer_capacity: usize,
+    event_wait_timeout: Duration,
+    event_clock: EventClockSource,
+    retry: RetryPolicy,
+    consumer: String,
 }
 
 struct PinHandle {
+    chip: Arc<FairMutex<ChipLines>>,
     line: u32,
     settings: PinSettings,
-    gpiod_handle: Arc<FairMutex<GpiodHandle>>,
-    listener: Option<EdgeListener>, // drop in reverse order
+    pwm: Option<SoftPwm>,
 }
 
 impl PinHandle {
-    fn new(
-        line: u32,
-        settings: PinSettings,
-        gpiod_handle: Arc<FairMutex<GpiodHandle>>,
-        listener: Option<EdgeListener>,
-    ) -> Self {
+    fn new(chip: Arc<FairMutex<ChipLines>>, line: u32, settings: PinSettings) -> Self {
         Self {
+            chip,
             line,
             settings,
-            gpiod_handle,
-            listener,
+            pwm: None,
         }
     }
 }
 
-struct GpiodHandle {
-    request: request::Request,
+/// Every line held on one chip, requested together so pins of a chip are
+/// written and read with a single call.
+///
+/// A request can't grow or shrink, so enabling or disabling a pin releases
+/// the chip's lines and requests them again. Outputs are requested at the
+/// level they drove, but may glitch while released.
+struct ChipLines {
+    chip: Chip,
+    request: Option<request::Request>,
+    lines: FxHashMap<line::Offset, line::Settings>, // settings each held line was requested with
+    written: FxHashMap<line::Offset, u8>,           // last value written per output line
 }
 
-impl GpiodHandle {
-    fn new(chip: &str, line_cfg: &line::Config) -> Result<Self, AppError> {
-        let chip = Self::open_chip(chip)?;
-        let request = Self::request_lines(&chip, line_cfg)?;
-        Ok(Self { request })
+impl ChipLines {
+    fn new(chip: Chip) -> Self {
+        Self {
+            chip,
+            request: None,
+            lines: FxHashMap::default(),
+            written: FxHashMap::default(),
+        }
     }
 
     fn open_chip(path: &str) -> Result<Chip, AppError> {
@@ -59,87 +79,343 @@ impl GpiodHandle {
         Chip::open(&p).map_err(|e| AppError::Gpio(format!("open chip {path}: {e}")))
     }
 
-    fn request_lines(chip: &Chip, line_cfg: &line::Config) -> Result<request::Request, AppError> {
+    /// Moves `offset` to `settings`, or releases it when `None`. A line
+    /// already held is reconfigured in place, adding or releasing one
+    /// requests the chip's lines again and restores the old request when
+    /// that fails.
+    fn apply(
+        &mut self,
+        pin_id: PinId,
+        offset: line::Offset,
+        settings: Option<line::Settings>,
+        retry: &RetryPolicy,
+        consumer: &str,
+    ) -> Result<(), AppError> {
+        let held = self.lines.contains_key(&offset);
+        if !held && settings.is_none() {
+            return Ok(());
+        }
+
+        let line_cfg = self.line_config(offset, settings.as_ref())?;
+        match self.request.as_mut() {
+            Some(request) if held && settings.is_some() => {
+                retry
+                    .run("reconfigure lines", is_transient, || {
+                        request.reconfigure_lines(&line_cfg).map(|_| ())
+                    })
+                    .map_err(|e| AppError::Gpio(format!("reconfigure lines: {e}")))?;
+            }
+            _ => {
+                let restore_cfg = self.line_config(offset, self.lines.get(&offset))?;
+                let remaining = settings.is_some() || self.lines.keys().any(|l| *l != offset);
+                self.request = None;
+                if remaining {
+                    let request =
+                        Self::request_lines(&self.chip, pin_id, offset, &line_cfg, retry, consumer)
+                            .inspect_err(|_| {
+                                self.restore(pin_id, offset, &restore_cfg, retry, consumer)
+                            })?;
+                    self.request = Some(request);
+                }
+            }
+        }
+
+        match settings {
+            Some(settings) => self.lines.insert(offset, settings),
+            None => self.lines.remove(&offset),
+        };
+        // the line may come back at another level
+        self.written.remove(&offset);
+        Ok(())
+    }
+
+    /// Requests the lines held before a failed change again.
+    fn restore(
+        &mut self,
+        pin_id: PinId,
+        offset: line::Offset,
+        restore_cfg: &line::Config,
+        retry: &RetryPolicy,
+        consumer: &str,
+    ) {
+        if self.lines.is_empty() {
+            return;
+        }
+        match Self::request_lines(&self.chip, pin_id, offset, restore_cfg, retry, consumer) {
+            Ok(request) => self.request = Some(request),
+            Err(e) => warn!("restore lines after pin {pin_id} failed: {e}"),
+        }
+    }
+
+    /// Config of every held line with `offset` set to `settings`, or left
+    /// out when `None`. Other outputs keep the level they drive now.
+    fn line_config(
+        &self,
+        offset: line::Offset,
+        settings: Option<&line::Settings>,
+    ) -> Result<line::Config, AppError> {
+        let mut cfg =
+            line::Config::new().map_err(|e| AppError::Gpio(format!("line config: {e}")))?;
+        let others = self.lines.iter().filter(|(line, _)| **line != offset);
+        for (line, ls) in others {
+            let mut ls = ls
+                .try_clone()
+                .map_err(|e| AppError::Gpio(format!("copy line settings: {e}")))?;
+            if matches!(ls.direction(), Ok(line::Direction::Output))
+                && let Some(value) = self.level(*line)
+            {
+                ls.set_output_value(value)
+                    .map_err(|e| AppError::Gpio(format!("set output value: {e}")))?;
+            }
+            cfg.add_line_settings(&[*line], ls)
+                .map_err(|e| AppError::Gpio(format!("line config add settings: {e}")))?;
+        }
+        if let Some(ls) = settings {
+            let ls = ls
+                .try_clone()
+                .map_err(|e| AppError::Gpio(format!("copy line settings: {e}")))?;
+            cfg.add_line_settings(&[offset], ls)
+                .map_err(|e| AppError::Gpio(format!("line config add settings: {e}")))?;
+        }
+        Ok(cfg)
+    }
+
+    fn request(&self) -> Result<&request::Request, AppError> {
+        self.request
+            .as_ref()
+            .ok_or_else(|| AppError::Gpio("chip lines not requested".into()))
+    }
+
+    fn request_mut(&mut self) -> Result<&mut request::Request, AppError> {
+        self.request
+            .as_mut()
+            .ok_or_else(|| AppError::Gpio("chip lines not requested".into()))
+    }
+
+    /// Level an output drives, the written one or else what the line reads,
+    /// which covers pwm lines.
+    fn level(&self, offset: line::Offset) -> Option<line::Value> {
+        match self.written.get(&offset) {
+            Some(1) => Some(line::Value::Active),
+            Some(_) => Some(line::Value::InActive),
+            None => self.request.as_ref()?.value(offset).ok(),
+        }
+    }
+
+    fn request_lines(
+        chip: &Chip,
+        pin_id: PinId,
+        offset: line::Offset,
+        line_cfg: &line::Config,
+        retry: &RetryPolicy,
+        consumer: &str,
+    ) -> Result<request::Request, AppError> {
         let mut req_cfg =
             request::Config::new().map_err(|e| AppError::Gpio(format!("request config: {e}")))?;
         req_cfg
-            .set_consumer(env!("CARGO_PKG_NAME"))
+            .set_consumer(consumer)
             .map_err(|e| AppError::Gpio(format!("request consumer: {e}")))?;
-        chip.request_lines(Some(&req_cfg), line_cfg)
-            .map_err(|e| AppError::Gpio(format!("request lines: {e}")))
+        retry
+            .run("request lines", is_transient, || {
+                chip.request_lines(Some(&req_cfg), line_cfg)
+            })
+            .map_err(|e| match e {
+                // the other lines are ours already, so `offset` is the one
+                // another process holds, name it so the client can act
+                libgpiod::Error::OperationFailed(_, errno) if errno.0 == libc::EBUSY => {
+                    let consumer = chip
+                        .line_info(offset)
+                        .ok()
+                        .and_then(|info| info.consumer().ok().map(str::to_string))
+                        .unwrap_or_else(|| "another consumer".into());
+                    AppError::LineBusy { pin_id, consumer }
+                }
+                e => AppError::Gpio(format!("request lines: {e}")),
+            })
     }
 }
 
-struct EdgeListener {
+/// Failures worth another try: the call was interrupted or the kernel asked
+/// to try again. A busy line stays busy until its consumer lets go.
+fn is_transient(e: &libgpiod::Error) -> bool {
+    matches!(e, libgpiod::Error::OperationFailed(_, errno)
+        if errno.0 == libc::EINTR || errno.0 == libc::EAGAIN)
+}
+
+/// Values to write to the lines of one chip.
+type WriteGroup = (Arc<FairMutex<ChipLines>>, Vec<(line::Offset, u8)>);
+/// Pins and their line offsets to read from one chip.
+type ReadGroup = (Arc<FairMutex<ChipLines>>, Vec<(PinId, line::Offset)>);
+/// Values answered from the write cache and the pins left to read.
+type ReadPlan = (Vec<(PinId, u8)>, Vec<ReadGroup>);
+
+struct EdgeRegistration {
+    chip: Arc<FairMutex<ChipLines>>,
+    line: line::Offset,
+    handler: EventHandler,
+}
+
+/// Pins registered on one chip, keyed by their line offset.
+type PolledChip = (
+    Arc<FairMutex<ChipLines>>,
+    Vec<(line::Offset, PinId, EventHandler)>,
+);
+
+/// Waits on the requests of every chip with edge-detecting pins from a
+/// single thread, handing each edge to the pin on its line.
+///
+/// A chip's `FairMutex` is only taken to drain its buffered events once
+/// `poll` reports them ready, so value reads and writes never queue behind
+/// the wait.
+struct EdgePoller {
+    registrations: Arc<PLMutex<FxHashMap<PinId, EdgeRegistration>>>, // keyed by pin id
+    iteration: Arc<FairMutex<()>>,
     cancel: Arc<AtomicBool>,
     handle: Option<JoinHandle<()>>,
 }
 
-impl EdgeListener {
-    fn new(
-        pin_id: u32,
-        gpiod_handle: Arc<FairMutex<GpiodHandle>>,
-        handler: EventHandler,
-    ) -> Result<Self, AppError> {
+impl EdgePoller {
+    fn new(buffer_capacity: usize, wait_timeout: Duration) -> Result<Self, AppError> {
+        let registrations = Arc::new(PLMutex::new(FxHashMap::<PinId, EdgeRegistration>::default()));
+        let iteration = Arc::new(FairMutex::new(()));
         let cancel = Arc::new(AtomicBool::new(false));
-        let cancel_flag = cancel.clone();
-        let mut buffer = request::Buffer::new(LIBGPIOD_BACKEND_EVENT_BUFFER_CAPACITY)
+        let mut buffer = request::Buffer::new(buffer_capacity)
             .map_err(|e| AppError::Gpio(format!("event buffer: {e}")))?;
+        let timeout_ms = wait_timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
 
-        let handle = std::thread::spawn(move || {
-            while !cancel_flag.load(Ordering::Relaxed) {
-                let hdl = gpiod_handle.lock();
-                let req = &hdl.request;
-
-                let has_event =
-                    match req.wait_edge_events(Some(LIBGPIOD_BACKEND_EVENT_WAIT_TIMEOUT_MS)) {
-                        Ok(v) => v,
-                        Err(e) => {
-                            warn!("wait edge events error for pin {pin_id}: {e}");
-                            yield_now();
-                            continue;
+        let handle = {
+            let registrations = registrations.clone();
+            let iteration = iteration.clone();
+            let cancel = cancel.clone();
+            std::thread::spawn(move || {
+                while !cancel.load(Ordering::Relaxed) {
+                    // held for the whole iteration so lines are only released
+                    // and requested again while no fd below is polled
+                    let iteration_guard = iteration.lock();
+                    let mut chips: Vec<PolledChip> = Vec::new();
+                    for (pin_id, r) in registrations.lock().iter() {
+                        let pin = (r.line, *pin_id, r.handler.clone());
+                        match chips
+                            .iter_mut()
+                            .find(|(chip, _)| Arc::ptr_eq(chip, &r.chip))
+                        {
+                            Some((_, pins)) => pins.push(pin),
+                            None => chips.push((r.chip.clone(), vec![pin])),
                         }
-                    };
-                if !has_event {
-                    continue;
-                }
-
-                let events = match req.read_edge_events(&mut buffer) {
-                    Ok(evts) => evts,
-                    Err(e) => {
-                        warn!("read edge events error for pin {pin_id}: {e}");
-                        yield_now();
+                    }
+                    if chips.is_empty() {
+                        drop(iteration_guard);
+                        std::thread::sleep(wait_timeout);
                         continue;
                     }
-                };
-                for evt in events {
-                    let evt = match evt {
-                        Ok(e) => e,
-                        Err(_) => continue,
-                    };
-                    let edge_kind = match evt.event_type() {
-                        Ok(line::EdgeKind::Rising) => EdgeDetect::Rising,
-                        Ok(line::EdgeKind::Falling) => EdgeDetect::Falling,
-                        Err(_) => continue,
+
+                    // negative fds of chips without a request are skipped by poll
+                    let mut fds: Vec<_> = chips
+                        .iter()
+                        .map(|(chip, _)| libc::pollfd {
+                            fd: chip.lock().request.as_ref().map_or(-1, |r| r.as_raw_fd()),
+                            events: libc::POLLIN,
+                            revents: 0,
+                        })
+                        .collect();
+                    // SAFETY: every fd belongs to a request kept alive until the
+                    // iteration guard is dropped
+                    let ret = unsafe {
+                        libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout_ms)
                     };
+                    if ret < 0 {
+                        let err = std::io::Error::last_os_error();
+                        if err.kind() != std::io::ErrorKind::Interrupted {
+                            warn!("poll edge events error: {err}");
+                            std::thread::sleep(wait_timeout);
+                        }
+                        continue;
+                    }
 
-                    handler.dispatch(EdgeEvent {
-                        pin_id,
-                        edge: edge_kind,
-                        timestamp_ms: evt.timestamp().as_millis() as u64,
-                    });
+                    for (fd, (chip, pins)) in fds.iter().zip(&chips) {
+                        if fd.revents & libc::POLLIN == 0 {
+                            continue;
+                        }
+                        for (line, edge, timestamp_ms) in Self::read_edges(chip, &mut buffer) {
+                            let Some((_, pin_id, handler)) =
+                                pins.iter().find(|(offset, _, _)| *offset == line)
+                            else {
+                                continue;
+                            };
+                            handler.dispatch(EdgeEvent {
+                                pin_id: *pin_id,
+                                edge,
+                                timestamp_ms,
+                                seq: 0,
+                                count: 1,
+                            });
+                        }
+                    }
                 }
-            }
-        });
+            })
+        };
 
         Ok(Self {
+            registrations,
+            iteration,
             cancel,
             handle: Some(handle),
         })
     }
+
+    fn read_edges(
+        chip: &FairMutex<ChipLines>,
+        buffer: &mut request::Buffer,
+    ) -> Vec<(line::Offset, EdgeDetect, u64)> {
+        let chip = chip.lock();
+        let Some(request) = chip.request.as_ref() else {
+            return Vec::new();
+        };
+        let events = match request.read_edge_events(buffer) {
+            Ok(evts) => evts,
+            Err(e) => {
+                warn!("read edge events error: {e}");
+                return Vec::new();
+            }
+        };
+
+        events
+            .filter_map(|evt| {
+                let evt = evt.ok()?;
+                let edge = match evt.event_type() {
+                    Ok(line::EdgeKind::Rising) => EdgeDetect::Rising,
+                    Ok(line::EdgeKind::Falling) => EdgeDetect::Falling,
+                    Err(_) => return None,
+                };
+                Some((evt.line_offset(), edge, evt.timestamp().as_millis() as u64))
+            })
+            .collect()
+    }
+
+    fn register(
+        &self,
+        pin_id: PinId,
+        chip: &Arc<FairMutex<ChipLines>>,
+        line: line::Offset,
+        handler: EventHandler,
+    ) {
+        self.registrations.lock().insert(
+            pin_id,
+            EdgeRegistration {
+                chip: chip.clone(),
+                line,
+                handler,
+            },
+        );
+    }
+
+    /// Edges already read for the pin may still be dispatched once.
+    fn unregister(&self, pin_id: PinId) {
+        self.registrations.lock().remove(&pin_id);
+    }
 }
 
-impl Drop for EdgeListener {
+impl Drop for EdgePoller {
     fn drop(&mut self) {
         self.cancel.store(true, Ordering::Relaxed);
         if let Some(handle) = self.handle.take() {
@@ -149,53 +425,80 @@ impl Drop for EdgeListener {
 }
 
 impl LibgpiodBackend {
-    pub fn new() -> Result<Self, AppError> {
+    /// `event_buffer_capacity` bounds the edges read per pin and wakeup, and
+    /// `event_wait_timeout` how long the poller blocks before picking up newly
+    /// registered pins. Shorter timeouts react faster to reconfiguration but
+    /// wake an idle poller more often. `event_clock` is the clock edge events
+    /// are stamped with, lines that reject it fall back to realtime. `retry`
+    /// covers line requests and reconfigurations failing transiently.
+    pub fn new(
+        event_buffer_capacity: usize,
+        event_wait_timeout: Duration,
+        event_clock: EventClockSource,
+        retry: RetryPolicy,
+    ) -> Result<Self, AppError> {
+        if event_buffer_capacity == 0 {
+            return Err(AppError::Config(
+                "libgpiod event buffer must hold at least one event".into(),
+            ));
+        }
+
         Ok(Self {
+            chips: PLRwLock::new(FxHashMap::default()),
             pins: PLRwLock::new(FxHashMap::default()),
+            settings_versions: PLMutex::new(FxHashMap::default()),
+            settings_update: tokio::sync::Mutex::new(()),
+            poller: EdgePoller::new(event_buffer_capacity, event_wait_timeout)?,
+            event_buffer_capacity,
+            event_wait_timeout,
+            event_clock,
+            retry,
+            consumer: env!("CARGO_PKG_NAME").into(),
         })
     }
 
-    fn validate_pin_settings(settings: &PinSettings) -> Result<(), AppError> {
-        match settings.state {
-            GpioState::Error => Err(AppError::InvalidState(
-                "cannot set pin to error state".into(),
-            )),
-            GpioState::Disabled => {
-                if settings.edge != EdgeDetect::None {
-                    return Err(AppError::InvalidState(
-                        "cannot set edge detection on disabled pin".into(),
-                    ));
-                }
-                if settings.debounce_ms != 0 {
-                    return Err(AppError::InvalidState(
-                        "cannot set debounce on disabled pin".into(),
-                    ));
-                }
-                Ok(())
-            }
-            _ => {
-                match settings.edge {
-                    EdgeDetect::None => {
-                        if settings.debounce_ms != 0 {
-                            return Err(AppError::InvalidState(
-                                "debouncing requires edge detection to be enabled".into(),
-                            ));
-                        }
-                    }
-                    _ => {
-                        if !settings.state.is_edge_detectable() {
-                            return Err(AppError::InvalidState(
-                                "edge detection requires an input-capable state".into(),
-                            ));
-                        }
-                    }
-                }
-                Ok(())
-            }
+    /// Requests lines under `consumer` instead of the crate name.
+    pub fn with_consumer(mut self, consumer: impl Into<String>) -> Self {
+        self.consumer = consumer.into();
+        self
+    }
+
+    pub fn event_buffer_capacity(&self) -> usize {
+        self.event_buffer_capacity
+    }
+
+    pub fn event_wait_timeout(&self) -> Duration {
+        self.event_wait_timeout
+    }
+
+    pub fn event_clock(&self) -> EventClockSource {
+        self.event_clock
+    }
+
+    pub fn consumer(&self) -> &str {
+        &self.consumer
+    }
+
+    pub fn opened_chips(&self) -> usize {
+        self.chips.read().len()
+    }
+
+    fn chip(&self, path: &str) -> Result<Arc<FairMutex<ChipLines>>, AppError> {
+        let chips = self.chips.upgradable_read();
+        if let Some(chip) = chips.get(path) {
+            return Ok(chip.clone());
         }
+
+        let chip = Arc::new(FairMutex::new(ChipLines::new(ChipLines::open_chip(path)?)));
+        RwLockUpgradableReadGuard::upgrade(chips).insert(path.to_string(), chip.clone());
+        Ok(chip)
     }
 
-    fn make_line_settings(settings: &PinSettings) -> Result<line::Settings, AppError> {
+    fn make_line_settings(
+        settings: &PinSettings,
+        active_low: bool,
+        clock: EventClockSource,
+    ) -> Result<line::Settings, AppError> {
         let mut ls =
             line::Settings::new().map_err(|e| AppError::Gpio(format!("libgpiod settings: {e}")))?;
 
@@ -205,7 +508,7 @@ impl LibgpiodBackend {
                     "cannot create settings for error or disabled state".into(),
                 ));
             }
-            GpioState::PushPull => {
+            GpioState::PushPull | GpioState::Pwm => {
                 ls.set_direction(line::Direction::Output)
                     .map_err(|e| AppError::Gpio(format!("set direction: {e}")))?;
                 ls.set_drive(line::Drive::PushPull)
@@ -226,6 +529,7 @@ impl LibgpiodBackend {
             GpioState::Floating => {
                 ls.set_direction(line::Direction::Input)
                     .map_err(|e| AppError::Gpio(format!("set direction: {e}")))?;
+                // leaves the pull as-is unless disabled explicitly below
                 ls.set_bias(None)
                     .map_err(|e| AppError::Gpio(format!("set bias: {e}")))?;
             }
@@ -243,6 +547,27 @@ impl LibgpiodBackend {
             }
         }
 
+        // pulled inputs already carry their pull, floating inputs may only
+        // turn it off and outputs get one when asked
+        let explicit = settings.state.is_writable()
+            || matches!(settings.state, GpioState::Pwm | GpioState::Floating);
+        if let Some(bias) = settings.bias.filter(|_| explicit) {
+            let bias = match bias {
+                Bias::Disabled => line::Bias::Disabled,
+                Bias::PullUp => line::Bias::PullUp,
+                Bias::PullDown => line::Bias::PullDown,
+            };
+            ls.set_bias(Some(bias))
+                .map_err(|e| AppError::Gpio(format!("set bias: {e}")))?;
+        }
+
+        if let Some(ma) = settings.drive_strength_ma {
+            warn!("drive strength of {ma}mA ignored, not supported by the gpio character device");
+        }
+
+        // values and edges are reported logically, the kernel handles the inversion
+        ls.set_active_low(active_low);
+
         if settings.edge != EdgeDetect::None && settings.state.is_edge_detectable() {
             let edge = match settings.edge {
                 EdgeDetect::None => None,
@@ -252,178 +577,569 @@ impl LibgpiodBackend {
             };
             ls.set_edge_detection(edge)
                 .map_err(|e| AppError::Gpio(format!("set edge detection: {e}")))?;
-            ls.set_event_clock(EventClock::Realtime)
+            let clock = match clock {
+                EventClockSource::Realtime => EventClock::Realtime,
+                EventClockSource::Monotonic => EventClock::Monotonic,
+                EventClockSource::Hte => EventClock::HTE,
+            };
+            ls.set_event_clock(clock)
                 .map_err(|e| AppError::Gpio(format!("set event clock: {e}")))?;
-            ls.set_debounce_period(Duration::from_millis(settings.debounce_ms));
+            // the manager debounces instead when software debounce is requested
+            let debounce_ms = if settings.software_debounce {
+                0
+            } else {
+                settings.debounce_ms
+            };
+            ls.set_debounce_period(Duration::from_millis(debounce_ms));
         }
 
         Ok(ls)
     }
 
-    fn make_line_config(offset: u32, settings: line::Settings) -> Result<line::Config, AppError> {
-        let mut cfg =
-            line::Config::new().map_err(|e| AppError::Gpio(format!("line config: {e}")))?;
-        cfg.add_line_settings(&[offset], settings)
-            .map_err(|e| AppError::Gpio(format!("line config add settings: {e}")))?;
-        Ok(cfg)
+    /// Maps what the kernel reports for a line back to settings, taking what
+    /// it can't report (pwm, software debounce) from the cached ones.
+    fn settings_from_info(
+        info: &line::Info,
+        cached: &PinSettings,
+    ) -> Result<PinSettings, AppError> {
+        let direction = info
+            .direction()
+            .map_err(|e| AppError::Gpio(format!("get direction: {e}")))?;
+        let state = match direction {
+            line::Direction::Output => {
+                match info
+                    .drive()
+                    .map_err(|e| AppError::Gpio(format!("get drive: {e}")))?
+                {
+                    line::Drive::PushPull if cached.state == GpioState::Pwm => GpioState::Pwm,
+                    line::Drive::PushPull => GpioState::PushPull,
+                    line::Drive::OpenDrain => GpioState::OpenDrain,
+                    line::Drive::OpenSource => GpioState::OpenSource,
+                }
+            }
+            line::Direction::Input => {
+                match info
+                    .bias()
+                    .map_err(|e| AppError::Gpio(format!("get bias: {e}")))?
+                {
+                    None | Some(line::Bias::Disabled) => GpioState::Floating,
+                    Some(line::Bias::PullUp) => GpioState::PullUp,
+                    Some(line::Bias::PullDown) => GpioState::PullDown,
+                }
+            }
+            line::Direction::AsIs => GpioState::Error,
+        };
+        // pulled input states imply their pull, see make_line_settings
+        let bias = match (direction, state) {
+            (line::Direction::Input, GpioState::Floating) => info
+                .bias()
+                .map_err(|e| AppError::Gpio(format!("get bias: {e}")))?
+                .filter(|bias| *bias == line::Bias::Disabled)
+                .map(|_| Bias::Disabled),
+            (line::Direction::Output, _) => info
+                .bias()
+                .map_err(|e| AppError::Gpio(format!("get bias: {e}")))?
+                .map(|bias| match bias {
+                    line::Bias::Disabled => Bias::Disabled,
+                    line::Bias::PullUp => Bias::PullUp,
+                    line::Bias::PullDown => Bias::PullDown,
+                }),
+            _ => None,
+        };
+        let edge = match info
+            .edge_detection()
+            .map_err(|e| AppError::Gpio(format!("get edge detection: {e}")))?
+        {
+            None => EdgeDetect::None,
+            Some(line::Edge::Rising) => EdgeDetect::Rising,
+            Some(line::Edge::Falling) => EdgeDetect::Falling,
+            Some(line::Edge::Both) => EdgeDetect::Both,
+        };
+        let debounce_ms = if cached.software_debounce {
+            cached.debounce_ms
+        } else {
+            info.debounce_period().as_millis() as u64
+        };
+
+        Ok(PinSettings {
+            state,
+            edge,
+            debounce_ms,
+            software_debounce: cached.software_debounce,
+            // merged in the server, the line knows nothing of it
+            coalesce_ms: cached.coalesce_ms,
+            pwm: cached.pwm.filter(|_| state == GpioState::Pwm),
+            // never applied, the character device has no drive strength
+            drive_strength_ma: None,
+            bias,
+        })
+    }
+
+    fn read_chip_info(path: &str) -> Result<ChipInfo, AppError> {
+        let chip = ChipLines::open_chip(path)?;
+        let info = chip
+            .info()
+            .map_err(|e| AppError::Gpio(format!("chip info {path}: {e}")))?;
+        let num_lines = info.num_lines() as u32;
+
+        let mut lines = Vec::with_capacity(num_lines as usize);
+        for offset in 0..num_lines {
+            let line = chip
+                .line_info(offset)
+                .map_err(|e| AppError::Gpio(format!("line info {path}:{offset}: {e}")))?;
+            lines.push(LineInfo {
+                offset,
+                name: line.name().ok().map(str::to_string),
+                consumer: line.consumer().ok().map(str::to_string),
+                used: line.is_used(),
+            });
+        }
+
+        Ok(ChipInfo {
+            path: path.to_string(),
+            name: info.name().unwrap_or_default().to_string(),
+            label: info.label().unwrap_or_default().to_string(),
+            num_lines,
+            lines,
+        })
+    }
+
+    /// Builds the line settings and hands them to `apply`, retrying with the
+    /// realtime clock when the kernel rejects the configured event clock.
+    fn apply_line_settings<T>(
+        pin_id: PinId,
+        active_low: bool,
+        settings: &PinSettings,
+        clock: EventClockSource,
+        mut apply: impl FnMut(line::Settings) -> Result<T, AppError>,
+    ) -> Result<T, AppError> {
+        match apply(Self::make_line_settings(settings, active_low, clock)?) {
+            Err(e)
+                if clock != EventClockSource::Realtime
+                    && settings.edge != EdgeDetect::None
+                    && settings.state.is_edge_detectable() =>
+            {
+                warn!(
+                    "event clock {clock:?} rejected for pin {pin_id}, falling back to realtime: {e}"
+                );
+                apply(Self::make_line_settings(
+                    settings,
+                    active_low,
+                    EventClockSource::Realtime,
+                )?)
+            }
+            res => res,
+        }
+    }
+
+    /// Starts software pwm on the line when `settings` ask for it.
+    fn start_pwm(
+        settings: &PinSettings,
+        chip: &Arc<FairMutex<ChipLines>>,
+        line: line::Offset,
+    ) -> Option<SoftPwm> {
+        let pwm = settings
+            .pwm
+            .as_ref()
+            .filter(|_| settings.state == GpioState::Pwm)?;
+        let chip = chip.clone();
+        Some(SoftPwm::new(pwm, move |v| {
+            let value = match v {
+                1 => line::Value::Active,
+                _ => line::Value::InActive,
+            };
+            if let Some(request) = chip.lock().request.as_mut()
+                && let Err(e) = request.set_value(line, value)
+            {
+                warn!("pwm set value error for line {line}: {e}");
+            }
+        }))
+    }
+
+    /// Validates every pin is an output and groups the values by chip, so
+    /// pins of one chip are written with a single set_values_subset call.
+    fn group_values(&self, values: &[(PinId, u8)]) -> Result<Vec<WriteGroup>, AppError> {
+        let pins = self.pins.read();
+
+        // lock every pin up front and validate before touching any line
+        let mut handles = Vec::with_capacity(values.len());
+        for (pin_id, value) in values {
+            let handle = pins
+                .get(pin_id)
+                .ok_or_else(|| {
+                    AppError::InvalidState(format!("pin {pin_id} not configured, set state first"))
+                })?
+                .read()?;
+            if !handle.settings.state.is_writable() {
+                return Err(AppError::InvalidState(format!(
+                    "pin {pin_id} must be in output mode to set value"
+                )));
+            }
+            handles.push((handle, *value));
+        }
+
+        let mut groups: Vec<WriteGroup> = Vec::new();
+        for (handle, value) in &handles {
+            match groups
+                .iter_mut()
+                .find(|(chip, _)| Arc::ptr_eq(chip, &handle.chip))
+            {
+                Some((_, lines)) => lines.push((handle.line, *value)),
+                None => groups.push((handle.chip.clone(), vec![(handle.line, *value)])),
+            }
+        }
+
+        Ok(groups)
+    }
+
+    /// Groups pins by chip so pins of one chip are read with a single
+    /// values_subset call, answering push-pull pins from their written value.
+    fn group_reads(&self, pins_to_read: &[PinId]) -> Result<ReadPlan, AppError> {
+        let pins = self.pins.read();
+
+        let mut cached = Vec::new();
+        let mut groups: Vec<ReadGroup> = Vec::new();
+        for pin_id in pins_to_read {
+            let handle = pins
+                .get(pin_id)
+                .ok_or_else(|| {
+                    AppError::InvalidState(format!("pin {pin_id} not configured, set state first"))
+                })?
+                .read()?;
+            if handle.settings.state == GpioState::PushPull
+                && let Some(value) = handle.chip.lock().written.get(&handle.line)
+            {
+                cached.push((*pin_id, *value));
+                continue;
+            }
+            match groups
+                .iter_mut()
+                .find(|(chip, _)| Arc::ptr_eq(chip, &handle.chip))
+            {
+                Some((_, lines)) => lines.push((*pin_id, handle.line)),
+                None => groups.push((handle.chip.clone(), vec![(*pin_id, handle.line)])),
+            }
+        }
+
+        Ok((cached, groups))
+    }
+
+    /// Reads a pin, from its last written value when it is push-pull and
+    /// `use_written` is set. Released open-drain/source lines follow their
+    /// pull, so those are always read.
+    async fn read_line(&self, pin_id: PinId, use_written: bool) -> Result<u8, AppError> {
+        let (offset, chip, push_pull) = {
+            let pins = self.pins.read();
+            let handle = pins
+                .get(&pin_id)
+                .ok_or_else(|| {
+                    AppError::InvalidState("pin not configured, set state first".into())
+                })?
+                .read()?;
+            (
+                handle.line,
+                handle.chip.clone(),
+                handle.settings.state == GpioState::PushPull,
+            )
+        };
+        if use_written
+            && push_pull
+            && let Some(value) = chip.lock().written.get(&offset)
+        {
+            return Ok(*value);
+        }
+
+        run_blocking(move || {
+            let value = chip
+                .lock()
+                .request()?
+                .value(offset)
+                .map_err(|e| AppError::Gpio(format!("get value: {e}")))?;
+            Ok(match value {
+                line::Value::InActive => 0,
+                line::Value::Active => 1,
+            })
+        })
+        .await
     }
 }
 
+/// Runs a blocking libgpiod call on the blocking pool so it does not stall
+/// the async workers serving requests.
+async fn run_blocking<T, F>(f: F) -> Result<T, AppError>
+where
+    F: FnOnce() -> Result<T, AppError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| AppError::Gpio(format!("blocking gpio call failed: {e}")))?
+}
+
 impl GpioBackend for LibgpiodBackend {
-    fn get_settings(&self, pin_id: u32) -> Result<PinSettings, AppError> {
+    async fn get_settings(&self, pin_id: PinId) -> Result<PinSettings, AppError> {
         let pins = self.pins.read();
 
         match pins.get(&pin_id) {
             None => Ok(PinSettings::default()),
             Some(handle_lock) => {
-                let handle = handle_lock
-                    .read()
-                    .map_err(|e| AppError::Gpio(format!("lock poisoned: {e}")))?;
+                let handle = handle_lock.read()?;
                 Ok(handle.settings.clone())
             }
         }
     }
 
-    fn set_settings(
+    async fn settings_version(&self, pin_id: PinId) -> Result<u64, AppError> {
+        Ok(self
+            .settings_versions
+            .lock()
+            .get(&pin_id)
+            .copied()
+            .unwrap_or(0))
+    }
+
+    async fn read_hardware_settings(&self, pin_id: PinId) -> Result<PinSettings, AppError> {
+        let (chip, offset, cached) = {
+            let pins = self.pins.read();
+            match pins.get(&pin_id) {
+                None => return Ok(PinSettings::default()),
+                Some(handle_lock) => {
+                    let handle = handle_lock.read()?;
+                    (handle.chip.clone(), handle.line, handle.settings.clone())
+                }
+            }
+        };
+
+        run_blocking(move || {
+            let info = chip
+                .lock()
+                .chip
+                .line_info(offset)
+                .map_err(|e| AppError::Gpio(format!("line info {offset}: {e}")))?;
+            Self::settings_from_info(&info, &cached)
+        })
+        .await
+    }
+
+    async fn set_settings(
         &self,
-        pin_id: u32,
+        pin_id: PinId,
         pin: &PinConfig,
         settings: &PinSettings,
         event_handler: Option<EventHandler>,
     ) -> Result<(), AppError> {
-        let get_listener = |edge: EdgeDetect,
-                            pin_id: u32,
-                            gpiod_handle: &Arc<FairMutex<GpiodHandle>>,
-                            handler: Option<EventHandler>|
-         -> Result<Option<EdgeListener>, AppError> {
-            if edge != EdgeDetect::None
-                && let Some(handler) = handler
-            {
-                let listener = EdgeListener::new(pin_id, gpiod_handle.clone(), handler)?;
-                Ok(Some(listener))
-            } else {
-                Ok(None)
+        settings.validate()?;
+
+        let _update = self.settings_update.lock().await;
+        let (chip, offset) = {
+            let pins = self.pins.read();
+            match pins.get(&pin_id) {
+                Some(handle) => {
+                    let mut handle = handle.write()?;
+                    // stop software pwm so it doesn't race the line change,
+                    // it is restarted below when that fails
+                    handle.pwm = None;
+                    (handle.chip.clone(), handle.line)
+                }
+                None if settings.state == GpioState::Disabled => {
+                    *self.settings_versions.lock().entry(pin_id).or_default() += 1;
+                    return Ok(());
+                }
+                None => (self.chip(&pin.chip)?, pin.line),
             }
         };
 
-        Self::validate_pin_settings(settings)?;
+        let applied = {
+            let chip = chip.clone();
+            let iteration = self.poller.iteration.clone();
+            let settings = settings.clone();
+            let active_low = pin.active_low;
+            let clock = self.event_clock;
+            let retry = self.retry;
+            let consumer = self.consumer.clone();
+            run_blocking(move || {
+                // the poller must not wait on a request released below
+                let _iteration = iteration.lock();
+                let mut lines = chip.lock();
+                if settings.state == GpioState::Disabled {
+                    return lines.apply(pin_id, offset, None, &retry, &consumer);
+                }
+                Self::apply_line_settings(pin_id, active_low, &settings, clock, |ls| {
+                    lines.apply(pin_id, offset, Some(ls), &retry, &consumer)
+                })
+            })
+            .await
+        };
 
-        let pins = self.pins.upgradable_read();
+        let mut pins = self.pins.write();
+        if let Err(e) = applied {
+            // the line kept its old settings, and so do its edges and pwm
+            if let Some(handle) = pins.get(&pin_id) {
+                let mut handle = handle.write()?;
+                handle.pwm = Self::start_pwm(&handle.settings, &handle.chip, handle.line);
+            }
+            return Err(e);
+        }
 
-        // fast path for disabling pin
         if settings.state == GpioState::Disabled {
-            if pins.get(&pin_id).is_some() {
-                let _ = RwLockUpgradableReadGuard::upgrade(pins).remove(&pin_id);
+            self.poller.unregister(pin_id);
+            pins.remove(&pin_id);
+        } else {
+            match event_handler {
+                Some(handler) if settings.edge != EdgeDetect::None => {
+                    self.poller.register(pin_id, &chip, offset, handler)
+                }
+                _ => self.poller.unregister(pin_id),
+            }
+            let pwm = Self::start_pwm(settings, &chip, offset);
+            match pins.get(&pin_id) {
+                Some(handle) => {
+                    let mut handle = handle.write()?;
+                    handle.settings = settings.clone();
+                    handle.pwm = pwm;
+                }
+                None => {
+                    let mut handle = PinHandle::new(chip, offset, settings.clone());
+                    handle.pwm = pwm;
+                    pins.insert(pin_id, RwLock::new(handle));
+                }
             }
-            return Ok(());
         }
+        *self.settings_versions.lock().entry(pin_id).or_default() += 1;
 
-        match pins.get(&pin_id) {
-            Some(handle) => {
-                let mut handle = handle
-                    .write()
-                    .map_err(|e| AppError::Gpio(format!("lock poisoned: {e}")))?;
-
-                // drop listener if disabling edge detection before reconfiguring lines
-                if settings.edge == EdgeDetect::None
-                    && let Some(listener) = handle.listener.take()
-                {
-                    drop(listener);
-                }
+        Ok(())
+    }
 
-                let line_settings = Self::make_line_settings(settings)?;
-                let line_cfg = Self::make_line_config(handle.line, line_settings)?;
+    async fn read_value(&self, pin_id: PinId) -> Result<u8, AppError> {
+        self.read_line(pin_id, true).await
+    }
 
-                handle
-                    .gpiod_handle
-                    .lock()
-                    .request
-                    .reconfigure_lines(&line_cfg)
-                    .map_err(|e| AppError::Gpio(format!("reconfigure lines: {e}")))?;
+    async fn read_hardware_value(&self, pin_id: PinId) -> Result<u8, AppError> {
+        self.read_line(pin_id, false).await
+    }
 
-                if handle.listener.is_none() {
-                    handle.listener =
-                        get_listener(settings.edge, pin_id, &handle.gpiod_handle, event_handler)?;
-                }
+    async fn read_values(&self, pins: &[PinId]) -> Result<Vec<(PinId, u8)>, AppError> {
+        let (mut values, groups) = self.group_reads(pins)?;
+        if groups.is_empty() {
+            return Ok(values);
+        }
 
-                handle.settings = settings.clone();
+        run_blocking(move || {
+            for (chip, lines) in groups {
+                let offsets: Vec<line::Offset> = lines.iter().map(|(_, offset)| *offset).collect();
+                let map = chip
+                    .lock()
+                    .request()?
+                    .values_subset(&offsets)
+                    .map_err(|e| AppError::Gpio(format!("get values: {e}")))?;
+                for (pin_id, offset) in lines {
+                    let value = match map.get(offset) {
+                        Some(line::Value::Active) => 1,
+                        Some(line::Value::InActive) => 0,
+                        None => {
+                            return Err(AppError::Gpio(format!("no value read for pin {pin_id}")));
+                        }
+                    };
+                    values.push((pin_id, value));
+                }
             }
-            None => {
-                // since upgradable read lock is exclusive held by this thread, it safe to pre-allocate
-                // new pin handle without double locking
-                let line_settings = Self::make_line_settings(settings)?;
-                let line_cfg = Self::make_line_config(pin.line, line_settings)?;
-
-                let gpiod_handle =
-                    Arc::new(FairMutex::new(GpiodHandle::new(&pin.chip, &line_cfg)?));
-                let listener = get_listener(settings.edge, pin_id, &gpiod_handle, event_handler)?;
-
-                let handle = RwLock::new(PinHandle::new(
-                    pin.line,
-                    settings.clone(),
-                    gpiod_handle,
-                    listener,
-                ));
+            Ok(values)
+        })
+        .await
+    }
 
-                let mut pins = RwLockUpgradableReadGuard::upgrade(pins);
-                pins.insert(pin_id, handle);
+    async fn write_value(&self, pin_id: PinId, value: u8) -> Result<(), AppError> {
+        let (offset, chip) = {
+            let pins = self.pins.read();
+            let handle = pins
+                .get(&pin_id)
+                .ok_or_else(|| {
+                    AppError::InvalidState("pin not configured, set state first".into())
+                })?
+                .read()?;
+            if !handle.settings.state.is_writable() {
+                return Err(AppError::InvalidState(
+                    "pin must be in output mode to set value".into(),
+                ));
             }
-        }
+            (handle.line, handle.chip.clone())
+        };
+        let line_value = match value {
+            1 => line::Value::Active,
+            _ => line::Value::InActive,
+        };
 
-        Ok(())
+        run_blocking(move || {
+            let mut chip = chip.lock();
+            chip.request_mut()?
+                .set_value(offset, line_value)
+                .map_err(|e| AppError::Gpio(format!("set value: {e}")))?;
+            chip.written.insert(offset, value);
+            Ok(())
+        })
+        .await
     }
 
-    fn read_value(&self, pin_id: u32) -> Result<u8, AppError> {
-        let pins = self.pins.read();
-        let handle_lock = pins
-            .get(&pin_id)
-            .ok_or_else(|| AppError::InvalidState("pin not configured, set state first".into()))?;
-        let handle = handle_lock
-            .read()
-            .map_err(|e| AppError::Gpio(format!("lock poisoned: {e}")))?;
+    async fn set_values(&self, values: &[(PinId, u8)]) -> Result<(), AppError> {
+        let groups = self.group_values(values)?;
 
-        let value = handle
-            .gpiod_handle
-            .lock()
-            .request
-            .value(handle.line)
-            .map_err(|e| AppError::Gpio(format!("get value: {e}")))?;
-        Ok(match value {
-            line::Value::InActive => 0,
-            line::Value::Active => 1,
+        run_blocking(move || {
+            for (chip, lines) in groups {
+                let mut map = line::ValueMap::new();
+                for (offset, value) in &lines {
+                    let value = match value {
+                        1 => line::Value::Active,
+                        _ => line::Value::InActive,
+                    };
+                    map.insert(*offset, value);
+                }
+                let mut chip = chip.lock();
+                chip.request_mut()?
+                    .set_values_subset(map)
+                    .map_err(|e| AppError::Gpio(format!("set values: {e}")))?;
+                chip.written.extend(lines);
+            }
+            Ok(())
         })
+        .await
     }
 
-    fn write_value(&self, pin_id: u32, value: u8) -> Result<(), AppError> {
-        let pins = self.pins.read();
-        let handle_lock = pins
-            .get(&pin_id)
-            .ok_or_else(|| AppError::InvalidState("pin not configured, set state first".into()))?;
-        let handle = handle_lock
-            .read()
-            .map_err(|e| AppError::Gpio(format!("lock poisoned: {e}")))?;
-
-        if !handle.settings.state.is_writable() {
-            return Err(AppError::InvalidState(
-                "pin must be in output mode to set value".into(),
-            ));
-        }
+    async fn probe_chip(&self, chip: &str) -> Result<(), AppError> {
+        self.chip(chip).map(|_| ())
+    }
 
-        let offset = handle.line;
+    fn kind(&self) -> &'static str {
+        "libgpiod"
+    }
 
-        handle
-            .gpiod_handle
-            .lock()
-            .request
-            .set_value(
-                offset,
-                match value {
-                    0 => line::Value::InActive,
-                    1 => line::Value::Active,
-                    _ => line::Value::InActive,
-                },
-            )
-            .map_err(|e| AppError::Gpio(format!("set value: {e}")))?;
-        Ok(())
+    fn library_version(&self) -> Option<&'static str> {
+        libgpiod::libgpiod_version().ok()
+    }
+
+    async fn chip_info(
+        &self,
+        _pins: &FxHashMap<PinId, PinConfig>,
+    ) -> Result<Vec<ChipInfo>, AppError> {
+        run_blocking(|| {
+            let entries = std::fs::read_dir("/dev")
+                .map_err(|e| AppError::Gpio(format!("enumerate chips: {e}")))?;
+
+            let mut paths: Vec<String> = entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with("gpiochip"))
+                .map(|entry| entry.path().to_string_lossy().into_owned())
+                .collect();
+            paths.sort();
+
+            let mut chips = Vec::with_capacity(paths.len());
+            for path in paths {
+                match Self::read_chip_info(&path) {
+                    Ok(info) => chips.push(info),
+                    Err(e) => warn!("skip chip {path}: {e}"),
+                }
+            }
+            Ok(chips)
+        })
+        .await
     }
 }
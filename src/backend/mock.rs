@@ -1,67 +1,246 @@
 use rustc_hash::FxHashMap;
-use std::sync::RwLock;
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-use crate::config::{EdgeDetect, PinConfig};
+use super::pwm::SoftPwm;
+use crate::clock::{Clock, SystemClock};
+use crate::config::{EdgeDetect, PinConfig, SimulateConfig};
 use crate::error::AppError;
-use crate::gpio::{EdgeEvent, EventHandler, GpioBackend, GpioState, PinSettings};
+use crate::gpio::{
+    Bias, ChipInfo, EdgeEvent, EventHandler, GpioBackend, GpioState, LineInfo, PinId, PinSettings,
+};
 
-#[derive(Default)]
 pub struct MockGpioBackend {
-    pins: RwLock<FxHashMap<u32, RwLock<MockPinState>>>, // keyed by pin id
+    pins: RwLock<FxHashMap<PinId, Arc<RwLock<MockPinState>>>>, // keyed by pin id
+    clock: Arc<dyn Clock>,
+    line_reads: AtomicU64,
+    foreign_consumers: RwLock<FxHashMap<PinId, String>>, // lines held by other processes
+}
+
+impl Default for MockGpioBackend {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
 }
 
-#[derive(Clone)]
 struct MockPinState {
     settings: PinSettings,
-    value: u8,
+    value: u8,                 // physical line level
+    written: Option<u8>,       // last logical value written to an output
+    version: u64,              // successful set_settings calls
+    external_pull: Option<u8>, // physical level of a resistor on the board, kept across settings
+    active_low: bool,
     handler: Option<EventHandler>,
-    last_event: Option<Instant>,
+    last_event: Option<u64>,
+    clock: Arc<dyn Clock>,
+    pwm: Option<(SoftPwm, Arc<AtomicU8>)>, // logical level driven by the pwm thread
+    simulation: Option<Simulation>,
+    queued_levels: VecDeque<u8>, // physical levels taken by the next line reads
+}
+
+/// Drives a pin through a pattern of logical levels from a thread, as if
+/// the line was toggled externally, so edges reach the event pipeline.
+struct Simulation {
+    cancel: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Simulation {
+    fn new(pin_id: PinId, pin: Weak<RwLock<MockPinState>>, config: SimulateConfig) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancel_flag = cancel.clone();
+        let period = Duration::from_millis(config.period_ms);
+
+        let handle = thread::spawn(move || {
+            for level in config.pattern.iter().cycle() {
+                thread::park_timeout(period);
+                if cancel_flag.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Some(pin) = pin.upgrade() else {
+                    break;
+                };
+                // the pin may be reconfigured concurrently, skip this tick then
+                if let Ok(mut pin) = pin.try_write() {
+                    pin.write(pin_id, *level);
+                }
+            }
+        });
+
+        Self {
+            cancel,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Simulation {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            // the thread itself may drop the last reference to its pin
+            if handle.thread().id() != thread::current().id() {
+                handle.thread().unpark();
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+impl MockPinState {
+    /// Writes `value` as the output would drive it. Open-drain lines only
+    /// drive low and open-source lines only drive high, otherwise the line
+    /// is released to the external pull, the bias, or stays where it was.
+    fn write_output(&mut self, pin_id: PinId, value: u8) {
+        let physical = value ^ self.active_low as u8;
+        let level = match (self.settings.state, physical) {
+            (GpioState::OpenDrain, 1) | (GpioState::OpenSource, 0) => self.released_level(),
+            _ => physical,
+        };
+        self.write(pin_id, level ^ self.active_low as u8);
+        self.written = Some(value);
+    }
+
+    fn released_level(&self) -> u8 {
+        match (self.external_pull, self.settings.bias) {
+            (Some(pull), _) => pull,
+            (None, Some(Bias::PullUp)) => 1,
+            (None, Some(Bias::PullDown)) => 0,
+            (None, _) => self.value,
+        }
+    }
+
+    fn write(&mut self, pin_id: PinId, value: u8) {
+        // edges are observed on the logical level, like libgpiod with active-low set
+        let old = self.value ^ self.active_low as u8;
+        self.value = value ^ self.active_low as u8;
+
+        if let Some(edge_kind) = match (old, value) {
+            (0, 1) => Some(EdgeDetect::Rising),
+            (1, 0) => Some(EdgeDetect::Falling),
+            _ => None,
+        } && edge_matches(self.settings.edge, edge_kind)
+        {
+            let now = self.clock.now_ms();
+            let debounce = if self.settings.software_debounce {
+                0
+            } else {
+                self.settings.debounce_ms
+            };
+            let allow = self
+                .last_event
+                .map(|t| now.saturating_sub(t) >= debounce)
+                .unwrap_or(true);
+            if allow {
+                self.last_event = Some(now);
+                if let Some(h) = &self.handler {
+                    h.dispatch(EdgeEvent {
+                        pin_id,
+                        edge: edge_kind,
+                        timestamp_ms: now,
+                        seq: 0,
+                        count: 1,
+                    });
+                }
+            }
+        }
+    }
 }
 
 impl GpioBackend for MockGpioBackend {
-    fn get_settings(&self, pin_id: u32) -> Result<PinSettings, AppError> {
-        let pins = self
-            .pins
-            .read()
-            .map_err(|e| AppError::Gpio(format!("lock poisoned: {e}")))?;
+    async fn get_settings(&self, pin_id: PinId) -> Result<PinSettings, AppError> {
+        let pins = self.pins.read()?;
 
         if let Some(pin_lock) = pins.get(&pin_id) {
-            let pin = pin_lock
-                .read()
-                .map_err(|e| AppError::Gpio(format!("lock poisoned: {e}")))?;
+            let pin = pin_lock.read()?;
             Ok(pin.settings.clone())
         } else {
             Ok(PinSettings::default())
         }
     }
 
-    fn set_settings(
+    async fn settings_version(&self, pin_id: PinId) -> Result<u64, AppError> {
+        let pins = self.pins.read()?;
+
+        match pins.get(&pin_id) {
+            Some(pin_lock) => Ok(pin_lock.read()?.version),
+            None => Ok(0),
+        }
+    }
+
+    async fn read_hardware_settings(&self, pin_id: PinId) -> Result<PinSettings, AppError> {
+        // nothing else can reconfigure a mock line
+        self.get_settings(pin_id).await
+    }
+
+    async fn set_settings(
         &self,
-        pin_id: u32,
-        _pin: &PinConfig,
+        pin_id: PinId,
+        pin_config: &PinConfig,
         settings: &PinSettings,
         event_handler: Option<EventHandler>,
     ) -> Result<(), AppError> {
-        let mut pins = self
-            .pins
-            .write()
-            .map_err(|e| AppError::Gpio(format!("lock poisoned: {e}")))?;
+        settings.validate()?;
+        let mut pins = self.pins.write()?;
+
+        let requested = pins
+            .get(&pin_id)
+            .map(|pin| {
+                pin.read()
+                    .map(|pin| pin.settings.state != GpioState::Disabled)
+            })
+            .transpose()?
+            .unwrap_or(false);
+        if !requested
+            && settings.state != GpioState::Disabled
+            && let Some(consumer) = self.foreign_consumers.read()?.get(&pin_id)
+        {
+            return Err(AppError::LineBusy {
+                pin_id,
+                consumer: consumer.clone(),
+            });
+        }
 
         let entry = pins.entry(pin_id).or_insert_with(|| {
-            RwLock::new(MockPinState {
+            Arc::new(RwLock::new(MockPinState {
                 settings: PinSettings::default(),
                 value: 0,
+                written: None,
+                version: 0,
+                external_pull: None,
+                active_low: false,
                 handler: None,
                 last_event: None,
-            })
+                clock: self.clock.clone(),
+                pwm: None,
+                simulation: None,
+                queued_levels: VecDeque::new(),
+            }))
         });
+        let weak = Arc::downgrade(entry);
 
-        let mut pin = entry
-            .write()
-            .map_err(|e| AppError::Gpio(format!("lock poisoned: {e}")))?;
+        let mut pin = entry.write()?;
 
+        // a fresh line request starts logically low
+        if pin.settings.state == GpioState::Disabled {
+            pin.value = pin_config.active_low as u8;
+        }
         pin.settings = settings.clone();
+        pin.written = None;
+        pin.queued_levels.clear();
+        pin.active_low = pin_config.active_low;
+        pin.pwm = match (settings.state, &settings.pwm) {
+            (GpioState::Pwm, Some(pwm)) => {
+                let level = Arc::new(AtomicU8::new(0));
+                let pwm_level = level.clone();
+                let pwm = SoftPwm::new(pwm, move |v| pwm_level.store(v, Ordering::Relaxed));
+                Some((pwm, level))
+            }
+            _ => None,
+        };
         if settings.state == GpioState::Disabled {
             pin.value = 0;
             pin.handler = None;
@@ -71,76 +250,301 @@ impl GpioBackend for MockGpioBackend {
         } else {
             pin.handler = None;
         }
+        pin.simulation = match &pin_config.simulate {
+            Some(simulate) if settings.state != GpioState::Disabled => {
+                Some(Simulation::new(pin_id, weak, simulate.clone()))
+            }
+            _ => None,
+        };
+        pin.version += 1;
 
         Ok(())
     }
 
-    fn read_value(&self, pin_id: u32) -> Result<u8, AppError> {
-        let mut pins = self
-            .pins
-            .write()
-            .map_err(|e| AppError::Gpio(format!("lock poisoned: {e}")))?;
+    async fn read_value(&self, pin_id: PinId) -> Result<u8, AppError> {
+        self.read_line(pin_id, true)
+    }
+
+    async fn read_hardware_value(&self, pin_id: PinId) -> Result<u8, AppError> {
+        self.read_line(pin_id, false)
+    }
+
+    async fn read_values(&self, pins: &[PinId]) -> Result<Vec<(PinId, u8)>, AppError> {
+        let mut values = Vec::with_capacity(pins.len());
+        for pin_id in pins {
+            values.push((*pin_id, self.read_value(*pin_id).await?));
+        }
+        Ok(values)
+    }
+
+    async fn write_value(&self, pin_id: PinId, value: u8) -> Result<(), AppError> {
+        let mut pins = self.pins.write()?;
         let entry = pins
             .get_mut(&pin_id)
             .ok_or_else(|| AppError::InvalidState("pin not configured, set state first".into()))?;
-        let pin = entry
-            .read()
-            .map_err(|e| AppError::Gpio(format!("lock poisoned: {e}")))?;
+        let mut pin = entry.write()?;
+
+        if !pin.settings.state.is_writable() {
+            return Err(AppError::InvalidState(
+                "pin must be in output mode to set value".into(),
+            ));
+        }
+
+        pin.write_output(pin_id, value);
+        Ok(())
+    }
+
+    async fn set_values(&self, values: &[(PinId, u8)]) -> Result<(), AppError> {
+        // hold the map write lock so no reader observes a partial update
+        let pins = self.pins.write()?;
+
+        for (pin_id, _) in values {
+            let pin = pins
+                .get(pin_id)
+                .ok_or_else(|| {
+                    AppError::InvalidState(format!("pin {pin_id} not configured, set state first"))
+                })?
+                .read()?;
+            if !pin.settings.state.is_writable() {
+                return Err(AppError::InvalidState(format!(
+                    "pin {pin_id} must be in output mode to set value"
+                )));
+            }
+        }
+
+        for (pin_id, value) in values {
+            pins[pin_id].write()?.write_output(*pin_id, *value);
+        }
+        Ok(())
+    }
+
+    async fn probe_chip(&self, _chip: &str) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    fn kind(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn chip_info(
+        &self,
+        pins: &FxHashMap<PinId, PinConfig>,
+    ) -> Result<Vec<ChipInfo>, AppError> {
+        let active = self.pins.read()?;
+
+        // synthesize one chip per configured path, sized to fit its highest line
+        let mut chips: BTreeMap<&str, Vec<(u32, &PinConfig, bool)>> = BTreeMap::new();
+        for (id, pin) in pins {
+            chips.entry(pin.chip.as_str()).or_default().push((
+                pin.line,
+                pin,
+                active.contains_key(id),
+            ));
+        }
+
+        Ok(chips
+            .into_iter()
+            .enumerate()
+            .map(|(index, (path, mut lines))| {
+                lines.sort_by_key(|(line, _, _)| *line);
+                ChipInfo {
+                    path: path.to_string(),
+                    name: format!("gpiochip{index}"),
+                    label: "mock".into(),
+                    num_lines: lines.last().map_or(0, |(line, _, _)| line + 1),
+                    lines: lines
+                        .into_iter()
+                        .map(|(offset, pin, used)| LineInfo {
+                            offset,
+                            name: Some(pin.name.clone()),
+                            consumer: used.then(|| env!("CARGO_PKG_NAME").to_string()),
+                            used,
+                        })
+                        .collect(),
+                }
+            })
+            .collect())
+    }
+}
+
+impl MockGpioBackend {
+    /// Stamps edges produced by value transitions with `clock`.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            pins: RwLock::default(),
+            clock,
+            line_reads: AtomicU64::new(0),
+            foreign_consumers: RwLock::default(),
+        }
+    }
+
+    /// How many reads reached a line rather than a written value.
+    pub fn line_reads(&self) -> u64 {
+        self.line_reads.load(Ordering::Relaxed)
+    }
+
+    fn read_line(&self, pin_id: PinId, use_written: bool) -> Result<u8, AppError> {
+        let pins = self.pins.read()?;
+        let mut pin = pins
+            .get(&pin_id)
+            .ok_or_else(|| AppError::InvalidState("pin not configured, set state first".into()))?
+            .write()?;
 
         if pin.settings.state == GpioState::Disabled {
             return Err(AppError::InvalidState(
                 "pin is disabled and cannot be read".into(),
             ));
         }
-        Ok(pin.value)
+        if let Some(level) = pin.queued_levels.pop_front() {
+            let active_low = pin.active_low as u8;
+            pin.write(pin_id, level ^ active_low);
+        }
+        if use_written
+            && pin.settings.state == GpioState::PushPull
+            && let Some(value) = pin.written
+        {
+            return Ok(value);
+        }
+        self.line_reads.fetch_add(1, Ordering::Relaxed);
+        if let Some((_, level)) = &pin.pwm {
+            return Ok(level.load(Ordering::Relaxed));
+        }
+        Ok(pin.value ^ pin.active_low as u8)
     }
 
-    fn write_value(&self, pin_id: u32, value: u8) -> Result<(), AppError> {
-        let mut pins = self
-            .pins
-            .write()
-            .map_err(|e| AppError::Gpio(format!("lock poisoned: {e}")))?;
+    /// Toggles a configured pin every `period_ms` until its settings change,
+    /// dispatching edges like an externally driven input would.
+    pub fn start_simulation(&self, pin_id: PinId, period_ms: u64) -> Result<(), AppError> {
+        let pins = self.pins.read()?;
         let entry = pins
-            .get_mut(&pin_id)
+            .get(&pin_id)
             .ok_or_else(|| AppError::InvalidState("pin not configured, set state first".into()))?;
-        let mut pin = entry
-            .write()
-            .map_err(|e| AppError::Gpio(format!("lock poisoned: {e}")))?;
+        let config = SimulateConfig {
+            period_ms,
+            pattern: vec![1, 0],
+        };
+        entry.write()?.simulation = Some(Simulation::new(pin_id, Arc::downgrade(entry), config));
 
-        if !pin.settings.state.is_writable() {
+        Ok(())
+    }
+
+    /// Sets the physical level of an input pin as an external signal
+    /// toggling it would. Edges follow the pin's `edge` and `debounce_ms`
+    /// like on a real line; output, pwm and disabled pins are rejected.
+    pub fn set_input_level(&self, pin_id: PinId, level: u8) -> Result<(), AppError> {
+        if level > 1 {
+            return Err(AppError::InvalidValue("input level must be 0 or 1".into()));
+        }
+        let pins = self.pins.read()?;
+        let mut pin = pins
+            .get(&pin_id)
+            .ok_or_else(|| AppError::InvalidState("pin not configured, set state first".into()))?
+            .write()?;
+        if !pin.settings.state.is_edge_detectable() {
             return Err(AppError::InvalidState(
-                "pin must be in output mode to set value".into(),
+                "pin must be in an input state to set its level".into(),
             ));
         }
+        let active_low = pin.active_low as u8;
+        pin.write(pin_id, level ^ active_low);
 
-        let old = pin.value;
-        pin.value = value;
+        Ok(())
+    }
 
-        if let Some(edge_kind) = match (old, value) {
-            (0, 1) => Some(EdgeDetect::Rising),
-            (1, 0) => Some(EdgeDetect::Falling),
-            _ => None,
-        } && edge_matches(pin.settings.edge, edge_kind)
+    /// Queues physical levels an input pin takes, one before each of the
+    /// next reads, as a noisy signal would. Checked like `set_input_level`.
+    pub fn queue_input_levels(&self, pin_id: PinId, levels: &[u8]) -> Result<(), AppError> {
+        if levels.iter().any(|level| *level > 1) {
+            return Err(AppError::InvalidValue("input level must be 0 or 1".into()));
+        }
+        let pins = self.pins.read()?;
+        let mut pin = pins
+            .get(&pin_id)
+            .ok_or_else(|| AppError::InvalidState("pin not configured, set state first".into()))?
+            .write()?;
+        if !pin.settings.state.is_edge_detectable() {
+            return Err(AppError::InvalidState(
+                "pin must be in an input state to set its level".into(),
+            ));
+        }
+        pin.queued_levels.extend(levels);
+
+        Ok(())
+    }
+
+    /// Dispatches an edge through the pin's registered handler without a
+    /// value transition, so tests can drive exact edge sequences.
+    pub fn inject_event(
+        &self,
+        pin_id: PinId,
+        edge: EdgeDetect,
+        timestamp_ms: u64,
+    ) -> Result<(), AppError> {
+        let pins = self.pins.read()?;
+        let pin = pins
+            .get(&pin_id)
+            .ok_or_else(|| AppError::InvalidState("pin not configured, set state first".into()))?
+            .read()?;
+        let handler = pin.handler.as_ref().ok_or_else(|| {
+            AppError::InvalidState("edge detection is not enabled on this pin".into())
+        })?;
+        handler.dispatch(EdgeEvent {
+            pin_id,
+            edge,
+            timestamp_ms,
+            seq: 0,
+            count: 1,
+        });
+
+        Ok(())
+    }
+
+    /// Simulates another process holding the line of `pin_id` as
+    /// `consumer`, or releasing it with `None`. Requesting the line while it
+    /// is held fails as busy.
+    pub fn set_foreign_consumer(
+        &self,
+        pin_id: PinId,
+        consumer: Option<&str>,
+    ) -> Result<(), AppError> {
+        let mut consumers = self.foreign_consumers.write()?;
+        match consumer {
+            Some(consumer) => consumers.insert(pin_id, consumer.to_string()),
+            None => consumers.remove(&pin_id),
+        };
+
+        Ok(())
+    }
+
+    /// Simulates a pull resistor on the board pulling the line to the
+    /// physical `level`, or removes it with `None`. A released open-drain
+    /// or open-source line follows it at once.
+    pub fn set_external_pull(&self, pin_id: PinId, level: Option<u8>) -> Result<(), AppError> {
+        if level.is_some_and(|l| l > 1) {
+            return Err(AppError::InvalidValue("pull level must be 0 or 1".into()));
+        }
+        let pins = self.pins.read()?;
+        let mut pin = pins
+            .get(&pin_id)
+            .ok_or_else(|| AppError::InvalidState("pin not configured, set state first".into()))?
+            .write()?;
+        pin.external_pull = level;
+        if matches!(
+            pin.settings.state,
+            GpioState::OpenDrain | GpioState::OpenSource
+        ) && let Some(value) = pin.written
         {
-            let now = Instant::now();
-            let debounce = pin.settings.debounce_ms;
-            let allow = pin
-                .last_event
-                .map(|t| now.duration_since(t).as_millis() >= debounce as u128)
-                .unwrap_or(true);
-            if allow {
-                pin.last_event = Some(now);
-                if let Some(h) = &pin.handler {
-                    h.dispatch(EdgeEvent {
-                        pin_id,
-                        edge: edge_kind,
-                        timestamp_ms: epoch_millis(),
-                    });
-                }
-            }
+            pin.write_output(pin_id, value);
         }
+
         Ok(())
     }
+
+    pub fn physical_value(&self, pin_id: PinId) -> Option<u8> {
+        let pins = self.pins.read().ok()?;
+        let pin = pins.get(&pin_id)?.read().ok()?;
+        Some(pin.value)
+    }
 }
 
 fn edge_matches(configured: EdgeDetect, observed: EdgeDetect) -> bool {
@@ -151,10 +555,3 @@ fn edge_matches(configured: EdgeDetect, observed: EdgeDetect) -> bool {
         EdgeDetect::Both => matches!(observed, EdgeDetect::Rising | EdgeDetect::Falling),
     }
 }
-
-fn epoch_millis() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_millis() as u64)
-        .unwrap_or(0)
-}
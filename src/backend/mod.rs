@@ -1,7 +1,10 @@
 #[cfg(feature = "hardware-gpio")]
 pub(crate) mod libgpiod;
 pub(crate) mod mock;
+pub(crate) mod pwm;
+pub(crate) mod retry;
 
 #[cfg(feature = "hardware-gpio")]
 pub use libgpiod::LibgpiodBackend;
 pub use mock::MockGpioBackend;
+pub use retry::RetryPolicy;
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::gpio::PwmConfig;
+
+// Software PWM driven by a dedicated thread, timing is subject to scheduler
+// jitter so it suits LED dimming rather than precise servo or motor control.
+pub(crate) struct SoftPwm {
+    cancel: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl SoftPwm {
+    pub(crate) fn new<F>(config: &PwmConfig, mut set_level: F) -> Self
+    where
+        F: FnMut(u8) + Send + 'static,
+    {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancel_flag = cancel.clone();
+
+        let period = Duration::from_secs_f64(1.0 / config.frequency_hz);
+        let high = period.mul_f64(config.duty_cycle);
+        let low = period.saturating_sub(high);
+
+        let handle = thread::spawn(move || {
+            while !cancel_flag.load(Ordering::Relaxed) {
+                if !high.is_zero() {
+                    set_level(1);
+                    thread::park_timeout(high);
+                }
+                if cancel_flag.load(Ordering::Relaxed) {
+                    break;
+                }
+                if !low.is_zero() {
+                    set_level(0);
+                    thread::park_timeout(low);
+                }
+            }
+        });
+
+        Self {
+            cancel,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for SoftPwm {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
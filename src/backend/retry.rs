@@ -0,0 +1,52 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use log::warn;
+
+/// How often a backend call failing with a transient error is tried again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total tries, 1 never retries.
+    pub attempts: u32,
+    /// Wait before the first retry, doubled for each further one.
+    pub delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            delay: Duration::from_millis(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Runs `op` until it succeeds, fails with an error `is_transient`
+    /// rejects, or the attempts are used up, returning its last result.
+    /// Blocks the calling thread while backing off, so async callers run it
+    /// on the blocking pool.
+    pub fn run<T, E: Display>(
+        &self,
+        what: &str,
+        is_transient: impl Fn(&E) -> bool,
+        mut op: impl FnMut() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut attempt = 1;
+        let mut delay = self.delay;
+        loop {
+            match op() {
+                Err(e) if attempt < self.attempts && is_transient(&e) => {
+                    warn!(
+                        "{what} failed, retrying in {delay:?} ({attempt}/{}): {e}",
+                        self.attempts
+                    );
+                    std::thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
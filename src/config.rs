@@ -1,28 +1,207 @@
-use std::{collections::HashSet, fs, path::Path};
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::{collections::HashSet, env, fmt, fs, path::Path, str::FromStr, time::Duration};
 
+use actix_cors::Cors;
+use actix_web::http::Uri;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 
 use crate::error::AppError;
+use crate::gpio::PinId;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Longest consumer name the kernel keeps whole, GPIO_MAX_NAME_SIZE - 1.
+const MAX_CONSUMER_LABEL_LEN: usize = 31;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct HttpConfig {
     pub unix_socket: Option<String>,
     pub unix_socket_mode: Option<String>,
     pub host: Option<String>,
-    pub path: String,
+    /// Base paths the api is mounted under, a single string or a list.
+    #[serde(deserialize_with = "one_or_many")]
+    pub path: Vec<String>,
     pub timeout: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Event websockets without client activity for this long are closed,
+    /// 0 keeps them open.
+    #[serde(default)]
+    pub ws_idle_timeout_ms: u64,
+    /// Largest request body the api buffers, bigger ones get 413.
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// Wraps successful api answers as `{"data":..,"error":null}`, error
+    /// bodies then carry `"data":null`.
+    #[serde(default)]
+    pub response_envelope: bool,
+}
+
+fn default_max_body_bytes() -> usize {
+    256 * 1024
+}
+
+fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(path) => vec![path],
+        OneOrMany::Many(paths) => paths,
+    })
+}
+
+impl Default for HttpConfig {
+    /// Serves `/api/v1` on `localhost:8080` with a 30 second timeout.
+    fn default() -> Self {
+        Self {
+            unix_socket: None,
+            unix_socket_mode: None,
+            host: Some("localhost:8080".into()),
+            path: vec!["/api/v1".into()],
+            timeout: 30,
+            tls: None,
+            cors: None,
+            rate_limit: None,
+            ws_idle_timeout_ms: 0,
+            max_body_bytes: default_max_body_bytes(),
+            response_envelope: false,
+        }
+    }
+}
+
+/// Token bucket applied to write routes, per api key when auth is enabled
+/// and per client address otherwise.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub struct RateLimitConfig {
+    pub writes_per_second: f64,
+    pub burst: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct CorsConfig {
+    /// Origins such as `https://dashboard.local`, `*` allows any origin.
+    pub allowed_origins: Vec<String>,
+    #[serde(default)]
+    pub allow_credentials: bool,
+}
+
+impl CorsConfig {
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|o| o == "*")
+    }
+
+    pub fn middleware(&self) -> Cors {
+        let cors = if self.allows_any_origin() {
+            Cors::default().allow_any_origin()
+        } else {
+            self.allowed_origins
+                .iter()
+                .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+        };
+        let cors = cors
+            .allow_any_method()
+            .allow_any_header()
+            .expose_any_header();
+
+        if self.allow_credentials {
+            cors.supports_credentials()
+        } else {
+            cors
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+}
+
+#[cfg(feature = "tls")]
+impl TlsConfig {
+    pub fn server_config(&self) -> Result<rustls::ServerConfig, AppError> {
+        use rustls::pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject};
+
+        let certs = CertificateDer::pem_file_iter(&self.cert_path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| AppError::Config(format!("failed to load tls cert: {e}")))?;
+        let key = PrivateKeyDer::from_pem_file(&self.key_path)
+            .map_err(|e| AppError::Config(format!("failed to load tls key: {e}")))?;
+
+        rustls::ServerConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|e| AppError::Config(format!("invalid tls config: {e}")))
+    }
 }
 
 impl HttpConfig {
-    pub fn socket_mode(&self) -> Option<u32> {
-        self.unix_socket_mode.as_deref().map(|s| {
-            u32::from_str_radix(s.strip_prefix("0o").or(s.strip_prefix('0')).unwrap_or(s), 8)
-                .unwrap_or_else(|_| panic!("invalid unix_socket_mode: {s}"))
+    /// `timeout` is in seconds, 0 disables it.
+    pub fn request_timeout(&self) -> Option<Duration> {
+        (self.timeout > 0).then(|| Duration::from_secs(self.timeout))
+    }
+
+    pub fn ws_idle_timeout(&self) -> Option<Duration> {
+        (self.ws_idle_timeout_ms > 0).then(|| Duration::from_millis(self.ws_idle_timeout_ms))
+    }
+
+    /// `unix_socket_mode` as permission bits, written in octal like "0660".
+    pub fn socket_mode(&self) -> Result<Option<u32>, AppError> {
+        self.unix_socket_mode
+            .as_deref()
+            .map(|s| {
+                let digits = s.strip_prefix("0o").or(s.strip_prefix('0')).unwrap_or(s);
+                u32::from_str_radix(digits, 8)
+                    .ok()
+                    .filter(|mode| *mode <= 0o7777)
+                    .ok_or_else(|| AppError::Config(format!("invalid unix_socket_mode: {s}")))
+            })
+            .transpose()
+    }
+
+    /// Applies `unix_socket_mode` to the bound socket, if both are set.
+    #[cfg(unix)]
+    pub fn apply_socket_mode(&self) -> Result<(), AppError> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (Some(path), Some(mode)) = (&self.unix_socket, self.socket_mode()?) else {
+            return Ok(());
+        };
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).map_err(|e| {
+            AppError::Config(format!(
+                "failed to set unix socket mode {mode:o} on {path}: {e}"
+            ))
         })
     }
 }
 
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+pub struct AuthConfig {
+    /// Keys accepted in the `X-API-Key` header, auth is off when empty.
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+}
+
+impl AuthConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty()
+    }
+}
+
 #[derive(Debug, Hash, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "kebab-case")]
 pub enum GpioCapability {
@@ -34,6 +213,53 @@ pub enum GpioCapability {
     Floating,
     PullUp,
     PullDown,
+    Pwm,
+}
+
+impl GpioCapability {
+    const ALL: [GpioCapability; 9] = [
+        GpioCapability::Error,
+        GpioCapability::Disabled,
+        GpioCapability::PushPull,
+        GpioCapability::OpenDrain,
+        GpioCapability::OpenSource,
+        GpioCapability::Floating,
+        GpioCapability::PullUp,
+        GpioCapability::PullDown,
+        GpioCapability::Pwm,
+    ];
+
+    /// The kebab-case name used on the wire.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GpioCapability::Error => "error",
+            GpioCapability::Disabled => "disabled",
+            GpioCapability::PushPull => "push-pull",
+            GpioCapability::OpenDrain => "open-drain",
+            GpioCapability::OpenSource => "open-source",
+            GpioCapability::Floating => "floating",
+            GpioCapability::PullUp => "pull-up",
+            GpioCapability::PullDown => "pull-down",
+            GpioCapability::Pwm => "pwm",
+        }
+    }
+}
+
+impl fmt::Display for GpioCapability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for GpioCapability {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.as_str() == s)
+            .ok_or_else(|| AppError::InvalidValue(format!("invalid capability: {s}")))
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
@@ -47,27 +273,536 @@ pub enum EdgeDetect {
     Both,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+impl EdgeDetect {
+    /// The kebab-case name used on the wire.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdgeDetect::None => "none",
+            EdgeDetect::Rising => "rising",
+            EdgeDetect::Falling => "falling",
+            EdgeDetect::Both => "both",
+        }
+    }
+}
+
+impl fmt::Display for EdgeDetect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EdgeDetect {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            EdgeDetect::None,
+            EdgeDetect::Rising,
+            EdgeDetect::Falling,
+            EdgeDetect::Both,
+        ]
+        .into_iter()
+        .find(|e| e.as_str() == s)
+        .ok_or_else(|| AppError::InvalidValue(format!("invalid edge: {s}")))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
 pub struct PinConfig {
     pub name: String,
     pub chip: String,
     pub line: u32,
     pub capabilities: HashSet<GpioCapability>,
+    #[serde(default)]
+    pub active_low: bool,
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_state: Option<GpioCapability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initial_value: Option<u8>,
+    #[serde(default, alias = "safe_state", skip_serializing_if = "Option::is_none")]
+    pub safe_value: Option<u8>,
+    /// Mock backend only, drives the line through `pattern` while enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simulate: Option<SimulateConfig>,
+    /// Events kept for this pin instead of `event_history_capacity`, 0 keeps
+    /// none while still broadcasting them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_capacity: Option<usize>,
+    /// Free-form labels for grouping pins, the line ignores them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl PinConfig {
+    /// Whether the pins differ beyond their tags, so the line needs to be
+    /// requested again.
+    pub(crate) fn line_differs(&self, other: &PinConfig) -> bool {
+        let untagged = |pin: &PinConfig| PinConfig {
+            tags: Vec::new(),
+            ..pin.clone()
+        };
+        untagged(self) != untagged(other)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct SimulateConfig {
+    pub period_ms: u64,
+    /// Logical levels applied one per period, cycling.
+    #[serde(default = "default_simulate_pattern")]
+    pub pattern: Vec<u8>,
+}
+
+fn default_simulate_pattern() -> Vec<u8> {
+    vec![1, 0]
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Pins whose events are delivered, all pins when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin_filter: Option<Vec<PinId>>,
+    /// Edge whose events are delivered, all edges when unset or `none`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edge: Option<EdgeDetect>,
+}
+
+impl WebhookConfig {
+    pub fn matches(&self, pin_id: PinId, edge: EdgeDetect) -> bool {
+        let pin_matches = self
+            .pin_filter
+            .as_ref()
+            .is_none_or(|pins| pins.contains(&pin_id));
+        let edge_matches = match self.edge {
+            None | Some(EdgeDetect::None) | Some(EdgeDetect::Both) => true,
+            Some(filter) => filter == edge,
+        };
+
+        pin_matches && edge_matches
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct MqttConfig {
+    /// Broker as `host:port`, the port defaults to 1883.
+    pub broker: String,
+    /// Events go to `{base_topic}/{pin_id}/event`, writes are taken from
+    /// `{base_topic}/{pin_id}/set`.
+    pub base_topic: String,
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+}
+
+fn default_mqtt_client_id() -> String {
+    "gmgr".into()
+}
+
+impl MqttConfig {
+    pub fn broker_addr(&self) -> Result<(&str, u16), AppError> {
+        match self.broker.rsplit_once(':') {
+            Some((host, port)) => port
+                .parse()
+                .map(|port| (host, port))
+                .map_err(|_| AppError::Config(format!("invalid mqtt broker: {}", self.broker))),
+            None => Ok((self.broker.as_str(), 1883)),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// What event websockets do once a client falls further behind than
+/// `broadcast_capacity` events.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LagPolicy {
+    /// Skip the missed events and tell the client how many were lost.
+    #[default]
+    DropOldest,
+    /// Close the socket so the client reconnects and resyncs its state.
+    Disconnect,
+}
+
+/// Clock the libgpiod backend asks the kernel to stamp edge events with.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EventClockSource {
+    /// Wall clock time, comparable across hosts but steps with clock changes.
+    #[default]
+    Realtime,
+    /// Time since boot, never steps.
+    Monotonic,
+    /// Hardware timestamp engine, only on kernels and chips that have one.
+    Hte,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct AppConfig {
     pub http: HttpConfig,
-    pub gpios: FxHashMap<u32, PinConfig>,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    pub gpios: FxHashMap<PinId, PinConfig>,
     pub broadcast_capacity: usize,
+    #[serde(default)]
+    pub lag_policy: LagPolicy,
     pub event_history_capacity: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_log_path: Option<String>,
+    /// JSON lines file recording every value write and settings change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audit_log_path: Option<String>,
+    #[serde(default = "default_libgpiod_event_buffer")]
+    pub libgpiod_event_buffer: usize,
+    #[serde(default = "default_libgpiod_wait_timeout_ms")]
+    pub libgpiod_wait_timeout_ms: u64,
+    #[serde(default)]
+    pub libgpiod_event_clock: EventClockSource,
+    /// Tries of a line request or reconfiguration failing transiently.
+    #[serde(default = "default_libgpiod_retry_attempts")]
+    pub libgpiod_retry_attempts: u32,
+    #[serde(default = "default_libgpiod_retry_delay_ms")]
+    pub libgpiod_retry_delay_ms: u64,
+    /// Consumer name the lines are requested under, shown by `gpioinfo`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub consumer_label: Option<String>,
+    /// How often websocket clients are pinged, 0 disables pings.
+    #[serde(default = "default_ws_ping_interval_ms")]
+    pub ws_ping_interval_ms: u64,
+    /// How long a pinged websocket client has to answer before it is closed.
+    #[serde(default = "default_ws_pong_timeout_ms")]
+    pub ws_pong_timeout_ms: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mqtt: Option<MqttConfig>,
+}
+
+fn default_libgpiod_event_buffer() -> usize {
+    64
+}
+
+fn default_libgpiod_wait_timeout_ms() -> u64 {
+    10
+}
+
+fn default_libgpiod_retry_attempts() -> u32 {
+    3
+}
+
+fn default_libgpiod_retry_delay_ms() -> u64 {
+    10
+}
+
+fn default_ws_ping_interval_ms() -> u64 {
+    30_000
+}
+
+fn default_ws_pong_timeout_ms() -> u64 {
+    10_000
 }
 
 impl AppConfig {
+    pub fn builder() -> AppConfigBuilder {
+        AppConfigBuilder::default()
+    }
+
+    /// Ping interval and pong timeout for websocket clients, if pings are on.
+    pub fn ws_heartbeat(&self) -> Option<(Duration, Duration)> {
+        (self.ws_ping_interval_ms > 0).then(|| {
+            (
+                Duration::from_millis(self.ws_ping_interval_ms),
+                Duration::from_millis(self.ws_pong_timeout_ms),
+            )
+        })
+    }
+
+    /// The configured `consumer_label`, or the crate name.
+    pub fn consumer_label(&self) -> &str {
+        self.consumer_label
+            .as_deref()
+            .unwrap_or(env!("CARGO_PKG_NAME"))
+    }
+
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, AppError> {
         let contents = fs::read_to_string(&path)
             .map_err(|e| AppError::Config(format!("failed to read config: {e}")))?;
-        serde_json::from_str(&contents)
-            .map_err(|e| AppError::Config(format!("invalid config json: {e}")))
+
+        let config: Self = match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_str(&contents)
+                .map_err(|e| AppError::Config(format!("invalid config yaml: {e}"))),
+            Some("toml") => toml::from_str(&contents)
+                .map_err(|e| AppError::Config(format!("invalid config toml: {e}"))),
+            _ => serde_json::from_str(&contents)
+                .map_err(|e| AppError::Config(format!("invalid config json: {e}"))),
+        }?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Checks the whole config, reporting every problem found at once.
+    pub fn validate(&self) -> Result<(), AppError> {
+        let mut errors = Vec::new();
+        let mut lines: FxHashMap<(&str, u32), Vec<u32>> = FxHashMap::default();
+        let mut pins: Vec<_> = self.gpios.iter().collect();
+        pins.sort_unstable_by_key(|(id, _)| **id);
+        for (id, pin) in pins {
+            if pin.capabilities.is_empty() {
+                errors.push(format!("pin {id} has no capabilities"));
+            }
+            if let Some(simulate) = &pin.simulate
+                && (simulate.period_ms == 0
+                    || simulate.pattern.is_empty()
+                    || simulate.pattern.iter().any(|v| *v > 1))
+            {
+                errors.push(format!(
+                    "pin {id} simulate needs a non-zero period_ms and a pattern of 0 and 1"
+                ));
+            }
+            if pin.safe_value.is_some_and(|v| v > 1) {
+                errors.push(format!("pin {id} safe_value must be 0 or 1"));
+            }
+            if pin.initial_value.is_some_and(|v| v > 1) {
+                errors.push(format!("pin {id} initial_value must be 0 or 1"));
+            }
+            if let Some(state) = pin.initial_state
+                && state != GpioCapability::Disabled
+                && !pin.capabilities.contains(&state)
+            {
+                errors.push(format!(
+                    "pin {id} initial_state {state} is not one of its capabilities"
+                ));
+            }
+            lines
+                .entry((pin.chip.as_str(), pin.line))
+                .or_default()
+                .push(id.0);
+        }
+
+        if let Err(AppError::Config(e)) = self.http.socket_mode() {
+            errors.push(e);
+        }
+
+        if self.http.path.is_empty() {
+            errors.push("http.path needs at least one path".into());
+        }
+        let mut paths = HashSet::new();
+        for path in &self.http.path {
+            if !path.starts_with('/') || !paths.insert(path.trim_end_matches('/')) {
+                errors.push(format!(
+                    "http.path entries must be distinct and start with '/': {path}"
+                ));
+            }
+        }
+
+        if self.http.max_body_bytes == 0 {
+            errors.push("http.max_body_bytes must be at least 1".into());
+        }
+
+        if self.http.tls.is_some() && !cfg!(feature = "tls") {
+            errors.push("http.tls requires building with the tls feature".into());
+        }
+
+        if let Some(cors) = &self.http.cors {
+            for origin in cors.allowed_origins.iter().filter(|o| *o != "*") {
+                let valid = origin
+                    .parse::<Uri>()
+                    .is_ok_and(|uri| uri.scheme().is_some() && uri.host().is_some());
+                if !valid {
+                    errors.push(format!("invalid cors origin: {origin}"));
+                }
+            }
+        }
+
+        if let Some(limit) = &self.http.rate_limit
+            && !(limit.writes_per_second > 0.0
+                && limit.writes_per_second.is_finite()
+                && limit.burst > 0)
+        {
+            errors.push("rate_limit needs positive writes_per_second and burst".into());
+        }
+
+        for hook in &self.webhooks {
+            let valid = hook
+                .url
+                .parse::<Uri>()
+                .is_ok_and(|uri| uri.scheme_str() == Some("http") && uri.host().is_some());
+            if !valid {
+                errors.push(format!("invalid webhook url: {}", hook.url));
+            }
+        }
+
+        if let Some(mqtt) = &self.mqtt {
+            if !cfg!(feature = "mqtt") {
+                errors.push("mqtt requires building with the mqtt feature".into());
+            }
+            if let Err(AppError::Config(e)) = mqtt.broker_addr() {
+                errors.push(e);
+            }
+            if mqtt.base_topic.is_empty() || mqtt.base_topic.contains(['+', '#']) {
+                errors.push(format!("invalid mqtt base_topic: {}", mqtt.base_topic));
+            }
+        }
+
+        if self.broadcast_capacity == 0 {
+            errors.push("broadcast_capacity must be at least 1".into());
+        }
+
+        if self.libgpiod_event_buffer == 0 {
+            errors.push("libgpiod_event_buffer must be at least 1".into());
+        }
+
+        if self.libgpiod_retry_attempts == 0 {
+            errors.push("libgpiod_retry_attempts must be at least 1".into());
+        }
+
+        if let Some(label) = &self.consumer_label
+            && (label.is_empty() || label.len() > MAX_CONSUMER_LABEL_LEN || label.contains('\0'))
+        {
+            errors.push(format!(
+                "consumer_label must be 1 to {MAX_CONSUMER_LABEL_LEN} bytes without NUL"
+            ));
+        }
+
+        if self.ws_ping_interval_ms > 0 && self.ws_pong_timeout_ms == 0 {
+            errors.push("ws_pong_timeout_ms must be at least 1 when pings are enabled".into());
+        }
+
+        let mut conflicts: Vec<_> = lines.into_iter().filter(|(_, ids)| ids.len() > 1).collect();
+        conflicts.sort();
+        for ((chip, line), mut ids) in conflicts {
+            ids.sort_unstable();
+            errors.push(format!("pins {ids:?} share line {line} on {chip}"));
+        }
+
+        if !errors.is_empty() {
+            return Err(AppError::Validation(errors));
+        }
+
+        Ok(())
+    }
+
+    pub fn apply_env_overrides(&mut self) -> Result<(), AppError> {
+        if let Ok(host) = env::var("GMGR_HTTP_HOST") {
+            self.http.host = Some(host);
+        }
+        if let Ok(port) = env::var("GMGR_HTTP_PORT") {
+            let port = port
+                .parse::<u16>()
+                .map_err(|_| AppError::Config(format!("invalid GMGR_HTTP_PORT: {port}")))?;
+            let hostname = self
+                .http
+                .host
+                .as_deref()
+                .map(|h| h.rsplit_once(':').map_or(h, |(name, _)| name))
+                .unwrap_or("localhost");
+            self.http.host = Some(format!("{hostname}:{port}"));
+        }
+        if let Ok(socket) = env::var("GMGR_HTTP_UNIX_SOCKET") {
+            self.http.unix_socket = Some(socket);
+        }
+        if let Ok(paths) = env::var("GMGR_HTTP_PATH") {
+            self.http.path = paths.split(',').map(|p| p.trim().to_string()).collect();
+        }
+        Ok(())
+    }
+}
+
+/// Builds an [`AppConfig`] in code for binaries embedding gmgr, starting
+/// from the default http config and no pins.
+#[derive(Debug, Clone)]
+pub struct AppConfigBuilder {
+    config: AppConfig,
+}
+
+impl Default for AppConfigBuilder {
+    fn default() -> Self {
+        Self {
+            config: AppConfig {
+                http: HttpConfig::default(),
+                auth: AuthConfig::default(),
+                gpios: FxHashMap::default(),
+                broadcast_capacity: 128,
+                lag_policy: LagPolicy::default(),
+                event_history_capacity: 32,
+                event_log_path: None,
+                audit_log_path: None,
+                libgpiod_event_buffer: default_libgpiod_event_buffer(),
+                libgpiod_wait_timeout_ms: default_libgpiod_wait_timeout_ms(),
+                libgpiod_event_clock: EventClockSource::default(),
+                libgpiod_retry_attempts: default_libgpiod_retry_attempts(),
+                libgpiod_retry_delay_ms: default_libgpiod_retry_delay_ms(),
+                consumer_label: None,
+                ws_ping_interval_ms: default_ws_ping_interval_ms(),
+                ws_pong_timeout_ms: default_ws_pong_timeout_ms(),
+                webhooks: Vec::new(),
+                mqtt: None,
+            },
+        }
+    }
+}
+
+impl AppConfigBuilder {
+    pub fn http(mut self, http: HttpConfig) -> Self {
+        self.config.http = http;
+        self
+    }
+
+    pub fn auth(mut self, auth: AuthConfig) -> Self {
+        self.config.auth = auth;
+        self
+    }
+
+    /// Adds a pin, replacing any pin already registered under `id`.
+    pub fn add_pin(mut self, id: impl Into<PinId>, pin: PinConfig) -> Self {
+        self.config.gpios.insert(id.into(), pin);
+        self
+    }
+
+    pub fn broadcast_capacity(mut self, capacity: usize) -> Self {
+        self.config.broadcast_capacity = capacity;
+        self
+    }
+
+    pub fn lag_policy(mut self, policy: LagPolicy) -> Self {
+        self.config.lag_policy = policy;
+        self
+    }
+
+    pub fn event_history_capacity(mut self, capacity: usize) -> Self {
+        self.config.event_history_capacity = capacity;
+        self
+    }
+
+    pub fn event_log_path(mut self, path: impl Into<String>) -> Self {
+        self.config.event_log_path = Some(path.into());
+        self
+    }
+
+    pub fn audit_log_path(mut self, path: impl Into<String>) -> Self {
+        self.config.audit_log_path = Some(path.into());
+        self
+    }
+
+    pub fn consumer_label(mut self, label: impl Into<String>) -> Self {
+        self.config.consumer_label = Some(label.into());
+        self
+    }
+
+    pub fn add_webhook(mut self, webhook: WebhookConfig) -> Self {
+        self.config.webhooks.push(webhook);
+        self
+    }
+
+    pub fn mqtt(mut self, mqtt: MqttConfig) -> Self {
+        self.config.mqtt = Some(mqtt);
+        self
+    }
+
+    /// Validates the config the same way a loaded file is validated.
+    pub fn build(self) -> Result<AppConfig, AppError> {
+        self.config.validate()?;
+        Ok(self.config)
     }
 }
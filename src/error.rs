@@ -1,34 +1,138 @@
-use actix_web::{HttpResponse, ResponseError, http::StatusCode};
+use std::sync::PoisonError;
+use std::time::Duration;
+
+use actix_web::http::{StatusCode, header};
+use actix_web::{HttpResponse, ResponseError};
 use serde_json::json;
 use thiserror::Error;
 
+use crate::gpio::PinId;
+use crate::request_id::{RequestContext, RequestId};
+
 #[derive(Debug, Error)]
 pub enum AppError {
     #[error("pin not found: {0}")]
-    NotFoundPin(String),
+    NotFoundPin(PinId),
+    #[error("pin not found: {0}")]
+    NotFoundPinName(String),
     #[error("invalid state: {0}")]
     InvalidState(String),
+    #[error("invalid state for pin {pin_id}: {reason}")]
+    InvalidPinState { pin_id: PinId, reason: String },
     #[error("invalid value: {0}")]
     InvalidValue(String),
+    #[error("invalid value: {value} is outside {min}..={max}")]
+    ValueOutOfRange {
+        pin_id: Option<PinId>,
+        value: i64,
+        min: i64,
+        max: i64,
+    },
     #[error("permission denied: {0}")]
     PermissionDenied(String),
+    #[error("validation failed: {}", .0.join("; "))]
+    Validation(Vec<String>),
     #[error("configuration error: {0}")]
     Config(String),
     #[error("gpio error: {0}")]
     Gpio(String),
+    #[error("service unavailable: {0}")]
+    Unavailable(String),
+    #[error("timed out: {0}")]
+    Timeout(String),
+    #[error("line of pin {pin_id} is busy, requested by {consumer}")]
+    LineBusy { pin_id: PinId, consumer: String },
+    #[error("conflict: {0}")]
+    Conflict(String),
+    #[error("locked: {0}")]
+    Locked(String),
+    #[error("rate limit exceeded, retry in {}s", .0.as_secs())]
+    RateLimited(Duration),
+}
+
+impl<T> From<PoisonError<T>> for AppError {
+    /// A poisoned lock means an earlier panic left the service degraded.
+    fn from(e: PoisonError<T>) -> Self {
+        AppError::Unavailable(format!("lock poisoned: {e}"))
+    }
+}
+
+impl AppError {
+    /// Stable machine-readable code for the error kind.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFoundPin(_) | AppError::NotFoundPinName(_) => "pin_not_found",
+            AppError::InvalidState(_) | AppError::InvalidPinState { .. } => "invalid_state",
+            AppError::InvalidValue(_) | AppError::ValueOutOfRange { .. } => "invalid_value",
+            AppError::PermissionDenied(_) => "permission_denied",
+            AppError::Validation(_) => "validation_error",
+            AppError::Config(_) => "config_error",
+            AppError::Gpio(_) => "gpio_error",
+            AppError::Unavailable(_) => "unavailable",
+            AppError::Timeout(_) => "timeout",
+            AppError::LineBusy { .. } => "line_busy",
+            AppError::Conflict(_) => "conflict",
+            AppError::Locked(_) => "locked",
+            AppError::RateLimited(_) => "rate_limited",
+        }
+    }
+
+    /// The pin the error concerns, when known.
+    pub fn pin_id(&self) -> Option<PinId> {
+        match self {
+            AppError::NotFoundPin(pin_id)
+            | AppError::InvalidPinState { pin_id, .. }
+            | AppError::LineBusy { pin_id, .. } => Some(*pin_id),
+            AppError::ValueOutOfRange { pin_id, .. } => *pin_id,
+            _ => None,
+        }
+    }
 }
 
 impl ResponseError for AppError {
     fn status_code(&self) -> StatusCode {
         match self {
-            AppError::NotFoundPin(_) => StatusCode::NOT_FOUND,
-            AppError::InvalidState(_) | AppError::InvalidValue(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFoundPin(_) | AppError::NotFoundPinName(_) => StatusCode::NOT_FOUND,
+            AppError::InvalidState(_)
+            | AppError::InvalidPinState { .. }
+            | AppError::InvalidValue(_)
+            | AppError::ValueOutOfRange { .. }
+            | AppError::Validation(_) => StatusCode::BAD_REQUEST,
             AppError::PermissionDenied(_) => StatusCode::FORBIDDEN,
             AppError::Config(_) | AppError::Gpio(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            AppError::LineBusy { .. } | AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Locked(_) => StatusCode::LOCKED,
+            AppError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
-        HttpResponse::build(self.status_code()).json(json!({ "error": self.to_string() }))
+        let mut body = json!({ "error": self.to_string(), "code": self.code() });
+        if let Some(pin_id) = self.pin_id() {
+            body["pin_id"] = pin_id.0.into();
+        }
+        if let AppError::ValueOutOfRange {
+            value, min, max, ..
+        } = self
+        {
+            body["value"] = (*value).into();
+            body["allowed"] = json!({ "min": min, "max": max });
+        }
+        if let AppError::Validation(errors) = self {
+            body["errors"] = json!(errors);
+        }
+        if let Some(RequestId(id)) = RequestId::current() {
+            body["request_id"] = id.into();
+        }
+        if RequestContext::envelope() {
+            body["data"] = serde_json::Value::Null;
+        }
+        let mut res = HttpResponse::build(self.status_code());
+        if let AppError::RateLimited(retry_after) = self {
+            res.insert_header((header::RETRY_AFTER, retry_after.as_secs()));
+        }
+        res.json(body)
     }
 }
@@ -1,16 +1,57 @@
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::num::ParseIntError;
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
 
-use parking_lot::RwLock;
+use log::warn;
+use parking_lot::{Mutex, RwLock};
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
 
+use crate::audit::{AuditLog, AuditRecord};
+use crate::clock::{Clock, SystemClock};
 use crate::config::{AppConfig, EdgeDetect, GpioCapability, PinConfig};
 use crate::error::AppError;
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::request_id::RequestContext;
+use crate::webhook::spawn_delivery;
 
 pub type GpioManager<B> = GenericGpioManager<B>;
 
+/// Id of a configured pin, distinct from the line offset on its chip.
+/// Serialized as a plain integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PinId(pub u32);
+
+impl fmt::Display for PinId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for PinId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(PinId)
+    }
+}
+
+impl From<u32> for PinId {
+    fn from(id: u32) -> Self {
+        PinId(id)
+    }
+}
+
 pub type GpioState = GpioCapability;
 
 impl GpioState {
@@ -29,47 +70,472 @@ impl GpioState {
     }
 }
 
+/// Edge counts of a pin since startup or its last reset, independent of
+/// how much history is kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct EventStats {
+    pub rising_count: u64,
+    pub falling_count: u64,
+    pub last_event_ms: Option<u64>,
+}
+
+/// Load on the shared event stream, across all pins and subscribers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EventStreamStats {
+    pub subscribers: usize,
+    pub dispatched: u64,
+    /// Events skipped by subscribers that fell behind the broadcast buffer.
+    pub lagged: u64,
+}
+
+#[derive(Default)]
+struct SoftwareDebounce {
+    window_ms: u64,
+    last_ms: Option<u64>,
+}
+
+/// The event edges of one direction are being merged into, see
+/// `set_coalesce_window`.
+#[derive(Clone, Copy)]
+struct Burst {
+    edge: EdgeDetect,
+    started_ms: u64,
+    seq: u64,
+    count: u32,
+}
+
+#[derive(Default)]
+struct Coalescing {
+    window_ms: u64,
+    burst: Option<Burst>,
+}
+
+/// Locks of several pin tables are always taken in the order `debounce`,
+/// `stats`, `coalescing`, `event_history`, `history_capacity`.
 pub struct EventCallbackHandler {
     event_tx: broadcast::Sender<EdgeEvent>,
-    event_history: FxHashMap<u32, RwLock<VecDeque<EdgeEvent>>>,
+    event_history: RwLock<FxHashMap<PinId, RwLock<VecDeque<EdgeEvent>>>>,
     event_history_capacity: usize,
+    history_capacity: RwLock<FxHashMap<PinId, usize>>, // per-pin overrides
+    debounce: RwLock<FxHashMap<PinId, Mutex<SoftwareDebounce>>>,
+    stats: RwLock<FxHashMap<PinId, Mutex<EventStats>>>,
+    coalescing: RwLock<FxHashMap<PinId, Mutex<Coalescing>>>,
+    seq: AtomicU64,
+    dispatched: AtomicU64,
+    lagged: AtomicU64,
+    event_log: Option<Mutex<File>>,
+    clock: Arc<dyn Clock>,
+    #[cfg(feature = "metrics")]
+    metrics: Metrics,
 }
 
 impl EventCallbackHandler {
     pub fn new(
         event_tx: broadcast::Sender<EdgeEvent>,
-        event_history: FxHashMap<u32, RwLock<VecDeque<EdgeEvent>>>,
+        event_history: FxHashMap<PinId, RwLock<VecDeque<EdgeEvent>>>,
         event_history_capacity: usize,
     ) -> Self {
+        let debounce = event_history
+            .keys()
+            .map(|id| (*id, Mutex::new(SoftwareDebounce::default())))
+            .collect();
+        let stats = event_history
+            .keys()
+            .map(|id| (*id, Mutex::new(EventStats::default())))
+            .collect();
+        let coalescing = event_history
+            .keys()
+            .map(|id| (*id, Mutex::new(Coalescing::default())))
+            .collect();
+
         Self {
             event_tx,
-            event_history,
+            event_history: RwLock::new(event_history),
             event_history_capacity,
+            history_capacity: RwLock::new(FxHashMap::default()),
+            debounce: RwLock::new(debounce),
+            stats: RwLock::new(stats),
+            coalescing: RwLock::new(coalescing),
+            seq: AtomicU64::new(0),
+            dispatched: AtomicU64::new(0),
+            lagged: AtomicU64::new(0),
+            event_log: None,
+            clock: Arc::new(SystemClock),
+            #[cfg(feature = "metrics")]
+            metrics: Metrics::new(),
         }
     }
 
-    pub fn dispatch(&self, event: EdgeEvent) {
-        {
-            let event = event.clone();
-            if let Some(history_lock) = self.event_history.get(&event.pin_id) {
-                let mut history = history_lock.write();
-                while history.len() >= self.event_history_capacity {
-                    history.pop_front();
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Current time from the handler's clock, for timestamps that do not
+    /// come from the hardware.
+    pub fn now_ms(&self) -> u64 {
+        self.clock.now_ms()
+    }
+
+    /// Replays the tail of a JSON lines event log into the history, then
+    /// appends every dispatched event to it.
+    pub fn open_event_log<P: AsRef<Path>>(&mut self, path: P) -> Result<(), AppError> {
+        let path = path.as_ref();
+        match File::open(path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line
+                        .map_err(|e| AppError::Config(format!("failed to read event log: {e}")))?;
+                    let Ok(event) = serde_json::from_str::<EdgeEvent>(&line) else {
+                        warn!("skipping malformed event log line: {line}");
+                        continue;
+                    };
+                    self.seq.fetch_max(event.seq, Ordering::AcqRel);
+                    let capacity = self.history_capacity(event.pin_id);
+                    if let Some(history) = self.event_history.get_mut().get(&event.pin_id) {
+                        let mut history = history.write();
+                        // a coalescing update replaces the event it counts
+                        match history.back_mut() {
+                            Some(last) if last.seq == event.seq => *last = event,
+                            _ => push_history(&mut history, event, capacity),
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(AppError::Config(format!("failed to read event log: {e}"))),
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| AppError::Config(format!("failed to open event log: {e}")))?;
+        self.event_log = Some(Mutex::new(file));
+        Ok(())
+    }
+
+    /// Starts tracking history for new pins and drops it for pins that are
+    /// no longer configured.
+    pub fn retain_pins(&self, pin_ids: &[PinId]) {
+        // same order as dispatch, which holds coalescing while recording history
+        let mut debounce = self.debounce.write();
+        debounce.retain(|id, _| pin_ids.contains(id));
+        let mut stats = self.stats.write();
+        stats.retain(|id, _| pin_ids.contains(id));
+        let mut coalescing = self.coalescing.write();
+        coalescing.retain(|id, _| pin_ids.contains(id));
+        let mut history = self.event_history.write();
+        history.retain(|id, _| pin_ids.contains(id));
+        self.history_capacity
+            .write()
+            .retain(|id, _| pin_ids.contains(id));
+        for id in pin_ids {
+            history.entry(*id).or_default();
+            debounce.entry(*id).or_default();
+            stats.entry(*id).or_default();
+            coalescing.entry(*id).or_default();
+        }
+    }
+
+    /// Overrides `event_history_capacity` for one pin, `None` goes back to
+    /// it. Drops the oldest events the pin holds beyond the new capacity.
+    pub fn set_history_capacity(&self, pin_id: PinId, capacity: Option<usize>) {
+        match capacity {
+            Some(capacity) => self.history_capacity.write().insert(pin_id, capacity),
+            None => self.history_capacity.write().remove(&pin_id),
+        };
+        let capacity = self.history_capacity(pin_id);
+        if let Some(history) = self.event_history.read().get(&pin_id) {
+            let mut history = history.write();
+            let excess = history.len().saturating_sub(capacity);
+            history.drain(..excess);
+        }
+    }
+
+    fn history_capacity(&self, pin_id: PinId) -> usize {
+        self.history_capacity
+            .read()
+            .get(&pin_id)
+            .copied()
+            .unwrap_or(self.event_history_capacity)
+    }
+
+    pub fn set_software_debounce(&self, pin_id: PinId, window_ms: u64) {
+        if let Some(debounce) = self.debounce.read().get(&pin_id) {
+            *debounce.lock() = SoftwareDebounce {
+                window_ms,
+                last_ms: None,
+            };
+        }
+    }
+
+    /// Merges edges of the same direction arriving within `window_ms` of the
+    /// first one into that event, counting them in its `count`. The first
+    /// edge is delivered right away, each merged edge then sends the event
+    /// again under the same `seq` with its count so far, so subscribers and
+    /// the event log end with the final count.
+    pub fn set_coalesce_window(&self, pin_id: PinId, window_ms: u64) {
+        if let Some(coalescing) = self.coalescing.read().get(&pin_id) {
+            *coalescing.lock() = Coalescing {
+                window_ms,
+                burst: None,
+            };
+        }
+    }
+
+    pub fn stats(&self, pin_id: PinId) -> EventStats {
+        self.stats
+            .read()
+            .get(&pin_id)
+            .map(|stats| *stats.lock())
+            .unwrap_or_default()
+    }
+
+    pub fn reset_stats(&self, pin_id: PinId) {
+        if let Some(stats) = self.stats.read().get(&pin_id) {
+            *stats.lock() = EventStats::default();
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EdgeEvent> {
+        self.event_tx.subscribe()
+    }
+
+    /// Counts events a subscriber skipped after falling behind.
+    pub fn record_lag(&self, skipped: u64) {
+        self.lagged.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    pub fn stream_stats(&self) -> EventStreamStats {
+        EventStreamStats {
+            subscribers: self.event_tx.receiver_count(),
+            dispatched: self.dispatched.load(Ordering::Relaxed),
+            lagged: self.lagged.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Sequence number of the last dispatched event, 0 if none yet.
+    pub fn current_seq(&self) -> u64 {
+        self.seq.load(Ordering::Acquire)
+    }
+
+    /// Records and broadcasts an event, overwriting its `seq`.
+    pub fn dispatch(&self, mut event: EdgeEvent) {
+        if let Some(debounce) = self.debounce.read().get(&event.pin_id) {
+            let mut debounce = debounce.lock();
+            if debounce.window_ms > 0 {
+                if let Some(last) = debounce.last_ms
+                    && event.timestamp_ms.saturating_sub(last) < debounce.window_ms
+                {
+                    return;
                 }
-                history.push_back(event);
+                debounce.last_ms = Some(event.timestamp_ms);
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        self.metrics.record_edge(event.pin_id, event.edge);
+
+        if let Some(stats) = self.stats.read().get(&event.pin_id) {
+            let mut stats = stats.lock();
+            match event.edge {
+                EdgeDetect::Rising => stats.rising_count += 1,
+                EdgeDetect::Falling => stats.falling_count += 1,
+                EdgeDetect::None | EdgeDetect::Both => {}
+            }
+            stats.last_event_ms = Some(event.timestamp_ms);
+        }
+
+        let coalescing = self.coalescing.read();
+        let mut coalescing = coalescing
+            .get(&event.pin_id)
+            .map(|c| c.lock())
+            .filter(|c| c.window_ms > 0);
+        if let Some(c) = coalescing.as_deref_mut()
+            && let Some(burst) = &mut c.burst
+            && burst.edge == event.edge
+            && event.timestamp_ms.saturating_sub(burst.started_ms) < c.window_ms
+        {
+            burst.count += 1;
+            let update = EdgeEvent {
+                pin_id: event.pin_id,
+                edge: burst.edge,
+                timestamp_ms: burst.started_ms,
+                seq: burst.seq,
+                count: burst.count,
+            };
+            if let Some(history) = self.event_history.read().get(&event.pin_id)
+                && let Some(merged) = history.write().iter_mut().rfind(|e| e.seq == burst.seq)
+            {
+                merged.count = burst.count;
+            }
+            self.publish(update);
+            return;
+        }
+
+        let capacity = self.history_capacity(event.pin_id);
+        // assign under the history lock so each pin's history stays ordered by seq
+        let histories = self.event_history.read();
+        let history = histories.get(&event.pin_id).map(|h| h.write());
+        event.seq = self.seq.fetch_add(1, Ordering::AcqRel) + 1;
+        if let Some(c) = &mut coalescing {
+            c.burst = Some(Burst {
+                edge: event.edge,
+                started_ms: event.timestamp_ms,
+                seq: event.seq,
+                count: 1,
+            });
+        }
+        if let Some(mut history) = history {
+            push_history(&mut history, event.clone(), capacity);
+        }
+        self.publish(event);
+    }
+
+    /// Appends an event to the event log and broadcasts it.
+    fn publish(&self, event: EdgeEvent) {
+        if let Some(log) = &self.event_log {
+            let line = serde_json::to_string(&event).unwrap_or_default();
+            if let Err(e) = writeln!(log.lock(), "{line}") {
+                warn!("failed to append to event log: {e}");
             }
         }
+        self.dispatched.fetch_add(1, Ordering::Relaxed);
         let _ = self.event_tx.send(event);
     }
 }
 
+/// Appends `event`, dropping the oldest ones beyond `capacity`. Keeps
+/// nothing when `capacity` is 0.
+fn push_history(history: &mut VecDeque<EdgeEvent>, event: EdgeEvent, capacity: usize) {
+    if capacity == 0 {
+        history.clear();
+        return;
+    }
+    let excess = (history.len() + 1).saturating_sub(capacity);
+    history.drain(..excess);
+    history.push_back(event);
+}
+
 pub type EventHandler = Arc<EventCallbackHandler>;
 
-#[derive(Debug, Clone, Serialize)]
+/// Checks `value` is a level a pin can be driven to, 0 or 1.
+pub(crate) fn check_value(pin_id: Option<PinId>, value: i64) -> Result<u8, AppError> {
+    match value {
+        0 | 1 => Ok(value as u8),
+        _ => Err(AppError::ValueOutOfRange {
+            pin_id,
+            value,
+            min: 0,
+            max: 1,
+        }),
+    }
+}
+
+/// Checks a pulse lasts at most `MAX_PULSE_MS`.
+pub(crate) fn check_pulse_duration(pin_id: PinId, duration_ms: u64) -> Result<(), AppError> {
+    if duration_ms > MAX_PULSE_MS {
+        return Err(AppError::ValueOutOfRange {
+            pin_id: Some(pin_id),
+            value: duration_ms.min(i64::MAX as u64) as i64,
+            min: 0,
+            max: MAX_PULSE_MS as i64,
+        });
+    }
+    Ok(())
+}
+
+/// Parses a pin value written as a number or as `true`/`false`/`high`/`low`
+/// in any case, optionally quoted like a JSON string.
+pub(crate) fn parse_value(text: &str) -> Result<u8, AppError> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(text);
+    if ["true", "high"]
+        .iter()
+        .any(|v| text.eq_ignore_ascii_case(v))
+    {
+        return Ok(1);
+    }
+    if ["false", "low"]
+        .iter()
+        .any(|v| text.eq_ignore_ascii_case(v))
+    {
+        return Ok(0);
+    }
+    let value = text.parse::<i64>().map_err(|_| {
+        AppError::InvalidValue("value must be 0, 1, true, false, high or low".into())
+    })?;
+    check_value(None, value)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EdgeEvent {
-    pub pin_id: u32,
+    pub pin_id: PinId,
     pub edge: EdgeDetect,
     pub timestamp_ms: u64,
+    pub seq: u64,
+    /// Edges merged into this event by coalescing, 1 otherwise.
+    #[serde(default = "one")]
+    pub count: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// Internal pull of a line, on top of the one implied by input states.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Bias {
+    Disabled,
+    PullUp,
+    PullDown,
+}
+
+impl fmt::Display for Bias {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Bias::Disabled => "disabled",
+            Bias::PullUp => "pull-up",
+            Bias::PullDown => "pull-down",
+        })
+    }
+}
+
+impl FromStr for Bias {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Bias::Disabled, Bias::PullUp, Bias::PullDown]
+            .into_iter()
+            .find(|b| b.to_string() == s)
+            .ok_or_else(|| AppError::InvalidValue(format!("invalid bias: {s}")))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PwmConfig {
+    pub frequency_hz: f64,
+    pub duty_cycle: f64,
+}
+
+impl PwmConfig {
+    pub fn validate(&self) -> Result<(), AppError> {
+        if !(self.frequency_hz > 0.0 && self.frequency_hz.is_finite()) {
+            return Err(AppError::InvalidValue(
+                "pwm frequency_hz must be positive".into(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.duty_cycle) {
+            return Err(AppError::InvalidValue(
+                "pwm duty_cycle must be within 0.0 and 1.0".into(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,6 +543,19 @@ pub struct PinSettings {
     pub state: GpioState,
     pub edge: EdgeDetect,
     pub debounce_ms: u64,
+    #[serde(default)]
+    pub software_debounce: bool,
+    /// Window in which same-direction edges are merged into one event.
+    #[serde(default)]
+    pub coalesce_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pwm: Option<PwmConfig>,
+    /// Output drive strength, applied only where the backend supports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drive_strength_ma: Option<u32>,
+    /// Pull for the line, input states imply theirs when this is unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bias: Option<Bias>,
 }
 
 impl Default for PinSettings {
@@ -85,37 +564,284 @@ impl Default for PinSettings {
             state: GpioState::Disabled,
             edge: EdgeDetect::None,
             debounce_ms: 0,
+            software_debounce: false,
+            coalesce_ms: 0,
+            pwm: None,
+            drive_strength_ma: None,
+            bias: None,
         }
     }
 }
 
+/// Longest debounce period accepted, in milliseconds.
+pub const MAX_DEBOUNCE_MS: u64 = 60_000;
+
+/// Most reads a voted read takes.
+pub const MAX_VOTE_SAMPLES: usize = 64;
+
+/// Longest pause between the reads of a voted read, in milliseconds.
+pub const MAX_VOTE_INTERVAL_MS: u64 = 1_000;
+
+/// Longest pulse accepted, in milliseconds.
+pub const MAX_PULSE_MS: u64 = 60_000;
+
+impl PinSettings {
+    /// Checks the settings are consistent on their own, before they are
+    /// matched against the capabilities of a pin.
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.debounce_ms > MAX_DEBOUNCE_MS {
+            return Err(AppError::InvalidValue(format!(
+                "debounce_ms must be within 0 and {MAX_DEBOUNCE_MS}"
+            )));
+        }
+        if self.coalesce_ms > MAX_DEBOUNCE_MS {
+            return Err(AppError::InvalidValue(format!(
+                "coalesce_ms must be within 0 and {MAX_DEBOUNCE_MS}"
+            )));
+        }
+        if self.coalesce_ms != 0 && self.edge == EdgeDetect::None {
+            return Err(AppError::InvalidState(
+                "coalescing requires edge detection to be enabled".into(),
+            ));
+        }
+
+        if let Some(ma) = self.drive_strength_ma {
+            if !(self.state.is_writable() || self.state == GpioState::Pwm) {
+                return Err(AppError::InvalidState(format!(
+                    "cannot set drive strength on {} pin",
+                    self.state
+                )));
+            }
+            if ma == 0 {
+                return Err(AppError::InvalidValue(
+                    "drive_strength_ma must be at least 1".into(),
+                ));
+            }
+        }
+
+        if let Some(bias) = self.bias {
+            let compatible = match self.state {
+                GpioState::Error | GpioState::Disabled => false,
+                GpioState::Floating => bias == Bias::Disabled,
+                GpioState::PullUp => bias == Bias::PullUp,
+                GpioState::PullDown => bias == Bias::PullDown,
+                // a pull would only fight a line driven both ways
+                GpioState::PushPull | GpioState::Pwm => bias == Bias::Disabled,
+                GpioState::OpenDrain => bias != Bias::PullDown,
+                GpioState::OpenSource => bias != Bias::PullUp,
+            };
+            if !compatible {
+                return Err(AppError::InvalidState(format!(
+                    "bias {bias} conflicts with the {} state",
+                    self.state
+                )));
+            }
+        }
+
+        match self.state {
+            GpioState::Error => {
+                return Err(AppError::InvalidState(format!(
+                    "cannot set pin to {} state",
+                    self.state
+                )));
+            }
+            GpioState::Disabled => {
+                if self.edge != EdgeDetect::None {
+                    return Err(AppError::InvalidState(
+                        "cannot set edge detection on disabled pin".into(),
+                    ));
+                }
+                if self.debounce_ms != 0 {
+                    return Err(AppError::InvalidState(
+                        "cannot set debounce on disabled pin".into(),
+                    ));
+                }
+            }
+            _ if self.debounce_ms != 0 && !self.state.is_edge_detectable() => {
+                return Err(AppError::InvalidState(format!(
+                    "cannot set debounce on {} pin",
+                    self.state
+                )));
+            }
+            _ => match self.edge {
+                EdgeDetect::None if self.debounce_ms != 0 => {
+                    return Err(AppError::InvalidState(
+                        "debouncing requires edge detection to be enabled".into(),
+                    ));
+                }
+                EdgeDetect::None => {}
+                _ if !self.state.is_edge_detectable() => {
+                    return Err(AppError::InvalidState(format!(
+                        "{} edge detection requires an input-capable state, not {}",
+                        self.edge, self.state
+                    )));
+                }
+                _ => {}
+            },
+        }
+
+        match (self.state, &self.pwm) {
+            (GpioState::Pwm, Some(pwm)) => pwm.validate(),
+            (GpioState::Pwm, None) => Err(AppError::InvalidState(
+                "pwm state requires pwm settings".into(),
+            )),
+            (_, Some(_)) => Err(AppError::InvalidState(
+                "pwm settings require the pwm state".into(),
+            )),
+            (_, None) => Ok(()),
+        }
+    }
+}
+
+/// Pin ids affected by a config reload, each list sorted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReloadSummary {
+    pub added: Vec<PinId>,
+    pub removed: Vec<PinId>,
+    pub changed: Vec<PinId>,
+}
+
+impl fmt::Display for ReloadSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids = |ids: &[PinId]| {
+            ids.iter()
+                .map(PinId::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(
+            f,
+            "added [{}], removed [{}], changed [{}]",
+            ids(&self.added),
+            ids(&self.removed),
+            ids(&self.changed)
+        )
+    }
+}
+
+/// The majority of repeated reads of a pin, with the reads themselves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VotedValue {
+    pub value: u8,
+    pub samples: Vec<u8>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PinDescriptor {
     pub info: PinConfig,
     pub settings: PinSettings,
 }
 
+/// An enabled pin with the value it reads now, left out when the read
+/// failed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivePin {
+    pub info: PinConfig,
+    pub settings: PinSettings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<u8>,
+}
+
+/// What serves the api: the backend kind and the versions behind it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerInfo {
+    pub backend: &'static str,
+    pub libgpiod_version: Option<&'static str>,
+    pub crate_version: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LineInfo {
+    pub offset: u32,
+    pub name: Option<String>,
+    pub consumer: Option<String>,
+    pub used: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChipInfo {
+    pub path: String,
+    pub name: String,
+    pub label: String,
+    pub num_lines: u32,
+    pub lines: Vec<LineInfo>,
+}
+
+/// Access to the gpio lines. Implementations must not block the calling
+/// task, blocking hardware calls belong on `spawn_blocking`.
 pub trait GpioBackend: Send + Sync {
-    fn get_settings(&self, pin_id: u32) -> Result<PinSettings, AppError>;
+    fn get_settings(
+        &self,
+        pin_id: PinId,
+    ) -> impl Future<Output = Result<PinSettings, AppError>> + Send;
     fn set_settings(
         &self,
-        pin_id: u32,
+        pin_id: PinId,
         pin: &PinConfig,
         settings: &PinSettings,
         event_callback: Option<EventHandler>,
-    ) -> Result<(), AppError>;
-    fn read_value(&self, pin_id: u32) -> Result<u8, AppError>;
-    fn write_value(&self, pin_id: u32, value: u8) -> Result<(), AppError>;
+    ) -> impl Future<Output = Result<(), AppError>> + Send;
+    /// Bumped on every successful `set_settings` of the pin, so callers can
+    /// tell whether its settings changed since they read them.
+    fn settings_version(&self, pin_id: PinId)
+    -> impl Future<Output = Result<u64, AppError>> + Send;
+    /// Settings as the line reports them now, which can differ from the
+    /// cached ones when another consumer reconfigured it.
+    fn read_hardware_settings(
+        &self,
+        pin_id: PinId,
+    ) -> impl Future<Output = Result<PinSettings, AppError>> + Send;
+    /// Push-pull pins may be answered from the last value written to them,
+    /// open-drain and open-source lines depend on their pull when released.
+    fn read_value(&self, pin_id: PinId) -> impl Future<Output = Result<u8, AppError>> + Send;
+    /// Reads the line itself, bypassing any written value kept for outputs.
+    fn read_hardware_value(
+        &self,
+        pin_id: PinId,
+    ) -> impl Future<Output = Result<u8, AppError>> + Send;
+    /// Reads several pins at once, failing as a whole if any pin can't be read.
+    fn read_values(
+        &self,
+        pins: &[PinId],
+    ) -> impl Future<Output = Result<Vec<(PinId, u8)>, AppError>> + Send;
+    fn write_value(
+        &self,
+        pin_id: PinId,
+        value: u8,
+    ) -> impl Future<Output = Result<(), AppError>> + Send;
+    fn set_values(
+        &self,
+        values: &[(PinId, u8)],
+    ) -> impl Future<Output = Result<(), AppError>> + Send;
+    fn probe_chip(&self, chip: &str) -> impl Future<Output = Result<(), AppError>> + Send;
+    fn chip_info(
+        &self,
+        pins: &FxHashMap<PinId, PinConfig>,
+    ) -> impl Future<Output = Result<Vec<ChipInfo>, AppError>> + Send;
+    /// Short name of the backend, reported by `/info`.
+    fn kind(&self) -> &'static str;
+    /// Version of the gpio library linked in, if the backend uses one.
+    fn library_version(&self) -> Option<&'static str> {
+        None
+    }
 }
 
 pub struct GenericGpioManager<B: GpioBackend> {
-    config: Arc<AppConfig>,
+    config: RwLock<Arc<AppConfig>>,
     backend: Arc<B>,
     event_handler: EventHandler,
+    audit_log: Option<AuditLog>,
+    ready: AtomicBool,
+    halted: AtomicBool,
+    settings_update: tokio::sync::Mutex<()>,
 }
 
 impl<B: GpioBackend> GenericGpioManager<B> {
     pub fn new(config: Arc<AppConfig>, backend: Arc<B>) -> Self {
+        Self::with_clock(config, backend, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(config: Arc<AppConfig>, backend: Arc<B>, clock: Arc<dyn Clock>) -> Self {
         let (event_tx, _) = broadcast::channel(config.broadcast_capacity);
 
         let mut history = FxHashMap::default();
@@ -123,24 +849,180 @@ impl<B: GpioBackend> GenericGpioManager<B> {
             history.insert(*id, RwLock::new(VecDeque::new()));
         }
 
-        let event_handler = Arc::new(EventCallbackHandler::new(
-            event_tx,
-            history,
-            config.event_history_capacity,
-        ));
+        let mut event_handler =
+            EventCallbackHandler::new(event_tx, history, config.event_history_capacity);
+        event_handler.set_clock(clock);
+        for (pin_id, pin) in &config.gpios {
+            event_handler.set_history_capacity(*pin_id, pin.history_capacity);
+        }
+        if let Some(path) = &config.event_log_path
+            && let Err(e) = event_handler.open_event_log(path)
+        {
+            warn!("event log disabled: {e}");
+        }
+        let event_handler = Arc::new(event_handler);
+        spawn_delivery(&config.webhooks, &event_handler);
+
+        let audit_log =
+            config
+                .audit_log_path
+                .as_ref()
+                .and_then(|path| match AuditLog::open(path) {
+                    Ok(log) => Some(log),
+                    Err(e) => {
+                        warn!("audit log disabled: {e}");
+                        None
+                    }
+                });
 
         Self {
-            config,
+            config: RwLock::new(config),
             backend,
             event_handler,
+            audit_log,
+            ready: AtomicBool::new(false),
+            halted: AtomicBool::new(false),
+            settings_update: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub async fn check_ready(&self) -> bool {
+        if self.ready.load(Ordering::Acquire) {
+            return true;
+        }
+
+        let config = self.config();
+        let chips: HashSet<&str> = config.gpios.values().map(|p| p.chip.as_str()).collect();
+        for chip in chips {
+            if let Err(e) = self.backend.probe_chip(chip).await {
+                warn!("chip {chip} is not ready: {e}");
+                return false;
+            }
+        }
+        self.ready.store(true, Ordering::Release);
+
+        true
+    }
+
+    fn sorted_pin_ids(config: &AppConfig) -> Vec<PinId> {
+        let mut ids: Vec<PinId> = config.gpios.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    pub async fn initialize(&self) {
+        let config = self.config();
+        for pin_id in Self::sorted_pin_ids(&config) {
+            self.initialize_pin(pin_id, &config.gpios[&pin_id]).await;
+        }
+    }
+
+    async fn initialize_pin(&self, pin_id: PinId, cfg: &PinConfig) {
+        if let Some(state) = cfg.initial_state {
+            let settings = PinSettings {
+                state,
+                ..PinSettings::default()
+            };
+            if let Err(e) = self.set_pin_settings(pin_id, &settings).await {
+                warn!("failed to apply initial state for pin {pin_id}: {e}");
+                return;
+            }
+        }
+        if let Some(value) = cfg.initial_value
+            && let Err(e) = self.write_value(pin_id, value).await
+        {
+            warn!("failed to apply initial value for pin {pin_id}: {e}");
+        }
+    }
+
+    /// Swaps in a reloaded config. Removed and changed pins are released,
+    /// added and changed pins get their initial settings, unchanged pins
+    /// are left alone. The http section can't change without a restart.
+    pub async fn reload(&self, config: AppConfig) -> Result<ReloadSummary, AppError> {
+        let current = self.config();
+        if config.http != current.http {
+            return Err(AppError::InvalidState(
+                "reload cannot change http settings, restart instead".into(),
+            ));
+        }
+
+        let mut summary = ReloadSummary::default();
+        for pin_id in Self::sorted_pin_ids(&current) {
+            match config.gpios.get(&pin_id) {
+                None => summary.removed.push(pin_id),
+                Some(cfg) if cfg.line_differs(&current.gpios[&pin_id]) => {
+                    summary.changed.push(pin_id)
+                }
+                Some(_) => {}
+            }
+        }
+        for pin_id in Self::sorted_pin_ids(&config) {
+            if !current.gpios.contains_key(&pin_id) {
+                summary.added.push(pin_id);
+            }
+        }
+
+        for pin_id in summary.removed.iter().chain(&summary.changed) {
+            if let Err(e) = self
+                .set_pin_settings(*pin_id, &PinSettings::default())
+                .await
+            {
+                warn!("failed to release pin {pin_id}: {e}");
+            }
+        }
+
+        let config = Arc::new(config);
+        *self.config.write() = config.clone();
+        self.event_handler
+            .retain_pins(&Self::sorted_pin_ids(&config));
+        for (pin_id, pin) in &config.gpios {
+            self.event_handler
+                .set_history_capacity(*pin_id, pin.history_capacity);
+        }
+
+        for pin_id in summary.added.iter().chain(&summary.changed) {
+            self.initialize_pin(*pin_id, &config.gpios[pin_id]).await;
+        }
+
+        Ok(summary)
+    }
+
+    /// Drives pins currently in an output state to their `safe_value`.
+    async fn apply_safe_values(&self) {
+        let config = self.config();
+        for pin_id in Self::sorted_pin_ids(&config) {
+            let Some(value) = config.gpios[&pin_id].safe_value else {
+                continue;
+            };
+            let writable = self
+                .backend
+                .get_settings(pin_id)
+                .await
+                .is_ok_and(|s| s.state.is_writable());
+            if writable && let Err(e) = self.backend_write(pin_id, value).await {
+                warn!("failed to apply safe value for pin {pin_id}: {e}");
+            }
+        }
+    }
+
+    /// Drives output pins to their `safe_value` and releases every line.
+    pub async fn shutdown(&self) {
+        self.apply_safe_values().await;
+
+        for pin_id in Self::sorted_pin_ids(&self.config()) {
+            if let Err(e) = self.set_pin_settings(pin_id, &PinSettings::default()).await {
+                warn!("failed to disable pin {pin_id}: {e}");
+            }
         }
     }
 
-    fn pin_config(&self, pin_id: u32) -> Result<&PinConfig, AppError> {
+    fn pin_config(&self, pin_id: PinId) -> Result<PinConfig, AppError> {
         self.config
+            .read()
             .gpios
             .get(&pin_id)
-            .ok_or_else(|| AppError::NotFoundPin(pin_id.to_string()))
+            .cloned()
+            .ok_or(AppError::NotFoundPin(pin_id))
     }
 
     fn capability_matches(state: GpioState, caps: &HashSet<GpioState>) -> bool {
@@ -155,26 +1037,77 @@ impl<B: GpioBackend> GenericGpioManager<B> {
         }
     }
 
-    pub async fn list_pins(&self) -> HashMap<u32, PinDescriptor> {
-        self.config
+    pub async fn list_pins(&self) -> HashMap<PinId, PinDescriptor> {
+        let config = self.config();
+        let mut pins = HashMap::with_capacity(config.gpios.len());
+        for (id, cfg) in &config.gpios {
+            let settings = self.backend.get_settings(*id).await.unwrap_or_default();
+            pins.insert(
+                *id,
+                PinDescriptor {
+                    info: cfg.clone(),
+                    settings,
+                },
+            );
+        }
+
+        pins
+    }
+
+    /// Pins whose backend settings are not disabled, with their value.
+    pub async fn list_active_pins(&self) -> HashMap<PinId, ActivePin> {
+        let config = self.config();
+        let mut pins = HashMap::new();
+        for (id, cfg) in &config.gpios {
+            let settings = self.backend.get_settings(*id).await.unwrap_or_default();
+            if settings.state == GpioState::Disabled {
+                continue;
+            }
+            let value = match self.backend.read_value(*id).await {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    warn!("failed to read active pin {id}: {e}");
+                    None
+                }
+            };
+            pins.insert(
+                *id,
+                ActivePin {
+                    info: cfg.clone(),
+                    settings,
+                    value,
+                },
+            );
+        }
+
+        pins
+    }
+
+    /// Resolves a pin by its configured name, names shared by several pins
+    /// are rejected rather than picking one.
+    pub fn pin_id_by_name(&self, name: &str) -> Result<PinId, AppError> {
+        let mut ids: Vec<u32> = self
+            .config
+            .read()
             .gpios
             .iter()
-            .map(|(id, cfg)| {
-                let settings = self.backend.get_settings(*id).unwrap_or_default();
-                (
-                    *id,
-                    PinDescriptor {
-                        info: cfg.clone(),
-                        settings,
-                    },
-                )
-            })
-            .collect()
+            .filter(|(_, cfg)| cfg.name == name)
+            .map(|(id, _)| id.0)
+            .collect();
+        ids.sort_unstable();
+
+        match ids.as_slice() {
+            [] => Err(AppError::NotFoundPinName(name.to_string())),
+            [id] => Ok(PinId(*id)),
+            _ => Err(AppError::InvalidState(format!(
+                "pins {ids:?} share the name {name}"
+            ))),
+        }
     }
 
-    pub async fn get_pin_descriptor(&self, pin_id: u32) -> Result<PinDescriptor, AppError> {
-        let cfg = self.pin_config(pin_id)?.clone();
-        let settings = self.backend.get_settings(pin_id).unwrap_or_default();
+    pub async fn get_pin_descriptor(&self, pin_id: PinId) -> Result<PinDescriptor, AppError> {
+        let cfg = self.pin_config(pin_id)?;
+        let settings = self.backend.get_settings(pin_id).await.unwrap_or_default();
 
         Ok(PinDescriptor {
             info: cfg,
@@ -182,88 +1115,752 @@ impl<B: GpioBackend> GenericGpioManager<B> {
         })
     }
 
-    pub async fn get_pin_info(&self, pin_id: u32) -> Result<PinConfig, AppError> {
-        self.pin_config(pin_id).cloned()
+    pub async fn get_pin_info(&self, pin_id: PinId) -> Result<PinConfig, AppError> {
+        self.pin_config(pin_id)
+    }
+
+    pub async fn chip_info(&self) -> Result<Vec<ChipInfo>, AppError> {
+        self.backend.chip_info(&self.config().gpios).await
+    }
+
+    pub async fn get_pin_settings(&self, pin_id: PinId) -> Result<PinSettings, AppError> {
+        self.pin_config(pin_id)?;
+        self.backend.get_settings(pin_id).await
     }
 
-    pub async fn get_pin_settings(&self, pin_id: u32) -> Result<PinSettings, AppError> {
+    pub async fn get_hardware_settings(&self, pin_id: PinId) -> Result<PinSettings, AppError> {
         self.pin_config(pin_id)?;
-        self.backend.get_settings(pin_id)
+        self.backend.read_hardware_settings(pin_id).await
+    }
+
+    pub async fn settings_version(&self, pin_id: PinId) -> Result<u64, AppError> {
+        self.pin_config(pin_id)?;
+        self.backend.settings_version(pin_id).await
     }
 
     pub async fn set_pin_settings(
         &self,
-        pin_id: u32,
+        pin_id: PinId,
         settings: &PinSettings,
     ) -> Result<(), AppError> {
+        let _update = self.settings_update.lock().await;
+        self.checked_set_pin_settings(pin_id, settings).await
+    }
+
+    /// Merges the current settings of a pin into new ones and applies them,
+    /// with no other settings change in between. With `if_version` set it
+    /// fails with a conflict when the settings changed since that version.
+    /// Returns the applied settings and their version.
+    pub async fn update_pin_settings(
+        &self,
+        pin_id: PinId,
+        if_version: Option<u64>,
+        merge: impl FnOnce(PinSettings) -> Result<PinSettings, AppError>,
+    ) -> Result<(PinSettings, u64), AppError> {
+        let _update = self.settings_update.lock().await;
+        self.merge_pin_settings(pin_id, if_version, merge).await
+    }
+
+    /// Merges and checks the settings of many pins, then applies them with
+    /// no other settings change in between. Nothing is applied unless every
+    /// pin passes its checks, and the error lists every problem. Returns the
+    /// applied settings or the apply failure of each pin.
+    pub async fn update_pins_settings<T>(
+        &self,
+        updates: impl IntoIterator<Item = (PinId, T)>,
+        merge: impl Fn(T, PinSettings) -> PinSettings,
+    ) -> Result<Vec<(PinId, Result<PinSettings, AppError>)>, AppError> {
+        let _update = self.settings_update.lock().await;
+
+        let mut merged = Vec::new();
+        let mut errors = Vec::new();
+        for (pin_id, update) in updates {
+            let checked = async {
+                let current = self.get_pin_settings(pin_id).await?;
+                let settings = merge(update, current);
+                self.check_pin_settings(pin_id, &settings)?;
+                Ok::<_, AppError>(settings)
+            }
+            .await;
+            match checked {
+                Ok(settings) => merged.push((pin_id, settings)),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(AppError::Validation(errors));
+        }
+
+        let mut results = Vec::with_capacity(merged.len());
+        for (pin_id, settings) in merged {
+            let result = self
+                .checked_set_pin_settings(pin_id, &settings)
+                .await
+                .map(|()| settings);
+            results.push((pin_id, result));
+        }
+
+        Ok(results)
+    }
+
+    /// `update_pin_settings` for callers already holding `settings_update`.
+    async fn merge_pin_settings(
+        &self,
+        pin_id: PinId,
+        if_version: Option<u64>,
+        merge: impl FnOnce(PinSettings) -> Result<PinSettings, AppError>,
+    ) -> Result<(PinSettings, u64), AppError> {
+        let version = self.settings_version(pin_id).await?;
+        if let Some(expected) = if_version
+            && expected != version
+        {
+            return Err(AppError::Conflict(format!(
+                "pin {pin_id} settings are at version {version}, not {expected}"
+            )));
+        }
+
+        let merged = merge(self.backend.get_settings(pin_id).await?)?;
+        self.checked_set_pin_settings(pin_id, &merged).await?;
+        let version = self.backend.settings_version(pin_id).await?;
+
+        Ok((merged, version))
+    }
+
+    /// Checks settings against the config of a pin without applying them.
+    pub fn check_pin_settings(
+        &self,
+        pin_id: PinId,
+        settings: &PinSettings,
+    ) -> Result<PinConfig, AppError> {
         let cfg = self.pin_config(pin_id)?;
+        if cfg.read_only && Self::drives(settings) {
+            return Err(AppError::PermissionDenied(format!(
+                "pin {pin_id} is read-only"
+            )));
+        }
+        Self::check_settings(pin_id, &cfg, settings)?;
+
+        Ok(cfg)
+    }
+
+    fn drives(settings: &PinSettings) -> bool {
+        settings.state.is_writable() || settings.state == GpioState::Pwm
+    }
+
+    async fn checked_set_pin_settings(
+        &self,
+        pin_id: PinId,
+        settings: &PinSettings,
+    ) -> Result<(), AppError> {
+        let cfg = self.check_pin_settings(pin_id, settings)?;
+        if Self::drives(settings) {
+            self.check_not_halted()?;
+        }
+
+        self.apply_pin_settings(pin_id, &cfg, settings).await
+    }
 
+    fn check_settings(
+        pin_id: PinId,
+        cfg: &PinConfig,
+        settings: &PinSettings,
+    ) -> Result<(), AppError> {
         if !Self::capability_matches(settings.state, &cfg.capabilities) {
-            return Err(AppError::InvalidState(format!(
-                "state not supported by pin {pin_id}"
-            )));
+            return Err(AppError::InvalidPinState {
+                pin_id,
+                reason: format!("state {} not supported", settings.state),
+            });
         }
 
-        let handler = if settings.edge != EdgeDetect::None {
-            if !settings.state.is_edge_detectable() {
-                return Err(AppError::InvalidState(format!(
-                    "edge detection requires an input-capable state by pin {pin_id}",
-                )));
-            }
-            Some(self.event_handler.clone())
-        } else {
-            None
+        if settings.state == GpioState::Pwm && !cfg.capabilities.iter().any(|c| c.is_writable()) {
+            return Err(AppError::InvalidPinState {
+                pin_id,
+                reason: "pwm requires an output-capable pin".into(),
+            });
+        }
+
+        settings.validate().map_err(|e| match e {
+            AppError::InvalidState(reason) => AppError::InvalidPinState { pin_id, reason },
+            e => e,
+        })
+    }
+
+    async fn apply_pin_settings(
+        &self,
+        pin_id: PinId,
+        cfg: &PinConfig,
+        settings: &PinSettings,
+    ) -> Result<(), AppError> {
+        Self::check_settings(pin_id, cfg, settings)?;
+        let handler = match settings.edge {
+            EdgeDetect::None => None,
+            _ => Some(self.event_handler.clone()),
+        };
+
+        let old = match &self.audit_log {
+            Some(_) => self.backend.get_settings(pin_id).await.ok(),
+            None => None,
+        };
+        self.backend
+            .set_settings(pin_id, cfg, settings, handler)
+            .await?;
+        self.audit(
+            "set_settings",
+            pin_id,
+            old.map(json_value),
+            json_value(settings),
+        );
+
+        // software debounce replaces the backend one, see the backends
+        let window_ms = match settings.edge {
+            EdgeDetect::None => 0,
+            _ if settings.software_debounce => settings.debounce_ms,
+            _ => 0,
         };
+        self.event_handler.set_software_debounce(pin_id, window_ms);
+        self.event_handler
+            .set_coalesce_window(pin_id, settings.coalesce_ms);
+
+        #[cfg(feature = "metrics")]
+        self.event_handler.metrics.record_set_settings();
 
-        self.backend.set_settings(pin_id, cfg, settings, handler)
+        Ok(())
     }
 
-    pub async fn read_value(&self, pin_id: u32) -> Result<u8, AppError> {
-        let value = self.backend.read_value(pin_id)?;
+    pub async fn read_value(&self, pin_id: PinId) -> Result<u8, AppError> {
+        let value = self.backend.read_value(pin_id).await?;
 
         Ok(value)
     }
 
-    pub async fn write_value(&self, pin_id: u32, value: u8) -> Result<(), AppError> {
-        if value > 1 {
-            return Err(AppError::InvalidValue("value must be 0 or 1".into()));
+    pub async fn read_hardware_value(&self, pin_id: PinId) -> Result<u8, AppError> {
+        self.backend.read_hardware_value(pin_id).await
+    }
+
+    /// Reads the line of a pin `samples` times, `interval_ms` apart, and
+    /// answers the value most reads agree on. A tie goes to the last read.
+    /// Outputs are sampled too, never answered from their written value.
+    pub async fn read_value_voted(
+        &self,
+        pin_id: PinId,
+        samples: usize,
+        interval_ms: u64,
+    ) -> Result<VotedValue, AppError> {
+        if !(1..=MAX_VOTE_SAMPLES).contains(&samples) {
+            return Err(AppError::ValueOutOfRange {
+                pin_id: Some(pin_id),
+                value: samples as i64,
+                min: 1,
+                max: MAX_VOTE_SAMPLES as i64,
+            });
+        }
+        if interval_ms > MAX_VOTE_INTERVAL_MS {
+            return Err(AppError::ValueOutOfRange {
+                pin_id: Some(pin_id),
+                value: interval_ms as i64,
+                min: 0,
+                max: MAX_VOTE_INTERVAL_MS as i64,
+            });
         }
 
-        self.pin_config(pin_id)?;
-        self.backend.write_value(pin_id, value)?;
+        let mut reads = Vec::with_capacity(samples);
+        for i in 0..samples {
+            if i > 0 {
+                tokio::time::sleep(Duration::from_millis(interval_ms)).await;
+            }
+            reads.push(self.backend.read_hardware_value(pin_id).await?);
+        }
+        let high = reads.iter().filter(|v| **v == 1).count();
+        let value = match (2 * high).cmp(&samples) {
+            std::cmp::Ordering::Greater => 1,
+            std::cmp::Ordering::Less => 0,
+            std::cmp::Ordering::Equal => reads[samples - 1],
+        };
+
+        Ok(VotedValue {
+            value,
+            samples: reads,
+        })
+    }
+
+    pub async fn read_values(
+        &self,
+        pins: Option<&[PinId]>,
+    ) -> HashMap<PinId, Result<u8, AppError>> {
+        let ids: Vec<PinId> = match pins {
+            Some(pins) => pins.to_vec(),
+            None => self.config().gpios.keys().copied().collect(),
+        };
+
+        let mut values = HashMap::with_capacity(ids.len());
+        let mut enabled = Vec::with_capacity(ids.len());
+        for pin_id in ids {
+            match self.get_pin_settings(pin_id).await {
+                Ok(settings) if settings.state == GpioState::Disabled => {}
+                Ok(_) => enabled.push(pin_id),
+                Err(e) => {
+                    values.insert(pin_id, Err(e));
+                }
+            }
+        }
+
+        match self.backend.read_values(&enabled).await {
+            Ok(read) => values.extend(read.into_iter().map(|(pin_id, v)| (pin_id, Ok(v)))),
+            // fall back to single reads so each failing pin reports its own error
+            Err(_) => {
+                for pin_id in enabled {
+                    values.insert(pin_id, self.backend.read_value(pin_id).await);
+                }
+            }
+        }
+
+        values
+    }
+
+    async fn backend_write(&self, pin_id: PinId, value: u8) -> Result<(), AppError> {
+        self.backend.write_value(pin_id, value).await?;
+
+        #[cfg(feature = "metrics")]
+        self.event_handler.metrics.record_value(pin_id, value);
+
+        Ok(())
+    }
+
+    pub async fn write_value(&self, pin_id: PinId, value: u8) -> Result<(), AppError> {
+        check_value(Some(pin_id), value.into())?;
+
+        self.writable_pin_config(pin_id)?;
+        let old = self.audited_value(pin_id).await;
+        self.backend_write(pin_id, value).await?;
+        self.audit("set_value", pin_id, old, value.into());
 
         Ok(())
     }
 
+    pub async fn write_values(&self, mut values: Vec<(PinId, u8)>) -> Result<(), AppError> {
+        for (pin_id, value) in &values {
+            check_value(Some(*pin_id), (*value).into())?;
+            self.writable_pin_config(*pin_id)?;
+        }
+        // the last value given for a pin wins
+        values.sort_by_key(|(pin_id, _)| *pin_id);
+        values.dedup_by(|later, kept| {
+            let duplicate = later.0 == kept.0;
+            if duplicate {
+                kept.1 = later.1;
+            }
+            duplicate
+        });
+
+        let mut old = Vec::with_capacity(values.len());
+        for (pin_id, _) in &values {
+            old.push(self.audited_value(*pin_id).await);
+        }
+        self.backend.set_values(&values).await?;
+        for ((pin_id, value), old) in values.iter().zip(old) {
+            self.audit("set_value", *pin_id, old, (*value).into());
+        }
+
+        #[cfg(feature = "metrics")]
+        for (pin_id, value) in &values {
+            self.event_handler.metrics.record_value(*pin_id, *value);
+        }
+
+        Ok(())
+    }
+
+    /// Current value of a pin for its audit record, skipped when not auditing.
+    async fn audited_value(&self, pin_id: PinId) -> Option<serde_json::Value> {
+        self.audit_log.as_ref()?;
+        self.backend.read_value(pin_id).await.ok().map(Into::into)
+    }
+
+    fn audit(
+        &self,
+        op: &'static str,
+        pin_id: PinId,
+        old: Option<serde_json::Value>,
+        new: serde_json::Value,
+    ) {
+        let Some(log) = &self.audit_log else {
+            return;
+        };
+        let request = RequestContext::current();
+        let api_key = request
+            .as_ref()
+            .and_then(|r| r.api_key.as_deref())
+            .and_then(|key| {
+                let config = self.config();
+                let index = config.auth.api_keys.iter().position(|k| k == key)?;
+                Some(format!("api_keys[{index}]"))
+            });
+
+        log.append(&AuditRecord {
+            timestamp_ms: self.event_handler.now_ms(),
+            op,
+            pin_id,
+            old,
+            new,
+            request_id: request.map(|r| r.id.0),
+            api_key,
+        });
+    }
+
+    fn writable_pin_config(&self, pin_id: PinId) -> Result<PinConfig, AppError> {
+        let cfg = self.pin_config(pin_id)?;
+        if cfg.read_only {
+            return Err(AppError::PermissionDenied(format!(
+                "pin {pin_id} is read-only"
+            )));
+        }
+        self.check_not_halted()?;
+
+        Ok(cfg)
+    }
+
+    fn check_not_halted(&self) -> Result<(), AppError> {
+        if self.halted.load(Ordering::Acquire) {
+            return Err(AppError::Locked("outputs are halted until resumed".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Emergency stop: rejects writes and output settings until `resume`,
+    /// then drives every output to its `safe_value` (or 0), stops pwm and
+    /// disables edge detection. Edge detection stays off after resuming.
+    pub async fn halt(&self) {
+        self.halted.store(true, Ordering::Release);
+        // settings changes that passed the halt check finish before the sweep
+        let _update = self.settings_update.lock().await;
+
+        let config = self.config();
+        for pin_id in Self::sorted_pin_ids(&config) {
+            let cfg = &config.gpios[&pin_id];
+            let Ok(settings) = self.backend.get_settings(pin_id).await else {
+                continue;
+            };
+            let mut stopped = match settings.state {
+                GpioState::Pwm => Some(PinSettings {
+                    state: [
+                        GpioState::PushPull,
+                        GpioState::OpenDrain,
+                        GpioState::OpenSource,
+                    ]
+                    .into_iter()
+                    .find(|state| cfg.capabilities.contains(state))
+                    .unwrap_or(GpioState::Disabled),
+                    pwm: None,
+                    bias: None,
+                    ..settings.clone()
+                }),
+                _ if settings.edge != EdgeDetect::None => Some(PinSettings {
+                    edge: EdgeDetect::None,
+                    software_debounce: false,
+                    coalesce_ms: 0,
+                    ..settings.clone()
+                }),
+                _ => None,
+            };
+            if let Some(stop) = &stopped
+                && let Err(e) = self.apply_pin_settings(pin_id, cfg, stop).await
+            {
+                // releasing the line is the last way to stop pwm or edges
+                warn!("failed to stop pin {pin_id}, disabling it: {e}");
+                let disabled = PinSettings::default();
+                if let Err(e) = self.apply_pin_settings(pin_id, cfg, &disabled).await {
+                    warn!("failed to disable pin {pin_id}: {e}");
+                }
+                stopped = Some(disabled);
+            }
+
+            let state = stopped.map_or(settings.state, |s| s.state);
+            let value = cfg.safe_value.unwrap_or(0);
+            if state.is_writable()
+                && let Err(e) = self.backend_write(pin_id, value).await
+            {
+                warn!("failed to drive pin {pin_id} to its safe value: {e}");
+            }
+        }
+    }
+
+    /// Accepts writes again after `halt`.
+    pub fn resume(&self) {
+        self.halted.store(false, Ordering::Release);
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted.load(Ordering::Acquire)
+    }
+
+    pub fn server_info(&self) -> ServerInfo {
+        ServerInfo {
+            backend: self.backend.kind(),
+            libgpiod_version: self.backend.library_version(),
+            crate_version: env!("CARGO_PKG_VERSION"),
+        }
+    }
+
+    pub(crate) async fn ensure_writable(&self, pin_id: PinId) -> Result<(), AppError> {
+        self.writable_pin_config(pin_id)?;
+
+        let settings = self.backend.get_settings(pin_id).await?;
+        if !settings.state.is_writable() {
+            return Err(AppError::InvalidPinState {
+                pin_id,
+                reason: "pin must be in output mode to set value".into(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Merges output settings into the current ones, see
+    /// `update_pin_settings`, and drives `value` in one call. The pin is
+    /// disabled again when the write fails, so it never stays an output
+    /// driving an unknown level. No other settings change gets in between.
+    pub async fn configure_and_write(
+        &self,
+        pin_id: PinId,
+        value: u8,
+        merge: impl FnOnce(PinSettings) -> Result<PinSettings, AppError>,
+    ) -> Result<(), AppError> {
+        check_value(Some(pin_id), value.into())?;
+
+        let _update = self.settings_update.lock().await;
+        self.merge_pin_settings(pin_id, None, |current| {
+            let settings = merge(current)?;
+            if !settings.state.is_writable() {
+                return Err(AppError::InvalidPinState {
+                    pin_id,
+                    reason: format!("cannot set value on {} pin", settings.state),
+                });
+            }
+            Ok(settings)
+        })
+        .await?;
+        if let Err(e) = self.write_value(pin_id, value).await {
+            if let Err(rollback) = self
+                .checked_set_pin_settings(pin_id, &PinSettings::default())
+                .await
+            {
+                warn!("failed to disable pin {pin_id} after a failed write: {rollback}");
+            }
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    pub async fn toggle_value(&self, pin_id: PinId) -> Result<u8, AppError> {
+        self.ensure_writable(pin_id).await?;
+
+        let value = self.backend.read_value(pin_id).await? ^ 1;
+        self.backend_write(pin_id, value).await?;
+        self.audit("toggle", pin_id, Some((value ^ 1).into()), value.into());
+
+        Ok(value)
+    }
+
+    pub async fn pulse(&self, pin_id: PinId, value: u8, duration_ms: u64) -> Result<(), AppError> {
+        check_value(Some(pin_id), value.into())?;
+        check_pulse_duration(pin_id, duration_ms)?;
+        self.ensure_writable(pin_id).await?;
+
+        let old = self.audited_value(pin_id).await;
+        self.backend_write(pin_id, value).await?;
+        let pulse = serde_json::json!({ "value": value, "duration_ms": duration_ms });
+        self.audit("pulse", pin_id, old, pulse);
+        tokio::time::sleep(Duration::from_millis(duration_ms)).await;
+        // a halt during the pulse has already driven the safe value
+        self.check_not_halted()?;
+        self.backend_write(pin_id, value ^ 1).await
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> &Metrics {
+        &self.event_handler.metrics
+    }
+
+    pub fn config(&self) -> Arc<AppConfig> {
+        self.config.read().clone()
+    }
+
+    pub fn event_handler(&self) -> EventHandler {
+        self.event_handler.clone()
+    }
+
     pub fn subscribe_events(&self) -> broadcast::Receiver<EdgeEvent> {
-        self.event_handler.event_tx.subscribe()
+        self.event_handler.subscribe()
+    }
+
+    /// Counts events a subscriber obtained from `subscribe_events` skipped.
+    pub fn record_event_lag(&self, skipped: u64) {
+        self.event_handler.record_lag(skipped);
+    }
+
+    pub fn event_stream_stats(&self) -> EventStreamStats {
+        self.event_handler.stream_stats()
     }
 
     pub async fn get_events(
         &self,
-        pin_id: u32,
+        pin_id: PinId,
         limit: Option<usize>,
+        edge: Option<EdgeDetect>,
+        since_ms: Option<u64>,
     ) -> Result<Vec<EdgeEvent>, AppError> {
         self.pin_config(pin_id)?;
-        let map = &self.event_handler.event_history;
+        let map = self.event_handler.event_history.read();
+        let edge = edge.filter(|e| *e != EdgeDetect::None);
 
         Ok(map
             .get(&pin_id)
             .map(|d| {
-                let events: Vec<EdgeEvent> = if let Some(lim) = limit {
-                    d.read().iter().rev().take(lim).cloned().collect()
-                } else {
-                    d.read().iter().cloned().collect()
-                };
-                events.into_iter().rev().collect()
+                let mut events: Vec<EdgeEvent> = d
+                    .read()
+                    .iter()
+                    .rev()
+                    .filter(|e| since_ms.is_none_or(|since| e.timestamp_ms > since))
+                    .filter(|e| edge.is_none_or(|edge| e.edge == edge))
+                    .take(limit.unwrap_or(usize::MAX))
+                    .cloned()
+                    .collect();
+                events.reverse();
+                events
             })
             .unwrap_or_default())
     }
 
-    pub async fn get_last_event(&self, pin_id: u32) -> Result<Option<EdgeEvent>, AppError> {
+    /// The histories of all pins merged in `timestamp_ms` order, ties broken
+    /// by seq. `limit` keeps the latest events of the merged set.
+    pub fn get_all_events(
+        &self,
+        limit: Option<usize>,
+        edge: Option<EdgeDetect>,
+        since_ms: Option<u64>,
+    ) -> Vec<EdgeEvent> {
+        let map = self.event_handler.event_history.read();
+        let edge = edge.filter(|e| *e != EdgeDetect::None);
+
+        let mut events: Vec<EdgeEvent> = map
+            .values()
+            .flat_map(|history| {
+                history
+                    .read()
+                    .iter()
+                    .filter(|e| since_ms.is_none_or(|since| e.timestamp_ms > since))
+                    .filter(|e| edge.is_none_or(|edge| e.edge == edge))
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        events.sort_unstable_by_key(|e| (e.timestamp_ms, e.seq));
+        let skip = events.len().saturating_sub(limit.unwrap_or(usize::MAX));
+        events.drain(..skip);
+        events
+    }
+
+    /// The last `per_pin` events of every pin, or of `pin_id` only, ordered
+    /// by seq across pins.
+    pub fn recent_events(&self, pin_id: Option<PinId>, per_pin: usize) -> Vec<EdgeEvent> {
+        let map = self.event_handler.event_history.read();
+        let mut events: Vec<EdgeEvent> = map
+            .iter()
+            .filter(|(id, _)| pin_id.is_none_or(|p| p == **id))
+            .flat_map(|(_, history)| {
+                let history = history.read();
+                let skip = history.len().saturating_sub(per_pin);
+                history.iter().skip(skip).cloned().collect::<Vec<_>>()
+            })
+            .collect();
+        events.sort_unstable_by_key(|e| e.seq);
+        events
+    }
+
+    pub fn current_event_seq(&self) -> u64 {
+        self.event_handler.current_seq()
+    }
+
+    pub async fn clear_events(&self, pin_id: PinId) -> Result<(), AppError> {
+        self.pin_config(pin_id)?;
+
+        if let Some(history) = self.event_handler.event_history.read().get(&pin_id) {
+            history.write().clear();
+        }
+        Ok(())
+    }
+
+    pub async fn event_stats(&self, pin_id: PinId) -> Result<EventStats, AppError> {
         self.pin_config(pin_id)?;
-        let map = &self.event_handler.event_history;
+        Ok(self.event_handler.stats(pin_id))
+    }
+
+    pub async fn reset_event_stats(&self, pin_id: PinId) -> Result<(), AppError> {
+        self.pin_config(pin_id)?;
+        self.event_handler.reset_stats(pin_id);
+        Ok(())
+    }
+
+    pub async fn get_last_event(&self, pin_id: PinId) -> Result<Option<EdgeEvent>, AppError> {
+        self.pin_config(pin_id)?;
+        let map = self.event_handler.event_history.read();
 
         Ok(map.get(&pin_id).and_then(|d| d.read().back().cloned()))
     }
+
+    /// Waits up to `timeout` for the next event of a pin dispatched after
+    /// the call, `None` when none arrives in time.
+    pub async fn next_event(
+        &self,
+        pin_id: PinId,
+        timeout: Duration,
+    ) -> Result<Option<EdgeEvent>, AppError> {
+        self.pin_config(pin_id)?;
+        let mut events = self.subscribe_events();
+
+        let next = async {
+            loop {
+                match events.recv().await {
+                    Ok(event) if event.pin_id == pin_id => return Some(event),
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        self.record_event_lag(skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        };
+
+        Ok(tokio::time::timeout(timeout, next).await.ok().flatten())
+    }
+}
+
+impl<B: GpioBackend> Drop for GenericGpioManager<B> {
+    /// Outputs still held when the manager goes away, including on panic
+    /// unwinding, are left at their `safe_value` rather than wherever the
+    /// last write put them. The backend releases the lines once it drops.
+    fn drop(&mut self) {
+        if self.config().gpios.values().all(|p| p.safe_value.is_none()) {
+            return;
+        }
+
+        // drop may run inside a runtime that must not be blocked on, or
+        // outside of any, so drive the writes from a thread of our own
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                match tokio::runtime::Builder::new_current_thread()
+                    .enable_time()
+                    .build()
+                {
+                    Ok(rt) => rt.block_on(self.apply_safe_values()),
+                    Err(e) => warn!("failed to apply safe values: {e}"),
+                }
+            });
+        });
+    }
+}
+
+fn json_value<T: Serialize>(value: T) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or_default()
 }
@@ -1,16 +1,46 @@
+#[cfg(feature = "json-logs")]
+mod access_log;
+mod audit;
+mod auth;
 mod backend;
+mod clock;
 mod config;
 mod error;
 mod gpio;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod openapi;
+mod rate_limit;
+mod request_id;
 mod routes;
+#[cfg(feature = "systemd")]
+mod systemd;
+mod webhook;
 
-pub use config::{AppConfig, EdgeDetect, GpioCapability, HttpConfig, PinConfig};
+pub use clock::{Clock, MockClock, SystemClock};
+pub use config::{
+    AppConfig, AppConfigBuilder, AuthConfig, CorsConfig, EdgeDetect, EventClockSource,
+    GpioCapability, HttpConfig, LagPolicy, MqttConfig, PinConfig, RateLimitConfig, SimulateConfig,
+    TlsConfig, WebhookConfig,
+};
 pub use error::AppError;
 pub use gpio::{
-    EdgeEvent, EventHandler, GpioBackend, GpioManager, GpioState, PinDescriptor, PinSettings,
+    ActivePin, Bias, ChipInfo, EdgeEvent, EventHandler, EventStats, EventStreamStats, GpioBackend,
+    GpioManager, GpioState, LineInfo, MAX_DEBOUNCE_MS, MAX_PULSE_MS, MAX_VOTE_INTERVAL_MS,
+    MAX_VOTE_SAMPLES, PinDescriptor, PinId, PinSettings, PwmConfig, ReloadSummary, ServerInfo,
+    VotedValue,
 };
+#[cfg(feature = "metrics")]
+pub use metrics::Metrics;
+#[cfg(feature = "mqtt")]
+pub use mqtt::spawn_mqtt_bridge;
+pub use request_id::RequestId;
 pub use routes::AppState;
+#[cfg(feature = "systemd")]
+pub use systemd::{ActivatedListener, activated_listeners, listen_fds};
 
 #[cfg(feature = "hardware-gpio")]
 pub use backend::LibgpiodBackend;
-pub use backend::MockGpioBackend;
+pub use backend::{MockGpioBackend, RetryPolicy};
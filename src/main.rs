@@ -1,37 +1,63 @@
-use log::info;
+use log::{info, warn};
 use std::fs;
-use std::os::unix::fs::PermissionsExt;
+use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
 
-use actix_web::{App, HttpServer, web};
+use actix_web::rt::signal::unix::{SignalKind, signal};
+use actix_web::{App, HttpServer, middleware::Condition, web};
 
-use gmgr::{AppConfig, AppState, GpioManager};
+use gmgr::{AppConfig, AppState, GpioManager, RequestId};
 
 #[cfg(feature = "hardware-gpio")]
 use gmgr::LibgpiodBackend;
 #[cfg(not(feature = "hardware-gpio"))]
 use gmgr::MockGpioBackend;
+#[cfg(feature = "mqtt")]
+use gmgr::spawn_mqtt_bridge;
+#[cfg(feature = "systemd")]
+use gmgr::{ActivatedListener, activated_listeners};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    env_logger::init();
+    env_logger::Builder::from_default_env()
+        .format(|buf, record| {
+            let ts = buf.timestamp();
+            let (level, target, args) = (record.level(), record.target(), record.args());
+            // lines logged while serving a request carry its id
+            match RequestId::current() {
+                Some(RequestId(id)) => writeln!(buf, "[{ts} {level} {target} {id}] {args}"),
+                None => writeln!(buf, "[{ts} {level} {target}] {args}"),
+            }
+        })
+        .init();
 
     let config_path = std::env::args()
         .nth(1)
         .or_else(|| std::env::var("GMGR_CONFIG").ok())
         .unwrap_or_else(|| "config.json".to_string());
-    let config = Arc::new(
-        AppConfig::load_from_file(&config_path)
-            .unwrap_or_else(|e| panic!("failed to load config: {e}")),
-    );
+    let mut config = AppConfig::load_from_file(&config_path)
+        .unwrap_or_else(|e| panic!("failed to load config: {e}"));
+    config
+        .apply_env_overrides()
+        .unwrap_or_else(|e| panic!("failed to apply env overrides: {e}"));
+    let config = Arc::new(config);
 
     let backend = {
         #[cfg(feature = "hardware-gpio")]
         {
             Arc::new(
-                LibgpiodBackend::new()
-                    .unwrap_or_else(|e| panic!("failed to init libgpiod backend: {e}")),
+                LibgpiodBackend::new(
+                    config.libgpiod_event_buffer,
+                    std::time::Duration::from_millis(config.libgpiod_wait_timeout_ms),
+                    config.libgpiod_event_clock,
+                    gmgr::RetryPolicy {
+                        attempts: config.libgpiod_retry_attempts,
+                        delay: std::time::Duration::from_millis(config.libgpiod_retry_delay_ms),
+                    },
+                )
+                .unwrap_or_else(|e| panic!("failed to init libgpiod backend: {e}"))
+                .with_consumer(config.consumer_label()),
             )
         }
         #[cfg(not(feature = "hardware-gpio"))]
@@ -41,53 +67,146 @@ async fn main() -> std::io::Result<()> {
     };
 
     let manager = Arc::new(GpioManager::new(config.clone(), backend));
-    let app_state = AppState { manager };
+    manager.initialize().await;
+    let app_state = AppState {
+        manager: manager.clone(),
+        config_path: Some(config_path.into()),
+    };
+
+    if config
+        .http
+        .cors
+        .as_ref()
+        .is_some_and(|c| c.allows_any_origin())
+    {
+        warn!("CORS allows any origin, restrict http.cors.allowed_origins in production");
+    }
+
+    #[cfg(feature = "mqtt")]
+    if let Some(mqtt) = &config.mqtt {
+        spawn_mqtt_bridge(manager.clone(), mqtt)
+            .unwrap_or_else(|e| panic!("failed to start mqtt bridge: {e}"));
+        info!(
+            "MQTT bridge publishing to {} on {}",
+            mqtt.base_topic, mqtt.broker
+        );
+    }
+
+    // SIGHUP re-reads the config file like POST /admin/reload
+    let reload_state = app_state.clone();
+    actix_web::rt::spawn(async move {
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => {
+                warn!("SIGHUP reload disabled: {e}");
+                return;
+            }
+        };
+        while hangups.recv().await.is_some() {
+            match reload_state.reload_config().await {
+                Ok(summary) => info!("Config reloaded: {summary}"),
+                Err(e) => warn!("Config reload failed, keeping the current config: {e}"),
+            }
+        }
+    });
 
     let http_cfg = config.http.clone();
     let server = HttpServer::new(move || {
-        let scope_path = http_cfg.path.clone();
+        let cors = http_cfg.cors.as_ref().map(|c| c.middleware());
         App::new()
+            .wrap(Condition::new(cors.is_some(), cors.unwrap_or_default()))
             .app_data(web::Data::new(app_state.clone()))
-            .service(app_state.api_scope(&scope_path))
+            .service(app_state.probe_services())
+            .service(app_state.api_scopes())
     });
+    let server = match config.http.request_timeout() {
+        Some(timeout) => server.client_request_timeout(timeout).keep_alive(timeout),
+        None => server,
+    };
 
-    let bind_addrs: String;
     let http_cfg = config.http.clone();
-    let server = match (&http_cfg.unix_socket, &http_cfg.host) {
-        (Some(socket_path), Some(host)) => {
-            if Path::new(socket_path).exists() {
-                fs::remove_file(socket_path)?;
+    let mut bind_addrs = Vec::new();
+    let mut server = server;
+
+    // sockets passed by systemd replace the configured ones
+    #[cfg(feature = "systemd")]
+    for listener in
+        activated_listeners().unwrap_or_else(|e| panic!("failed to take systemd sockets: {e}"))
+    {
+        server = match listener {
+            ActivatedListener::Unix(listener) => {
+                let addr = listener.local_addr()?;
+                let path = addr.as_pathname().unwrap_or(Path::new("unnamed"));
+                bind_addrs.push(format!("{} (systemd)", path.display()));
+
+                server.listen_uds(listener)?
             }
-            bind_addrs = format!("{} and {}", socket_path, host);
+            ActivatedListener::Tcp(listener) => {
+                let addr = listener.local_addr()?;
+                match &http_cfg.tls {
+                    #[cfg(feature = "tls")]
+                    Some(tls) => {
+                        let tls_config = tls
+                            .server_config()
+                            .unwrap_or_else(|e| panic!("failed to load tls config: {e}"));
+                        bind_addrs.push(format!("https://{addr} (systemd)"));
 
-            server.bind_uds(socket_path)?.bind_auto_h2c(host)?
+                        server.listen_rustls_0_23(listener, tls_config)?
+                    }
+                    _ => {
+                        bind_addrs.push(format!("{addr} (systemd)"));
+
+                        server.listen_auto_h2c(listener)?
+                    }
+                }
+            }
+        };
+    }
+
+    if bind_addrs.is_empty() {
+        if http_cfg.unix_socket.is_none() && http_cfg.host.is_none() {
+            panic!("config error: either 'unix_socket' or both 'host' and 'port' must be specified")
         }
-        (Some(socket_path), None) => {
+
+        if let Some(socket_path) = &http_cfg.unix_socket {
             if Path::new(socket_path).exists() {
                 fs::remove_file(socket_path)?;
             }
-            bind_addrs = socket_path.clone();
+            bind_addrs.push(socket_path.clone());
 
-            server.bind_uds(socket_path)?
+            server = server.bind_uds(socket_path)?;
         }
-        (None, Some(host)) => {
-            bind_addrs = host.clone();
+        if let Some(host) = &http_cfg.host {
+            server = match &http_cfg.tls {
+                #[cfg(feature = "tls")]
+                Some(tls) => {
+                    let tls_config = tls
+                        .server_config()
+                        .unwrap_or_else(|e| panic!("failed to load tls config: {e}"));
+                    bind_addrs.push(format!("https://{host}"));
 
-            server.bind_auto_h2c(host)?
-        }
-        _ => {
-            panic!("config error: either 'unix_socket' or both 'host' and 'port' must be specified")
-        }
-    };
+                    server.bind_rustls_0_23(host, tls_config)?
+                }
+                _ => {
+                    bind_addrs.push(host.clone());
 
-    if let Some(socket_path) = &config.http.unix_socket {
-        if let Some(mode) = config.http.socket_mode() {
-            fs::set_permissions(socket_path, fs::Permissions::from_mode(mode))?;
-            info!("Set unix socket permissions to {:o}", mode);
+                    server.bind_auto_h2c(host)?
+                }
+            };
         }
+
+        config
+            .http
+            .apply_socket_mode()
+            .unwrap_or_else(|e| panic!("failed to start: {e}"));
     }
 
-    info!("GMGR server starting on {}...", bind_addrs);
+    info!("GMGR server starting on {}...", bind_addrs.join(" and "));
+
+    // the server stops gracefully on SIGINT/SIGTERM, then lines are released
+    let result = server.run().await;
+    info!("GMGR server stopped, releasing GPIO lines...");
+    manager.shutdown().await;
 
-    server.run().await
+    result
 }
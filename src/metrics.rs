@@ -0,0 +1,85 @@
+use prometheus::{Encoder, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+
+use crate::config::EdgeDetect;
+use crate::error::AppError;
+use crate::gpio::PinId;
+
+pub struct Metrics {
+    registry: Registry,
+    edge_events_total: IntCounterVec,
+    pin_value: IntGaugeVec,
+    set_settings_total: IntCounter,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let edge_events_total = IntCounterVec::new(
+            Opts::new("gmgr_edge_events_total", "Edge events dispatched per pin"),
+            &["pin_id", "edge"],
+        )
+        .expect("valid edge events metric");
+        let pin_value = IntGaugeVec::new(
+            Opts::new("gmgr_pin_value", "Last value written to a pin"),
+            &["pin_id"],
+        )
+        .expect("valid pin value metric");
+        let set_settings_total =
+            IntCounter::new("gmgr_set_settings_total", "Successful pin settings changes")
+                .expect("valid set settings metric");
+
+        for collector in [
+            Box::new(edge_events_total.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(pin_value.clone()),
+            Box::new(set_settings_total.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metrics registered once");
+        }
+
+        Self {
+            registry,
+            edge_events_total,
+            pin_value,
+            set_settings_total,
+        }
+    }
+
+    pub fn record_edge(&self, pin_id: PinId, edge: EdgeDetect) {
+        let edge = match edge {
+            EdgeDetect::None => "none",
+            EdgeDetect::Rising => "rising",
+            EdgeDetect::Falling => "falling",
+            EdgeDetect::Both => "both",
+        };
+        self.edge_events_total
+            .with_label_values(&[pin_id.to_string().as_str(), edge])
+            .inc();
+    }
+
+    pub fn record_value(&self, pin_id: PinId, value: u8) {
+        self.pin_value
+            .with_label_values(&[pin_id.to_string().as_str()])
+            .set(value as i64);
+    }
+
+    pub fn record_set_settings(&self) {
+        self.set_settings_total.inc();
+    }
+
+    pub fn encode(&self) -> Result<String, AppError> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| AppError::Gpio(format!("metrics encode: {e}")))?;
+        String::from_utf8(buffer).map_err(|e| AppError::Gpio(format!("metrics encode: {e}")))
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
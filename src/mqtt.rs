@@ -0,0 +1,120 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::rt;
+use log::{info, warn};
+use rumqttc::{AsyncClient, Event, EventLoop, MqttOptions, Packet, Publish, QoS};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config::MqttConfig;
+use crate::error::AppError;
+use crate::gpio::{GpioBackend, GpioManager, PinId, parse_value};
+
+const REQUEST_CAPACITY: usize = 64;
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Publishes every event to `{base_topic}/{pin_id}/event` and writes values
+/// received on `{base_topic}/{pin_id}/set`. The connection is retried with
+/// backoff for as long as the bridge runs.
+pub fn spawn_mqtt_bridge<B: GpioBackend + 'static>(
+    manager: Arc<GpioManager<B>>,
+    config: &MqttConfig,
+) -> Result<(), AppError> {
+    let (host, port) = config.broker_addr()?;
+    let mut options = MqttOptions::new(config.client_id.clone(), host, port);
+    options.set_keep_alive(KEEP_ALIVE);
+    let (client, eventloop) = AsyncClient::new(options, REQUEST_CAPACITY);
+
+    let base_topic = config.base_topic.trim_end_matches('/').to_string();
+    rt::spawn(publish_events(
+        manager.clone(),
+        client.clone(),
+        base_topic.clone(),
+    ));
+    rt::spawn(drive_eventloop(manager, client, eventloop, base_topic));
+
+    Ok(())
+}
+
+async fn publish_events<B: GpioBackend>(
+    manager: Arc<GpioManager<B>>,
+    client: AsyncClient,
+    base_topic: String,
+) {
+    let mut events = manager.subscribe_events();
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("mqtt bridge fell behind, skipped {skipped} events");
+                manager.record_event_lag(skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        let topic = format!("{base_topic}/{}/event", event.pin_id);
+        let payload = serde_json::to_vec(&event).unwrap_or_default();
+        if let Err(e) = client
+            .publish(topic, QoS::AtLeastOnce, false, payload)
+            .await
+        {
+            warn!("mqtt publish error: {e}");
+            break;
+        }
+    }
+}
+
+async fn drive_eventloop<B: GpioBackend>(
+    manager: Arc<GpioManager<B>>,
+    client: AsyncClient,
+    mut eventloop: EventLoop,
+    base_topic: String,
+) {
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        match eventloop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                info!("mqtt connected, subscribing to {base_topic}/+/set");
+                backoff = INITIAL_BACKOFF;
+                // subscriptions are not kept across reconnects
+                let filter = format!("{base_topic}/+/set");
+                if let Err(e) = client.subscribe(filter, QoS::AtLeastOnce).await {
+                    warn!("mqtt subscribe error: {e}");
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                if let Err(e) = apply_set(&manager, &base_topic, &publish).await {
+                    warn!("mqtt set on {} failed: {e}", publish.topic);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("mqtt connection error, retrying in {backoff:?}: {e}");
+                rt::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+async fn apply_set<B: GpioBackend>(
+    manager: &GpioManager<B>,
+    base_topic: &str,
+    publish: &Publish,
+) -> Result<(), AppError> {
+    let pin_id = publish
+        .topic
+        .strip_prefix(base_topic)
+        .and_then(|t| t.strip_prefix('/'))
+        .and_then(|t| t.strip_suffix("/set"))
+        .and_then(|id| id.parse::<PinId>().ok())
+        .ok_or_else(|| AppError::InvalidValue("invalid pin id".into()))?;
+    let value = std::str::from_utf8(&publish.payload)
+        .map_err(|_| AppError::InvalidValue("value payload must be valid UTF-8".into()))
+        .and_then(parse_value)?;
+
+    manager.write_value(pin_id, value).await
+}
@@ -0,0 +1,519 @@
+use serde_json::{Map, Value, json};
+
+/// Hand-maintained OpenAPI 3 description of the api scope, served with
+/// `base_path` as its server url. Keep it in step with `api_scope`.
+pub(crate) fn document(base_path: &str) -> Value {
+    let pin_id = json!({
+        "name": "pin_id",
+        "in": "path",
+        "required": true,
+        "schema": { "type": "integer", "minimum": 0 }
+    });
+    let pin = [pin_id.clone()];
+
+    let mut paths = Map::new();
+    paths.insert(
+        "/info".into(),
+        json!({ "get": op("Backend kind and versions", &[], None, Some(schema_ref("ServerInfo"))) }),
+    );
+    paths.insert(
+        "/chips".into(),
+        json!({ "get": op("List gpio chips and line usage", &[], None, Some(array_of("ChipInfo"))) }),
+    );
+    paths.insert(
+        "/gpios".into(),
+        json!({
+            "get": op(
+                "List all pins, paginated with offset/limit",
+                &[
+                    query("offset", "integer"),
+                    query("limit", "integer"),
+                    query("capability", "string"),
+                    query("tag", "string"),
+                ],
+                None,
+                Some(json!({ "type": "object", "additionalProperties": schema_ref("PinDescriptor") })),
+            )
+        }),
+    );
+    paths.insert(
+        "/gpios/active".into(),
+        json!({
+            "get": op(
+                "Enabled pins with their current value",
+                &[],
+                None,
+                Some(json!({ "type": "object", "additionalProperties": schema_ref("ActivePin") })),
+            )
+        }),
+    );
+    paths.insert(
+        "/gpios/settings".into(),
+        json!({
+            "post": op(
+                "Set settings for many pins, keyed by pin id",
+                &[],
+                Some(json!({ "type": "object", "additionalProperties": schema_ref("PinSettings") })),
+                Some(json!({ "type": "object" })),
+            )
+        }),
+    );
+    paths.insert(
+        "/gpios/values".into(),
+        json!({
+            "get": op("Read values of enabled pins", &[query("pins", "string")], None, Some(value_map())),
+            "post": op("Write many output pins at once", &[], Some(value_map()), None)
+        }),
+    );
+    paths.insert(
+        "/gpios/export.csv".into(),
+        json!({
+            "get": {
+                "summary": "Export every pin with its settings and value as CSV",
+                "responses": {
+                    "200": {
+                        "description": "OK",
+                        "content": { "text/csv": { "schema": { "type": "string" } } }
+                    }
+                }
+            }
+        }),
+    );
+    paths.insert(
+        "/gpios/import".into(),
+        json!({
+            "post": {
+                "summary": "Apply the settings and value of each pin from an exported snapshot",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "text/csv": { "schema": { "type": "string" } },
+                        "application/json": {
+                            "schema": {
+                                "type": "array",
+                                "items": {
+                                    "type": "object",
+                                    "required": ["pin_id", "state"],
+                                    "properties": {
+                                        "pin_id": { "type": "integer" },
+                                        "state": schema_ref("GpioState"),
+                                        "edge": schema_ref("EdgeDetect"),
+                                        "debounce_ms": { "type": "integer" },
+                                        "software_debounce": { "type": "boolean" },
+                                        "coalesce_ms": { "type": "integer" },
+                                        "bias": { "type": "string", "enum": ["disabled", "pull-up", "pull-down"] },
+                                        "pwm_frequency_hz": { "type": "number" },
+                                        "pwm_duty_cycle": { "type": "number" },
+                                        "value": { "type": "integer", "enum": [0, 1] }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "responses": {
+                    "200": {
+                        "description": "OK",
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "object",
+                                    "properties": {
+                                        "results": { "type": "object" },
+                                        "warnings": { "type": "array", "items": { "type": "string" } }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "default": {
+                        "description": "Error",
+                        "content": { "application/json": { "schema": schema_ref("Error") } }
+                    }
+                }
+            }
+        }),
+    );
+    paths.insert(
+        "/gpios/events".into(),
+        json!({ "get": op("Websocket stream of events for all pins", &[query("replay", "integer")], None, Some(schema_ref("EdgeEvent"))) }),
+    );
+    paths.insert(
+        "/gpios/snapshot/ws".into(),
+        json!({
+            "get": op(
+                "Websocket pushing the values of all enabled pins every interval_ms",
+                &[query("interval_ms", "integer")],
+                None,
+                Some(json!({ "type": "object", "additionalProperties": true })),
+            )
+        }),
+    );
+    paths.insert(
+        "/gpios/events/sse".into(),
+        json!({ "get": op("Server-sent events for all pins", &[query("pin", "integer")], None, Some(schema_ref("EdgeEvent"))) }),
+    );
+    paths.insert(
+        "/gpios/events/history".into(),
+        json!({
+            "get": op(
+                "Event histories of all pins merged in time order",
+                &[query("limit", "integer"), query("edge", "string"), query("since_ms", "integer")],
+                None,
+                Some(array_of("EdgeEvent")),
+            )
+        }),
+    );
+    paths.insert(
+        "/gpios/events/stats".into(),
+        json!({ "get": op("Subscriber and event totals of the event stream", &[], None, Some(schema_ref("EventStreamStats"))) }),
+    );
+    paths.insert(
+        "/gpio/by-name/{name}".into(),
+        json!({
+            "get": op(
+                "Describe a pin by its configured name",
+                &[json!({ "name": "name", "in": "path", "required": true, "schema": { "type": "string" } })],
+                None,
+                Some(schema_ref("PinDescriptor")),
+            )
+        }),
+    );
+    paths.insert(
+        "/gpio/{pin_id}".into(),
+        json!({
+            "get": op("Describe a pin", &pin, None, Some(schema_ref("PinDescriptor"))),
+            "post": op(
+                "Apply output settings and write a value in one call",
+                &pin,
+                Some(json!({
+                    "allOf": [
+                        schema_ref("PinSettings"),
+                        {
+                            "type": "object",
+                            "required": ["value"],
+                            "properties": { "value": { "type": "integer", "enum": [0, 1] } }
+                        }
+                    ]
+                })),
+                Some(schema_ref("PinDescriptor")),
+            )
+        }),
+    );
+    paths.insert(
+        "/gpio/{pin_id}/info".into(),
+        json!({ "get": op("Configured pin info", &pin, None, Some(schema_ref("PinConfig"))) }),
+    );
+    paths.insert(
+        "/gpio/{pin_id}/settings".into(),
+        json!({
+            "get": op(
+                "Current pin settings, as cached or as the line reports them",
+                &[pin_id.clone(), query("source", "string")],
+                None,
+                Some(schema_ref("PinSettings")),
+            ),
+            "post": op(
+                "Update pin settings, 409 when If-Match names a stale ETag",
+                &[pin_id.clone(), json!({ "name": "If-Match", "in": "header", "required": false, "schema": { "type": "string" } })],
+                Some(schema_ref("PinSettings")),
+                Some(schema_ref("PinSettings")),
+            ),
+            "delete": no_content("Disable the pin and release its line", &pin)
+        }),
+    );
+    paths.insert(
+        "/gpio/{pin_id}/value".into(),
+        json!({
+            "get": op("Read the pin value", &[pin_id.clone(), query("verbose", "boolean"), query("force", "boolean"), query("samples", "integer"), query("interval_ms", "integer")], None, Some(json!({ "type": "integer", "enum": [0, 1] }))),
+            "post": op("Write the pin value", &pin, Some(json!({ "oneOf": [
+                { "type": "integer", "enum": [0, 1] },
+                { "type": "string", "enum": ["true", "false", "high", "low"] }
+            ] })), None)
+        }),
+    );
+    paths.insert(
+        "/gpio/{pin_id}/events/ws".into(),
+        json!({ "get": op("Websocket stream of events for one pin", &[pin_id.clone(), query("replay", "integer")], None, Some(schema_ref("EdgeEvent"))) }),
+    );
+    paths.insert(
+        "/gpio/{pin_id}/toggle".into(),
+        json!({ "post": op("Invert an output pin", &pin, None, Some(json!({ "type": "integer" }))) }),
+    );
+    paths.insert(
+        "/gpio/{pin_id}/pulse".into(),
+        json!({
+            "post": op(
+                "Drive a value for a duration, then restore",
+                &[pin_id.clone(), query("blocking", "boolean")],
+                Some(json!({
+                    "type": "object",
+                    "required": ["value", "duration_ms"],
+                    "properties": {
+                        "value": { "type": "integer", "enum": [0, 1] },
+                        "duration_ms": { "type": "integer", "maximum": 60000 }
+                    }
+                })),
+                None,
+            )
+        }),
+    );
+    paths.insert(
+        "/gpio/{pin_id}/event".into(),
+        json!({ "get": op("Last event of the pin", &pin, None, Some(schema_ref("EdgeEvent"))) }),
+    );
+    paths.insert(
+        "/gpio/{pin_id}/event/next".into(),
+        json!({
+            "get": op(
+                "Wait for the next event of the pin, 204 on timeout",
+                &[pin_id.clone(), query("timeout_ms", "integer")],
+                None,
+                Some(schema_ref("EdgeEvent")),
+            )
+        }),
+    );
+    paths.insert(
+        "/gpio/{pin_id}/events".into(),
+        json!({
+            "get": op(
+                "Event history of the pin",
+                &[pin_id.clone(), query("limit", "integer"), query("edge", "string"), query("since_ms", "integer")],
+                None,
+                Some(array_of("EdgeEvent")),
+            ),
+            "delete": no_content("Clear the event history of the pin", &pin)
+        }),
+    );
+    paths.insert(
+        "/gpio/{pin_id}/stats".into(),
+        json!({
+            "get": op("Edge counts of the pin", &pin, None, Some(schema_ref("EventStats"))),
+            "delete": no_content("Reset the edge counts of the pin", &pin)
+        }),
+    );
+    paths.insert(
+        "/admin/reload".into(),
+        json!({ "post": op("Reload the pin table from the config file", &[], None, Some(json!({ "type": "object" }))) }),
+    );
+    let halted = json!({ "type": "object", "properties": { "halted": { "type": "boolean" } } });
+    paths.insert(
+        "/admin/panic".into(),
+        json!({ "post": op("Drive outputs to their safe values and reject writes with 423 until resumed", &[], None, Some(halted.clone())) }),
+    );
+    paths.insert(
+        "/admin/resume".into(),
+        json!({ "post": op("Accept writes again after a panic", &[], None, Some(halted)) }),
+    );
+    #[cfg(feature = "metrics")]
+    paths.insert(
+        "/metrics".into(),
+        json!({ "get": op("Prometheus metrics in text format", &[], None, None) }),
+    );
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "gmgr",
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "servers": [{ "url": base_path }],
+        "paths": paths,
+        "components": { "schemas": schemas() }
+    })
+}
+
+/// An operation answering 200, with a json body when `response` is set.
+fn op(summary: &str, parameters: &[Value], body: Option<Value>, response: Option<Value>) -> Value {
+    let ok = match response {
+        Some(schema) => {
+            json!({ "description": "OK", "content": { "application/json": { "schema": schema } } })
+        }
+        None => json!({ "description": "OK" }),
+    };
+    let mut op = json!({
+        "summary": summary,
+        "parameters": parameters,
+        "responses": {
+            "200": ok,
+            "default": {
+                "description": "Error",
+                "content": { "application/json": { "schema": schema_ref("Error") } }
+            }
+        }
+    });
+    if let Some(body) = body {
+        op["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": body } }
+        });
+    }
+    op
+}
+
+/// An operation answering 204 without a body.
+fn no_content(summary: &str, parameters: &[Value]) -> Value {
+    let mut op = op(summary, parameters, None, None);
+    let responses = op["responses"].as_object_mut().unwrap();
+    responses.remove("200");
+    responses.insert("204".into(), json!({ "description": "No Content" }));
+    op
+}
+
+fn query(name: &str, ty: &str) -> Value {
+    json!({ "name": name, "in": "query", "required": false, "schema": { "type": ty } })
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{name}") })
+}
+
+fn array_of(name: &str) -> Value {
+    json!({ "type": "array", "items": schema_ref(name) })
+}
+
+fn value_map() -> Value {
+    json!({ "type": "object", "additionalProperties": { "type": "integer", "enum": [0, 1] } })
+}
+
+fn schemas() -> Value {
+    let capability = json!({
+        "type": "string",
+        "enum": [
+            "error", "disabled", "push-pull", "open-drain", "open-source",
+            "floating", "pull-up", "pull-down", "pwm"
+        ]
+    });
+
+    json!({
+        "GpioState": capability,
+        "EdgeDetect": { "type": "string", "enum": ["none", "rising", "falling", "both"] },
+        "PwmConfig": {
+            "type": "object",
+            "required": ["frequency_hz", "duty_cycle"],
+            "properties": {
+                "frequency_hz": { "type": "number" },
+                "duty_cycle": { "type": "number", "minimum": 0, "maximum": 1 }
+            }
+        },
+        "PinSettings": {
+            "type": "object",
+            "properties": {
+                "state": schema_ref("GpioState"),
+                "edge": schema_ref("EdgeDetect"),
+                "debounce_ms": { "type": "integer" },
+                "software_debounce": { "type": "boolean" },
+                "coalesce_ms": { "type": "integer", "minimum": 0 },
+                "pwm": schema_ref("PwmConfig"),
+                "drive_strength_ma": { "type": "integer", "minimum": 1 },
+                "bias": { "type": "string", "enum": ["disabled", "pull-up", "pull-down"] }
+            }
+        },
+        "PinConfig": {
+            "type": "object",
+            "required": ["name", "chip", "line", "capabilities"],
+            "properties": {
+                "name": { "type": "string" },
+                "chip": { "type": "string" },
+                "line": { "type": "integer" },
+                "capabilities": { "type": "array", "items": schema_ref("GpioState") },
+                "active_low": { "type": "boolean" },
+                "read_only": { "type": "boolean" },
+                "initial_state": schema_ref("GpioState"),
+                "initial_value": { "type": "integer", "enum": [0, 1] },
+                "safe_value": { "type": "integer", "enum": [0, 1] },
+                "tags": { "type": "array", "items": { "type": "string" } }
+            }
+        },
+        "PinDescriptor": {
+            "type": "object",
+            "required": ["info", "settings"],
+            "properties": {
+                "info": schema_ref("PinConfig"),
+                "settings": schema_ref("PinSettings")
+            }
+        },
+        "ServerInfo": {
+            "type": "object",
+            "required": ["backend", "libgpiod_version", "crate_version"],
+            "properties": {
+                "backend": { "type": "string", "enum": ["libgpiod", "mock"] },
+                "libgpiod_version": { "type": "string", "nullable": true },
+                "crate_version": { "type": "string" }
+            }
+        },
+        "ActivePin": {
+            "type": "object",
+            "required": ["info", "settings"],
+            "properties": {
+                "info": schema_ref("PinConfig"),
+                "settings": schema_ref("PinSettings"),
+                "value": { "type": "integer", "enum": [0, 1] }
+            }
+        },
+        "EdgeEvent": {
+            "type": "object",
+            "required": ["pin_id", "edge", "timestamp_ms", "seq"],
+            "properties": {
+                "pin_id": { "type": "integer" },
+                "edge": schema_ref("EdgeDetect"),
+                "timestamp_ms": { "type": "integer" },
+                "seq": { "type": "integer" },
+                "count": { "type": "integer", "minimum": 1 }
+            }
+        },
+        "EventStats": {
+            "type": "object",
+            "required": ["rising_count", "falling_count"],
+            "properties": {
+                "rising_count": { "type": "integer" },
+                "falling_count": { "type": "integer" },
+                "last_event_ms": { "type": "integer", "nullable": true }
+            }
+        },
+        "EventStreamStats": {
+            "type": "object",
+            "required": ["subscribers", "dispatched", "lagged"],
+            "properties": {
+                "subscribers": { "type": "integer" },
+                "dispatched": { "type": "integer" },
+                "lagged": { "type": "integer" }
+            }
+        },
+        "LineInfo": {
+            "type": "object",
+            "properties": {
+                "offset": { "type": "integer" },
+                "name": { "type": "string", "nullable": true },
+                "consumer": { "type": "string", "nullable": true },
+                "used": { "type": "boolean" }
+            }
+        },
+        "ChipInfo": {
+            "type": "object",
+            "properties": {
+                "path": { "type": "string" },
+                "name": { "type": "string" },
+                "label": { "type": "string" },
+                "num_lines": { "type": "integer" },
+                "lines": array_of("LineInfo")
+            }
+        },
+        "Error": {
+            "type": "object",
+            "required": ["error", "code"],
+            "properties": {
+                "error": { "type": "string" },
+                "code": {
+                    "type": "string",
+                    "enum": [
+                        "pin_not_found", "invalid_state", "invalid_value", "validation_error",
+                        "permission_denied", "config_error", "gpio_error", "unavailable", "timeout"
+                    ]
+                },
+                "pin_id": { "type": "integer" },
+                "errors": { "type": "array", "items": { "type": "string" } }
+            }
+        }
+    })
+}
@@ -0,0 +1,104 @@
+use std::time::{Duration, Instant};
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{Error, web};
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+
+use crate::auth::API_KEY_HEADER;
+use crate::config::RateLimitConfig;
+use crate::error::AppError;
+
+/// Buckets beyond this count get pruned of clients that are back at burst.
+const MAX_IDLE_BUCKETS: usize = 1024;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Per-client token buckets shared by every write route of a scope.
+pub(crate) struct WriteLimiter {
+    config: RateLimitConfig,
+    by_api_key: bool,
+    buckets: Mutex<FxHashMap<String, Bucket>>,
+}
+
+impl WriteLimiter {
+    pub(crate) fn new(config: RateLimitConfig, by_api_key: bool) -> Self {
+        Self {
+            config,
+            by_api_key,
+            buckets: Mutex::new(FxHashMap::default()),
+        }
+    }
+
+    /// Takes a token for `client`, or returns how long until one is available.
+    fn acquire(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        let RateLimitConfig {
+            writes_per_second: rate,
+            burst,
+        } = self.config;
+        let burst = burst as f64;
+        let mut buckets = self.buckets.lock();
+
+        if buckets.len() > MAX_IDLE_BUCKETS {
+            buckets.retain(|_, b| {
+                b.tokens + now.duration_since(b.updated).as_secs_f64() * rate < burst
+            });
+        }
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+
+    fn client_key(&self, req: &ServiceRequest) -> String {
+        // keys are already checked by the scope when auth is enabled
+        if self.by_api_key
+            && let Some(key) = req
+                .headers()
+                .get(API_KEY_HEADER)
+                .and_then(|v| v.to_str().ok())
+        {
+            return format!("key:{key}");
+        }
+        match req.peer_addr() {
+            Some(addr) => format!("ip:{}", addr.ip()),
+            None => "local".into(),
+        }
+    }
+}
+
+/// Answers 429 with `Retry-After` once a client runs out of write tokens.
+/// Passes everything through when no rate limit is configured.
+pub(crate) async fn limit_writes(
+    limiter: Option<web::Data<WriteLimiter>>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    if let Some(limiter) = limiter
+        && let Err(wait) = limiter.acquire(&limiter.client_key(&req), Instant::now())
+    {
+        // round up so clients never retry before a token is back
+        let retry_after = Duration::from_secs(wait.as_secs_f64().ceil().max(1.0) as u64);
+        let err = AppError::RateLimited(retry_after);
+        return Ok(req.error_response(err).map_into_right_body());
+    }
+
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
@@ -0,0 +1,134 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpMessage, web};
+
+use crate::auth::API_KEY_HEADER;
+use crate::gpio::GpioBackend;
+use crate::routes::AppState;
+
+pub(crate) const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+tokio::task_local! {
+    static REQUEST: RequestContext;
+}
+
+/// Caller details kept in scope while a request is served.
+#[derive(Debug, Clone)]
+pub(crate) struct RequestContext {
+    pub id: RequestId,
+    /// The `X-API-Key` sent, only checked against the config when auth is on.
+    pub api_key: Option<String>,
+    /// Successful answers are wrapped as `{"data","error"}`, see
+    /// `http.response_envelope`.
+    pub envelope: bool,
+}
+
+impl RequestContext {
+    pub(crate) fn current() -> Option<RequestContext> {
+        REQUEST.try_with(RequestContext::clone).ok()
+    }
+
+    /// Wraps `fut` in the context of the request being served, so a task
+    /// spawned for it still logs and audits under that request.
+    pub(crate) fn carry<F: Future>(fut: F) -> impl Future<Output = F::Output> {
+        let ctx = Self::current();
+        async move {
+            match ctx {
+                Some(ctx) => REQUEST.scope(ctx, fut).await,
+                None => fut.await,
+            }
+        }
+    }
+
+    /// Whether the request being served answers in the envelope.
+    pub(crate) fn envelope() -> bool {
+        REQUEST.try_with(|ctx| ctx.envelope).unwrap_or(false)
+    }
+}
+
+/// Id of the request being served, taken from `X-Request-Id` or generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// The id of the request whose handler is currently running, if any.
+    pub fn current() -> Option<RequestId> {
+        REQUEST.try_with(|ctx| ctx.id.clone()).ok()
+    }
+
+    fn generate() -> Self {
+        static PREFIX: OnceLock<u64> = OnceLock::new();
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+
+        let prefix = PREFIX.get_or_init(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0)
+        });
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        Self(format!("{prefix:x}-{n:x}"))
+    }
+
+    fn from_header(value: &HeaderValue) -> Option<Self> {
+        let id = value.to_str().ok()?.trim();
+        let valid = !id.is_empty()
+            && id.len() <= MAX_REQUEST_ID_LEN
+            && id.bytes().all(|b| b.is_ascii_graphic());
+        valid.then(|| Self(id.to_string()))
+    }
+}
+
+/// Reuses the client's `X-Request-Id` or assigns one, keeps it in the request
+/// extensions and in scope for error bodies and logs, and echoes it back.
+pub(crate) async fn assign_request_id<B: GpioBackend + 'static>(
+    state: web::Data<AppState<B>>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(RequestId::from_header)
+        .unwrap_or_else(RequestId::generate);
+    req.extensions_mut().insert(id.clone());
+    let header = HeaderValue::from_str(&id.0).expect("request ids are visible ascii");
+    let api_key = req
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let envelope = state.manager.config().http.response_envelope;
+
+    REQUEST
+        .scope(
+            RequestContext {
+                id,
+                api_key,
+                envelope,
+            },
+            async move {
+                match next.call(req).await {
+                    Ok(mut res) => {
+                        res.headers_mut().insert(REQUEST_ID_HEADER, header);
+                        Ok(res)
+                    }
+                    // render middleware errors while the id is still in scope
+                    Err(e) => {
+                        let mut res = e.error_response();
+                        res.headers_mut().insert(REQUEST_ID_HEADER, header);
+                        Err(InternalError::from_response(e, res).into())
+                    }
+                }
+            },
+        )
+        .await
+}
@@ -1,26 +1,58 @@
 use log::warn;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
-use actix_web::{HttpRequest, HttpResponse, Responder, guard, http::Method, web};
-use actix_ws::{Message, MessageStream, Session};
-use serde::Deserialize;
+use actix_web::body::MessageBody;
+use actix_web::dev::{HttpServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, ETag, EntityTag, IfMatch};
+use actix_web::middleware::{Next, from_fn};
+use actix_web::{
+    HttpMessage, HttpRequest, HttpResponse, HttpResponseBuilder, Responder, guard, http::Method,
+    web,
+};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
+use tokio::time::{Instant, MissedTickBehavior};
 use tokio_stream::StreamExt;
-use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::{BroadcastStream, IntervalStream};
 
-use crate::config::EdgeDetect;
+#[cfg(feature = "json-logs")]
+use crate::access_log::json_access_log;
+use crate::auth::require_api_key;
+use crate::config::{AppConfig, EdgeDetect, GpioCapability, LagPolicy};
 use crate::error::AppError;
-use crate::gpio::{EdgeEvent, GpioBackend, GpioManager, GpioState, PinSettings};
+use crate::gpio::{
+    Bias, EdgeEvent, GpioBackend, GpioManager, GpioState, PinDescriptor, PinId, PinSettings,
+    PwmConfig, ReloadSummary, check_pulse_duration, check_value, parse_value,
+};
+use crate::openapi;
+use crate::rate_limit::{WriteLimiter, limit_writes};
+use crate::request_id::{RequestContext, assign_request_id};
+
+const SSE_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+const EVENT_SEQ_HEADER: &str = "x-event-seq";
+const DEFAULT_EVENT_POLL_MS: u64 = 5_000;
+const MAX_EVENT_POLL_MS: u64 = 60_000;
+const DEFAULT_SNAPSHOT_INTERVAL_MS: u64 = 1_000;
+/// Shorter snapshot intervals are raised to this, every tick reads all pins.
+const MIN_SNAPSHOT_INTERVAL_MS: u64 = 100;
 
 pub struct AppState<B: GpioBackend> {
     pub manager: Arc<GpioManager<B>>,
+    /// Config file the server started from, reloads re-read it.
+    pub config_path: Option<PathBuf>,
 }
 
 impl<B: GpioBackend> Clone for AppState<B> {
     fn clone(&self) -> Self {
         Self {
             manager: Arc::clone(&self.manager),
+            config_path: self.config_path.clone(),
         }
     }
 }
@@ -30,37 +62,354 @@ struct SettingsPayload {
     state: Option<GpioState>,
     edge: Option<EdgeDetect>,
     debounce_ms: Option<u64>,
+    software_debounce: Option<bool>,
+    coalesce_ms: Option<u64>,
+    pwm: Option<PwmConfig>,
+    drive_strength_ma: Option<u32>,
+    bias: Option<Bias>,
+}
+
+#[derive(Deserialize)]
+struct ConfigurePayload {
+    #[serde(flatten)]
+    settings: SettingsPayload,
+    value: u8,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum PinResult<T> {
+    Ok(T),
+    Error(String),
+}
+
+impl<T> From<Result<T, AppError>> for PinResult<T> {
+    fn from(result: Result<T, AppError>) -> Self {
+        match result {
+            Ok(v) => PinResult::Ok(v),
+            Err(e) => PinResult::Error(e.to_string()),
+        }
+    }
+}
+
+/// One pin of an exported snapshot, other export columns are ignored.
+#[derive(Deserialize)]
+struct SnapshotRow {
+    pin_id: PinId,
+    state: GpioState,
+    #[serde(default)]
+    edge: EdgeDetect,
+    #[serde(default)]
+    debounce_ms: u64,
+    #[serde(default)]
+    software_debounce: bool,
+    #[serde(default)]
+    coalesce_ms: u64,
+    #[serde(default)]
+    bias: Option<Bias>,
+    #[serde(default)]
+    pwm_frequency_hz: Option<f64>,
+    #[serde(default)]
+    pwm_duty_cycle: Option<f64>,
+    #[serde(default)]
+    value: Option<u8>,
+}
+
+impl SnapshotRow {
+    /// The pwm config of the row, which needs both pwm columns or neither.
+    fn pwm(&self) -> Result<Option<PwmConfig>, AppError> {
+        match (self.pwm_frequency_hz, self.pwm_duty_cycle) {
+            (Some(frequency_hz), Some(duty_cycle)) => Ok(Some(PwmConfig {
+                frequency_hz,
+                duty_cycle,
+            })),
+            (None, None) => Ok(None),
+            _ => Err(AppError::InvalidValue(format!(
+                "pin {} needs both pwm_frequency_hz and pwm_duty_cycle",
+                self.pin_id
+            ))),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ImportReport {
+    results: BTreeMap<PinId, PinResult<PinSettings>>,
+    warnings: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct PulsePayload {
+    value: u8,
+    duration_ms: u64,
+}
+
+#[derive(Deserialize, Default)]
+struct EventPollQuery {
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+struct PulseQuery {
+    #[serde(default)]
+    blocking: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+enum SettingsSource {
+    #[default]
+    Cached,
+    Hardware,
+}
+
+#[derive(Deserialize, Default)]
+struct SettingsQuery {
+    #[serde(default)]
+    source: SettingsSource,
+}
+
+#[derive(Deserialize, Default)]
+struct ValueQuery {
+    #[serde(default)]
+    verbose: bool,
+    /// Read the line even for outputs, instead of their last written value.
+    #[serde(default)]
+    force: bool,
+    /// Reads to take and vote on, see `read_value_voted`.
+    samples: Option<usize>,
+    #[serde(default)]
+    interval_ms: u64,
+}
+
+#[derive(Serialize)]
+struct VerboseValue {
+    pin_id: PinId,
+    value: u8,
+    state: GpioState,
+    read_at_ms: u64,
+}
+
+/// Successful answer body when `http.response_envelope` is on.
+#[derive(Serialize)]
+struct Envelope<T> {
+    data: T,
+    error: Option<String>,
+}
+
+/// `HttpResponseBuilder::json` wrapping the body in an `Envelope` when the
+/// request is served with `http.response_envelope` on.
+trait ReplyJson {
+    fn reply_json(&mut self, body: impl Serialize) -> HttpResponse;
+}
+
+impl ReplyJson for HttpResponseBuilder {
+    fn reply_json(&mut self, body: impl Serialize) -> HttpResponse {
+        if RequestContext::envelope() {
+            self.json(Envelope {
+                data: body,
+                error: None,
+            })
+        } else {
+            self.json(body)
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct ListQuery {
+    offset: Option<usize>,
+    limit: Option<usize>,
+    capability: Option<String>,
+    tag: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PinPage {
+    total: usize,
+    offset: usize,
+    pins: BTreeMap<PinId, PinDescriptor>,
+}
+
+#[derive(Deserialize, Default)]
+struct ValuesQuery {
+    pins: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct SseQuery {
+    pin: Option<PinId>,
+}
+
+#[derive(Deserialize, Default)]
+struct SnapshotQuery {
+    interval_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+struct WsEventsQuery {
+    /// Recent events per pin sent before the live stream.
+    replay: Option<usize>,
 }
 
 #[derive(Deserialize, Default)]
 struct EventsQuery {
     limit: Option<usize>,
+    edge: Option<EdgeDetect>,
+    since_ms: Option<u64>,
 }
 
-async fn handle_event_websocket(
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum WsCommand {
+    GetValue {
+        pin_id: PinId,
+    },
+    SetValue {
+        pin_id: PinId,
+        value: u8,
+    },
+    SetSettings {
+        pin_id: PinId,
+        #[serde(flatten)]
+        settings: SettingsPayload,
+    },
+}
+
+#[derive(Serialize)]
+struct WsReply {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    result: PinResult<serde_json::Value>,
+}
+
+async fn handle_ws_command<B: GpioBackend>(manager: &GpioManager<B>, text: &str) -> WsReply {
+    let request: serde_json::Value = match serde_json::from_str(text) {
+        Ok(request) => request,
+        Err(e) => {
+            return WsReply {
+                id: None,
+                result: PinResult::Error(
+                    AppError::InvalidValue(format!("invalid command: {e}")).to_string(),
+                ),
+            };
+        }
+    };
+    let id = request.get("id").cloned();
+
+    let result = match serde_json::from_value::<WsCommand>(request) {
+        Ok(WsCommand::GetValue { pin_id }) => manager.read_value(pin_id).await.map(Into::into),
+        Ok(WsCommand::SetValue { pin_id, value }) => manager
+            .write_value(pin_id, value)
+            .await
+            .map(|_| value.into()),
+        Ok(WsCommand::SetSettings { pin_id, settings }) => manager
+            .update_pin_settings(pin_id, None, |current| {
+                Ok(merge_settings(settings, current))
+            })
+            .await
+            .and_then(|(merged, _)| {
+                serde_json::to_value(merged).map_err(|e| AppError::Gpio(e.to_string()))
+            }),
+        Err(e) => Err(AppError::InvalidValue(format!("invalid command: {e}"))),
+    };
+
+    WsReply {
+        id,
+        result: result.into(),
+    }
+}
+
+async fn handle_event_websocket<B: GpioBackend>(
+    manager: Arc<GpioManager<B>>,
     mut session: Session,
     mut client_stream: MessageStream,
     rx: broadcast::Receiver<EdgeEvent>,
-    pin_filter: Option<u32>,
+    pin_filter: Option<PinId>,
+    replay: Vec<EdgeEvent>,
 ) {
+    // `rx` subscribed before `replay` was read, so live events up to the last
+    // replayed seq of a pin were already sent. Seqs only order events within
+    // a pin's history, so the check is per pin.
+    let mut replayed: FxHashMap<PinId, u64> = FxHashMap::default();
+    for event in replay {
+        replayed.insert(event.pin_id, event.seq);
+        if let Ok(text) = serde_json::to_string(&event)
+            && session.text(text).await.is_err()
+        {
+            warn!("websocket client disconnected");
+            return;
+        }
+    }
+
     let mut events = BroadcastStream::new(rx);
+    let lag_policy = manager.config().lag_policy;
+    let heartbeat = manager.config().ws_heartbeat();
+    // next ping while the client is answering, the pong deadline while it is not
+    let mut deadline = heartbeat.map(|(interval, _)| Instant::now() + interval);
+    let mut awaiting_pong = false;
+    let idle_timeout = manager.config().http.ws_idle_timeout();
+    let mut last_activity = Instant::now();
 
     loop {
+        let idle_deadline = idle_timeout.map(|timeout| last_activity + timeout);
         tokio::select! {
+            _ = tokio::time::sleep_until(idle_deadline.unwrap_or_else(Instant::now)), if idle_deadline.is_some() => {
+                warn!("closing idle websocket client");
+                let reason = CloseReason {
+                    code: CloseCode::Away,
+                    description: Some("idle timeout".into()),
+                };
+                let _ = session.close(Some(reason)).await;
+                break;
+            }
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                let Some((_, timeout)) = heartbeat else { break; };
+
+                if awaiting_pong {
+                    warn!("closing websocket client that did not answer a ping within {timeout:?}");
+                    let reason = CloseReason {
+                        code: CloseCode::Away,
+                        description: Some("ping timeout".into()),
+                    };
+                    let _ = session.close(Some(reason)).await;
+                    break;
+                }
+                if session.ping(b"").await.is_err() {
+                    warn!("websocket client disconnected");
+                    break;
+                }
+                awaiting_pong = true;
+                deadline = Some(Instant::now() + timeout);
+            }
             msg = client_stream.recv() => {
                 let Some(msg) = msg else { break; };
+                last_activity = Instant::now();
 
                 match msg {
                     Ok(Message::Ping(bytes)) => {
                         let _ = session.pong(&bytes).await;
                     }
+                    Ok(Message::Pong(_)) => {
+                        if awaiting_pong {
+                            awaiting_pong = false;
+                            deadline = heartbeat.map(|(interval, _)| Instant::now() + interval);
+                        }
+                    }
                     Ok(Message::Close(reason)) => {
                         let _ = session.close(reason).await;
                         break;
                     }
-                    Ok(Message::Text(_))
-                    | Ok(Message::Binary(_))
-                    | Ok(Message::Pong(_))
+                    Ok(Message::Text(text)) => {
+                        let reply = handle_ws_command(&manager, &text).await;
+                        if let Ok(text) = serde_json::to_string(&reply)
+                            && session.text(text).await.is_err() {
+                                warn!("websocket client disconnected");
+                                break;
+                            }
+                    }
+                    Ok(Message::Binary(_))
                     | Ok(Message::Continuation(_))
                     | Ok(Message::Nop) => {}
                     Err(_) => break,
@@ -71,6 +420,9 @@ async fn handle_event_websocket(
 
                 match event {
                     Ok(event) => {
+                        if replayed.get(&event.pin_id).is_some_and(|seq| event.seq <= *seq) {
+                            continue;
+                        }
                         if pin_filter.as_ref().map(|p| *p == event.pin_id).unwrap_or(true)
                             && let Ok(text) = serde_json::to_string(&event)
                                 && session.text(text).await.is_err() {
@@ -79,7 +431,18 @@ async fn handle_event_websocket(
                                 }
                     }
                     Err(BroadcastStreamRecvError::Lagged(n)) => {
-                        if session.text(AppError::Gpio(format!("event stream lagged by {n} messages")).to_string()).await.is_err() {
+                        manager.record_event_lag(n);
+                        let message = AppError::Gpio(format!("event stream lagged by {n} messages")).to_string();
+                        if lag_policy == LagPolicy::Disconnect {
+                            warn!("closing websocket client that lagged by {n} events");
+                            let reason = CloseReason {
+                                code: CloseCode::Again,
+                                description: Some(message),
+                            };
+                            let _ = session.close(Some(reason)).await;
+                            break;
+                        }
+                        if session.text(message).await.is_err() {
                             warn!("websocket client lagged and disconnected");
                             break;
                         }
@@ -90,92 +453,850 @@ async fn handle_event_websocket(
     }
 }
 
-impl<B: GpioBackend + 'static> AppState<B> {
-    pub fn api_scope(&self, base_path: &str) -> actix_web::Scope {
-        web::scope(base_path)
-            .service(
-                web::resource("/gpios")
-                    .route(web::get().to(list_gpios::<B>))
-                    .route(
-                        web::route()
-                            .guard(guard_not_methods(&[Method::GET]))
-                            .to(method_not_allowed),
-                    ),
-            )
-            .service(
-                web::resource("/gpios/events")
-                    .route(web::get().to(events_ws_all::<B>))
-                    .route(
-                        web::route()
-                            .guard(guard_not_methods(&[Method::GET]))
-                            .to(method_not_allowed),
-                    ),
-            )
-            .service(
-                web::resource("/gpio/{pin_id}")
-                    .route(web::get().to(pin_descriptor::<B>))
-                    .route(
-                        web::route()
-                            .guard(guard_not_methods(&[Method::GET]))
-                            .to(method_not_allowed),
-                    ),
-            )
-            .service(
-                web::resource("/gpio/{pin_id}/info")
-                    .route(web::get().to(pin_info::<B>))
-                    .route(
-                        web::route()
-                            .guard(guard_not_methods(&[Method::GET]))
-                            .to(method_not_allowed),
-                    ),
-            )
-            .service(
-                web::resource("/gpio/{pin_id}/settings")
-                    .route(web::get().to(get_settings::<B>))
-                    .route(web::post().to(set_settings::<B>))
-                    .route(
-                        web::route()
-                            .guard(guard_not_methods(&[Method::GET, Method::POST]))
-                            .to(method_not_allowed),
-                    ),
-            )
-            .service(
-                web::resource("/gpio/{pin_id}/value")
-                    .route(web::get().to(get_value::<B>))
-                    .route(web::post().to(set_value::<B>))
-                    .route(
-                        web::route()
-                            .guard(guard_not_methods(&[Method::GET, Method::POST]))
-                            .to(method_not_allowed),
-                    ),
-            )
-            .service(
-                web::resource("/gpio/{pin_id}/event")
-                    .route(web::get().to(get_last_event::<B>))
-                    .route(
-                        web::route()
-                            .guard(guard_not_methods(&[Method::GET]))
-                            .to(method_not_allowed),
-                    ),
-            )
-            .service(
-                web::resource("/gpio/{pin_id}/events")
-                    .route(web::get().to(get_events::<B>))
-                    .route(
-                        web::route()
-                            .guard(guard_not_methods(&[Method::GET]))
-                            .to(method_not_allowed),
-                    ),
-            )
+/// Sends the values of all enabled pins every `interval`, shaped like
+/// `GET /gpios/values`, until the client goes away.
+async fn handle_snapshot_websocket<B: GpioBackend>(
+    manager: Arc<GpioManager<B>>,
+    mut session: Session,
+    mut client_stream: MessageStream,
+    interval: Duration,
+) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {
+                let values: HashMap<PinId, PinResult<u8>> = manager
+                    .read_values(None)
+                    .await
+                    .into_iter()
+                    .map(|(pin_id, value)| (pin_id, PinResult::from(value)))
+                    .collect();
+                if let Ok(text) = serde_json::to_string(&values)
+                    && session.text(text).await.is_err() {
+                        warn!("websocket client disconnected");
+                        break;
+                    }
+            }
+            msg = client_stream.recv() => {
+                let Some(msg) = msg else { break; };
+
+                match msg {
+                    Ok(Message::Ping(bytes)) => {
+                        let _ = session.pong(&bytes).await;
+                    }
+                    Ok(Message::Close(reason)) => {
+                        let _ = session.close(reason).await;
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(_) => break,
+                }
+            }
+        }
+    }
+}
+
+impl<B: GpioBackend + 'static> AppState<B> {
+    /// Re-reads `config_path` and applies its pin table to the manager.
+    pub async fn reload_config(&self) -> Result<ReloadSummary, AppError> {
+        let path = self
+            .config_path
+            .as_deref()
+            .ok_or_else(|| AppError::Unavailable("no config file to reload".into()))?;
+        let mut config = AppConfig::load_from_file(path)?;
+        config.apply_env_overrides()?;
+
+        self.manager.reload(config).await
+    }
+
+    pub fn probe_services(&self) -> impl HttpServiceFactory + 'static {
+        (
+            web::resource("/healthz")
+                .route(web::get().to(healthz))
+                .route(
+                    web::route()
+                        .guard(guard_not_methods(&[Method::GET]))
+                        .to(method_not_allowed),
+                ),
+            web::resource("/readyz")
+                .route(web::get().to(readyz::<B>))
+                .route(
+                    web::route()
+                        .guard(guard_not_methods(&[Method::GET]))
+                        .to(method_not_allowed),
+                ),
+        )
+    }
+
+    /// The api mounted under every configured `http.path`, sharing one
+    /// write rate limiter.
+    pub fn api_scopes(&self) -> Vec<impl HttpServiceFactory + use<B>> {
+        let config = self.manager.config();
+        let limiter = self.write_limiter();
+        config
+            .http
+            .path
+            .iter()
+            .map(|base_path| self.scope_with_limiter(base_path, limiter.clone()))
+            .collect()
+    }
+
+    pub fn api_scope(&self, base_path: &str) -> impl HttpServiceFactory + 'static {
+        self.scope_with_limiter(base_path, self.write_limiter())
+    }
+
+    fn write_limiter(&self) -> Option<web::Data<WriteLimiter>> {
+        let config = self.manager.config();
+        config
+            .http
+            .rate_limit
+            .map(|limit| web::Data::new(WriteLimiter::new(limit, config.auth.is_enabled())))
+    }
+
+    fn scope_with_limiter(
+        &self,
+        base_path: &str,
+        limiter: Option<web::Data<WriteLimiter>>,
+    ) -> impl HttpServiceFactory + use<B> {
+        let max_body_bytes = self.manager.config().http.max_body_bytes;
+        let mut scope = web::scope(base_path).app_data(web::PayloadConfig::new(max_body_bytes));
+        if let Some(limiter) = limiter {
+            scope = scope.app_data(limiter);
+        }
+        let scope = scope
+            .wrap(from_fn(require_api_key::<B>))
+            .wrap(from_fn(enforce_timeout::<B>));
+        #[cfg(feature = "json-logs")]
+        let scope = scope.wrap(from_fn(json_access_log));
+        let scope = scope.wrap(from_fn(assign_request_id::<B>));
+
+        #[cfg(feature = "metrics")]
+        let scope = scope.service(
+            web::resource("/metrics")
+                .route(web::get().to(get_metrics::<B>))
+                .route(
+                    web::route()
+                        .guard(guard_not_methods(&[Method::GET]))
+                        .to(method_not_allowed),
+                ),
+        );
+
+        let document = openapi::document(base_path);
+        scope
+            .service(
+                web::resource("/openapi.json")
+                    .route(web::get().to(move || {
+                        let document = document.clone();
+                        async move { HttpResponse::Ok().reply_json(document) }
+                    }))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/info")
+                    .route(web::get().to(get_info::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/chips")
+                    .route(web::get().to(list_chips::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpios")
+                    .route(web::get().to(list_gpios::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpios/active")
+                    .route(web::get().to(list_active_gpios::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpios/settings")
+                    .route(
+                        web::post()
+                            .to(set_settings_batch::<B>)
+                            .wrap(from_fn(limit_writes)),
+                    )
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::POST]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpios/values")
+                    .route(web::get().to(get_values::<B>))
+                    .route(web::post().to(set_values::<B>).wrap(from_fn(limit_writes)))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET, Method::POST]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpios/snapshot/ws")
+                    .route(web::get().to(snapshot_ws::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpios/export.csv")
+                    .route(web::get().to(export_csv::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpios/import")
+                    .route(
+                        web::post()
+                            .to(import_snapshot::<B>)
+                            .wrap(from_fn(limit_writes)),
+                    )
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::POST]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpios/events")
+                    .route(web::get().to(events_ws_all::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpios/events/history")
+                    .route(web::get().to(get_all_events::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpios/events/stats")
+                    .route(web::get().to(event_stream_stats::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpios/events/sse")
+                    .route(web::get().to(events_sse_all::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpio/by-name/{name}")
+                    .route(web::get().to(pin_descriptor_by_name::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpio/{pin_id}")
+                    .route(web::get().to(pin_descriptor::<B>))
+                    .route(
+                        web::post()
+                            .to(configure_pin::<B>)
+                            .wrap(from_fn(limit_writes)),
+                    )
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET, Method::POST]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpio/{pin_id}/info")
+                    .route(web::get().to(pin_info::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpio/{pin_id}/settings")
+                    .route(web::get().to(get_settings::<B>))
+                    .route(
+                        web::post()
+                            .to(set_settings::<B>)
+                            .wrap(from_fn(limit_writes)),
+                    )
+                    .route(web::delete().to(disable_pin::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[
+                                Method::GET,
+                                Method::POST,
+                                Method::DELETE,
+                            ]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpio/{pin_id}/value")
+                    .route(web::get().to(get_value::<B>))
+                    .route(web::post().to(set_value::<B>).wrap(from_fn(limit_writes)))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET, Method::POST]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpio/{pin_id}/events/ws")
+                    .route(web::get().to(events_ws_pin::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpio/{pin_id}/toggle")
+                    .route(
+                        web::post()
+                            .to(toggle_value::<B>)
+                            .wrap(from_fn(limit_writes)),
+                    )
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::POST]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpio/{pin_id}/pulse")
+                    .route(web::post().to(pulse_value::<B>).wrap(from_fn(limit_writes)))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::POST]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpio/{pin_id}/event")
+                    .route(web::get().to(get_last_event::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpio/{pin_id}/event/next")
+                    .route(web::get().to(next_event::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpio/{pin_id}/events")
+                    .route(web::get().to(get_events::<B>))
+                    .route(web::delete().to(clear_events::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET, Method::DELETE]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/gpio/{pin_id}/stats")
+                    .route(web::get().to(get_stats::<B>))
+                    .route(web::delete().to(reset_stats::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::GET, Method::DELETE]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/admin/reload")
+                    .route(web::post().to(reload_config::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::POST]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            // never rate limited, an emergency stop must always get through
+            .service(
+                web::resource("/admin/panic")
+                    .route(web::post().to(halt::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::POST]))
+                            .to(method_not_allowed),
+                    ),
+            )
+            .service(
+                web::resource("/admin/resume")
+                    .route(web::post().to(resume::<B>))
+                    .route(
+                        web::route()
+                            .guard(guard_not_methods(&[Method::POST]))
+                            .to(method_not_allowed),
+                    ),
+            )
+    }
+}
+
+/// Answers 504 when a handler outlives `http.timeout`. Only awaiting
+/// handlers can be cut short, a blocking backend call still runs to the end.
+async fn enforce_timeout<B: GpioBackend + 'static>(
+    state: web::Data<AppState<B>>,
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(timeout) = state.manager.config().http.request_timeout() else {
+        return next.call(req).await;
+    };
+
+    match tokio::time::timeout(timeout, next.call(req)).await {
+        Ok(res) => res,
+        Err(_) => Err(AppError::Timeout(format!("request exceeded {}s", timeout.as_secs())).into()),
+    }
+}
+
+async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn readyz<B: GpioBackend + 'static>(state: web::Data<AppState<B>>) -> HttpResponse {
+    if state.manager.check_ready().await {
+        HttpResponse::Ok().json(serde_json::json!({ "status": "ready" }))
+    } else {
+        HttpResponse::ServiceUnavailable().json(serde_json::json!({ "status": "not ready" }))
+    }
+}
+
+#[cfg(feature = "metrics")]
+async fn get_metrics<B: GpioBackend + 'static>(
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let body = state.manager.metrics().encode()?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(body))
+}
+
+async fn reload_config<B: GpioBackend + 'static>(
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let summary = state.reload_config().await?;
+
+    Ok(HttpResponse::Ok().reply_json(summary))
+}
+
+async fn halt<B: GpioBackend + 'static>(state: web::Data<AppState<B>>) -> HttpResponse {
+    state.manager.halt().await;
+
+    HttpResponse::Ok().reply_json(serde_json::json!({ "halted": true }))
+}
+
+async fn resume<B: GpioBackend + 'static>(state: web::Data<AppState<B>>) -> HttpResponse {
+    state.manager.resume();
+
+    HttpResponse::Ok().reply_json(serde_json::json!({ "halted": false }))
+}
+
+async fn list_chips<B: GpioBackend + 'static>(
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let chips = state.manager.chip_info().await?;
+
+    Ok(HttpResponse::Ok().reply_json(chips))
+}
+
+async fn get_info<B: GpioBackend + 'static>(state: web::Data<AppState<B>>) -> impl Responder {
+    HttpResponse::Ok().reply_json(state.manager.server_info())
+}
+
+async fn list_active_gpios<B: GpioBackend + 'static>(
+    state: web::Data<AppState<B>>,
+) -> impl Responder {
+    HttpResponse::Ok().reply_json(state.manager.list_active_pins().await)
+}
+
+async fn list_gpios<B: GpioBackend + 'static>(
+    query: web::Query<ListQuery>,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let mut pins = state.manager.list_pins().await;
+    if let Some(capabilities) = query.capability.as_deref() {
+        let capabilities = parse_capability_list(capabilities)?;
+        pins.retain(|_, pin| {
+            capabilities
+                .iter()
+                .all(|c| pin.info.capabilities.contains(c))
+        });
+    }
+    if let Some(tags) = query.tag.as_deref() {
+        let tags: Vec<&str> = tags.split(',').map(str::trim).collect();
+        pins.retain(|_, pin| tags.iter().all(|t| pin.info.tags.iter().any(|p| p == t)));
+    }
+    if query.offset.is_none() && query.limit.is_none() {
+        return Ok(HttpResponse::Ok().reply_json(pins));
+    }
+
+    let total = pins.len();
+    let offset = query.offset.unwrap_or(0);
+    let pins = BTreeMap::from_iter(pins)
+        .into_iter()
+        .skip(offset)
+        .take(query.limit.unwrap_or(usize::MAX))
+        .collect();
+
+    Ok(HttpResponse::Ok().reply_json(PinPage {
+        total,
+        offset,
+        pins,
+    }))
+}
+
+async fn set_settings_batch<B: GpioBackend + 'static>(
+    body: web::Bytes,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    if body.is_empty() {
+        return Err(AppError::InvalidValue("empty settings payload".into()));
+    }
+
+    let payloads: BTreeMap<PinId, SettingsPayload> = serde_json::from_slice(&body)
+        .map_err(|e| AppError::InvalidValue(format!("invalid settings payload: {e}")))?;
+
+    // nothing is applied unless every pin passes, all problems are reported
+    let results: HashMap<PinId, PinResult<PinSettings>> = state
+        .manager
+        .update_pins_settings(payloads, merge_settings)
+        .await?
+        .into_iter()
+        .map(|(pin_id, result)| (pin_id, result.into()))
+        .collect();
+
+    Ok(HttpResponse::Ok().reply_json(results))
+}
+
+async fn get_values<B: GpioBackend + 'static>(
+    query: web::Query<ValuesQuery>,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let pins = query.pins.as_deref().map(parse_pin_list).transpose()?;
+
+    let values: HashMap<PinId, PinResult<u8>> = state
+        .manager
+        .read_values(pins.as_deref())
+        .await
+        .into_iter()
+        .map(|(pin_id, value)| (pin_id, PinResult::from(value)))
+        .collect();
+
+    Ok(HttpResponse::Ok().reply_json(values))
+}
+
+/// Quotes a CSV field when it holds a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+async fn export_csv<B: GpioBackend + 'static>(
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let pins: BTreeMap<PinId, PinDescriptor> =
+        state.manager.list_pins().await.into_iter().collect();
+    // disabled pins are skipped by read_values and keep an empty value
+    let values = state.manager.read_values(None).await;
+
+    let mut csv = String::from(
+        "pin_id,name,chip,line,state,edge,debounce_ms,software_debounce,coalesce_ms,bias,\
+         pwm_frequency_hz,pwm_duty_cycle,value\r\n",
+    );
+    for (pin_id, pin) in &pins {
+        let value = match values.get(pin_id) {
+            Some(Ok(value)) => value.to_string(),
+            _ => String::new(),
+        };
+        let settings = &pin.settings;
+        let optional = |field: Option<String>| field.unwrap_or_default();
+        csv.push_str(&format!(
+            "{pin_id},{},{},{},{},{},{},{},{},{},{},{},{value}\r\n",
+            csv_field(&pin.info.name),
+            csv_field(&pin.info.chip),
+            pin.info.line,
+            settings.state,
+            settings.edge,
+            settings.debounce_ms,
+            settings.software_debounce,
+            settings.coalesce_ms,
+            optional(settings.bias.map(|b| b.to_string())),
+            optional(settings.pwm.map(|p| p.frequency_hz.to_string())),
+            optional(settings.pwm.map(|p| p.duty_cycle.to_string())),
+        ));
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .body(csv))
+}
+
+/// Splits CSV text into records, honouring quoted fields.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    records
+}
+
+/// Reads the rows of an exported CSV, matching columns by header name.
+fn parse_snapshot_csv(text: &str) -> Result<Vec<SnapshotRow>, AppError> {
+    let mut records = parse_csv(text).into_iter();
+    let header = records
+        .next()
+        .ok_or_else(|| AppError::InvalidValue("empty snapshot".into()))?;
+    let column = |name: &str| header.iter().position(|h| h.trim() == name);
+    let (Some(pin_col), Some(state_col)) = (column("pin_id"), column("state")) else {
+        return Err(AppError::InvalidValue(
+            "snapshot needs pin_id and state columns".into(),
+        ));
+    };
+    let (edge_col, debounce_col, value_col) =
+        (column("edge"), column("debounce_ms"), column("value"));
+    let (software_debounce_col, coalesce_col, bias_col) = (
+        column("software_debounce"),
+        column("coalesce_ms"),
+        column("bias"),
+    );
+    let (frequency_col, duty_cycle_col) = (column("pwm_frequency_hz"), column("pwm_duty_cycle"));
+
+    records
+        .enumerate()
+        .filter(|(_, record)| record.iter().any(|f| !f.trim().is_empty()))
+        .map(|(i, record)| {
+            let invalid =
+                |e: String| AppError::InvalidValue(format!("snapshot row {}: {e}", i + 1));
+            let field = |col: Option<usize>| {
+                col.and_then(|c| record.get(c))
+                    .map(|f| f.trim())
+                    .filter(|f| !f.is_empty())
+            };
+            let pin_id = field(Some(pin_col))
+                .ok_or_else(|| invalid("missing pin_id".into()))?
+                .parse()
+                .map(PinId)
+                .map_err(|e| invalid(format!("invalid pin_id: {e}")))?;
+            let state = field(Some(state_col))
+                .ok_or_else(|| invalid("missing state".into()))?
+                .parse()
+                .map_err(|e: AppError| invalid(e.to_string()))?;
+            let edge = field(edge_col)
+                .map(str::parse)
+                .transpose()
+                .map_err(|e: AppError| invalid(e.to_string()))?
+                .unwrap_or_default();
+            let debounce_ms = field(debounce_col)
+                .map(str::parse)
+                .transpose()
+                .map_err(|e| invalid(format!("invalid debounce_ms: {e}")))?
+                .unwrap_or(0);
+            let software_debounce = field(software_debounce_col)
+                .map(str::parse)
+                .transpose()
+                .map_err(|e| invalid(format!("invalid software_debounce: {e}")))?
+                .unwrap_or(false);
+            let coalesce_ms = field(coalesce_col)
+                .map(str::parse)
+                .transpose()
+                .map_err(|e| invalid(format!("invalid coalesce_ms: {e}")))?
+                .unwrap_or(0);
+            let bias = field(bias_col)
+                .map(str::parse)
+                .transpose()
+                .map_err(|e: AppError| invalid(e.to_string()))?;
+            let pwm_frequency_hz = field(frequency_col)
+                .map(str::parse)
+                .transpose()
+                .map_err(|e| invalid(format!("invalid pwm_frequency_hz: {e}")))?;
+            let pwm_duty_cycle = field(duty_cycle_col)
+                .map(str::parse)
+                .transpose()
+                .map_err(|e| invalid(format!("invalid pwm_duty_cycle: {e}")))?;
+            let value = field(value_col)
+                .map(str::parse)
+                .transpose()
+                .map_err(|e| invalid(format!("invalid value: {e}")))?;
+
+            Ok(SnapshotRow {
+                pin_id,
+                state,
+                edge,
+                debounce_ms,
+                software_debounce,
+                coalesce_ms,
+                bias,
+                pwm_frequency_hz,
+                pwm_duty_cycle,
+                value,
+            })
+        })
+        .collect()
+}
+
+/// Applies an exported snapshot, as CSV when sent as `text/csv` and as a
+/// JSON array of rows otherwise. Pins missing from the config are skipped.
+async fn import_snapshot<B: GpioBackend + 'static>(
+    req: HttpRequest,
+    body: web::Bytes,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    if body.is_empty() {
+        return Err(AppError::InvalidValue("empty snapshot".into()));
+    }
+
+    let mut rows = if req.content_type() == "text/csv" {
+        let text = std::str::from_utf8(&body)
+            .map_err(|e| AppError::InvalidValue(format!("invalid snapshot: {e}")))?;
+        parse_snapshot_csv(text)?
+    } else {
+        serde_json::from_slice::<Vec<SnapshotRow>>(&body)
+            .map_err(|e| AppError::InvalidValue(format!("invalid snapshot: {e}")))?
+    };
+    rows.sort_by_key(|row| row.pin_id);
+
+    let mut report = ImportReport {
+        results: BTreeMap::new(),
+        warnings: Vec::new(),
+    };
+    for row in rows {
+        if state.manager.get_pin_info(row.pin_id).await.is_err() {
+            report
+                .warnings
+                .push(format!("pin {} is not configured, skipped", row.pin_id));
+            continue;
+        }
+
+        let result = async {
+            let payload = SettingsPayload {
+                state: Some(row.state),
+                edge: Some(row.edge),
+                debounce_ms: Some(row.debounce_ms),
+                software_debounce: Some(row.software_debounce),
+                coalesce_ms: Some(row.coalesce_ms),
+                pwm: row.pwm()?,
+                drive_strength_ma: None,
+                bias: None,
+            };
+            let (merged, _) = state
+                .manager
+                .update_pin_settings(row.pin_id, None, |current| {
+                    let mut merged = merge_settings(payload, current);
+                    // an empty bias column means no bias, not the current one
+                    merged.bias = row.bias;
+                    Ok(merged)
+                })
+                .await?;
+            if let Some(value) = row.value
+                && merged.state.is_writable()
+            {
+                state.manager.write_value(row.pin_id, value).await?;
+            }
+            Ok(merged)
+        }
+        .await;
+        report.results.insert(row.pin_id, PinResult::from(result));
     }
+
+    Ok(HttpResponse::Ok().reply_json(report))
 }
 
-async fn list_gpios<B: GpioBackend + 'static>(
+async fn set_values<B: GpioBackend + 'static>(
+    body: web::Bytes,
     state: web::Data<AppState<B>>,
 ) -> Result<impl Responder, AppError> {
-    let pins = state.manager.list_pins().await;
+    if body.is_empty() {
+        return Err(AppError::InvalidValue("empty values payload".into()));
+    }
+
+    let values: HashMap<PinId, u8> = serde_json::from_slice(&body)
+        .map_err(|e| AppError::InvalidValue(format!("invalid values payload: {e}")))?;
 
-    Ok(web::Json(pins))
+    state
+        .manager
+        .write_values(values.into_iter().collect())
+        .await?;
+
+    Ok(HttpResponse::Ok())
 }
 
 async fn pin_descriptor<B: GpioBackend + 'static>(
@@ -185,7 +1306,44 @@ async fn pin_descriptor<B: GpioBackend + 'static>(
     let pin_id = parse_pin_id(&req)?;
     let desc = state.manager.get_pin_descriptor(pin_id).await?;
 
-    Ok(web::Json(desc))
+    Ok(HttpResponse::Ok().reply_json(desc))
+}
+
+/// Applies settings and a value together, see `configure_and_write`.
+async fn configure_pin<B: GpioBackend + 'static>(
+    req: HttpRequest,
+    body: web::Bytes,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let pin_id = parse_pin_id(&req)?;
+    if body.is_empty() {
+        return Err(AppError::InvalidValue("empty configure payload".into()));
+    }
+    let payload: ConfigurePayload = serde_json::from_slice(&body)
+        .map_err(|e| AppError::InvalidValue(format!("invalid configure payload: {e}")))?;
+
+    state
+        .manager
+        .configure_and_write(pin_id, payload.value, |current| {
+            Ok(merge_settings(payload.settings, current))
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().reply_json(state.manager.get_pin_descriptor(pin_id).await?))
+}
+
+async fn pin_descriptor_by_name<B: GpioBackend + 'static>(
+    req: HttpRequest,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let name = req
+        .match_info()
+        .get("name")
+        .ok_or_else(|| AppError::InvalidValue("missing pin name".into()))?;
+    let pin_id = state.manager.pin_id_by_name(name)?;
+    let desc = state.manager.get_pin_descriptor(pin_id).await?;
+
+    Ok(HttpResponse::Ok().reply_json(desc))
 }
 
 async fn pin_info<B: GpioBackend + 'static>(
@@ -195,17 +1353,24 @@ async fn pin_info<B: GpioBackend + 'static>(
     let pin_id = parse_pin_id(&req)?;
     let info = state.manager.get_pin_info(pin_id).await?;
 
-    Ok(web::Json(info))
+    Ok(HttpResponse::Ok().reply_json(info))
 }
 
 async fn get_settings<B: GpioBackend + 'static>(
     req: HttpRequest,
+    query: web::Query<SettingsQuery>,
     state: web::Data<AppState<B>>,
 ) -> Result<impl Responder, AppError> {
     let pin_id = parse_pin_id(&req)?;
-    let settings = state.manager.get_pin_settings(pin_id).await?;
+    let version = state.manager.settings_version(pin_id).await?;
+    let settings = match query.source {
+        SettingsSource::Cached => state.manager.get_pin_settings(pin_id).await?,
+        SettingsSource::Hardware => state.manager.get_hardware_settings(pin_id).await?,
+    };
 
-    Ok(web::Json(settings))
+    Ok(HttpResponse::Ok()
+        .insert_header(settings_etag(version))
+        .reply_json(settings))
 }
 
 async fn set_settings<B: GpioBackend + 'static>(
@@ -214,23 +1379,85 @@ async fn set_settings<B: GpioBackend + 'static>(
     state: web::Data<AppState<B>>,
 ) -> Result<impl Responder, AppError> {
     let pin_id = parse_pin_id(&req)?;
-    let current = state.manager.get_pin_settings(pin_id).await?;
-    let merged = parse_settings_payload(&body, current)?;
+    let if_version = parse_if_match(&req)?;
+    let (merged, version) = state
+        .manager
+        .update_pin_settings(pin_id, if_version, |current| {
+            parse_settings_payload(&body, current)
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(settings_etag(version))
+        .reply_json(merged))
+}
+
+fn settings_etag(version: u64) -> ETag {
+    ETag(EntityTag::new_strong(version.to_string()))
+}
+
+/// The settings version an `If-Match` header asks for, `None` when it is
+/// absent or `*`.
+fn parse_if_match(req: &HttpRequest) -> Result<Option<u64>, AppError> {
+    if !req.headers().contains_key(header::IF_MATCH) {
+        return Ok(None);
+    }
+    let invalid = || AppError::InvalidValue("If-Match must be one ETag from GET /settings".into());
+    match req.get_header::<IfMatch>().ok_or_else(invalid)? {
+        IfMatch::Any => Ok(None),
+        IfMatch::Items(tags) => match tags.as_slice() {
+            [tag] if !tag.weak => tag.tag().parse().map(Some).map_err(|_| invalid()),
+            _ => Err(invalid()),
+        },
+    }
+}
+
+async fn disable_pin<B: GpioBackend + 'static>(
+    req: HttpRequest,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let pin_id = parse_pin_id(&req)?;
 
-    state.manager.set_pin_settings(pin_id, &merged).await?;
+    state
+        .manager
+        .set_pin_settings(pin_id, &PinSettings::default())
+        .await?;
 
-    Ok(web::Json(merged))
+    Ok(HttpResponse::NoContent().finish())
 }
 
 async fn get_value<B: GpioBackend + 'static>(
     req: HttpRequest,
+    query: web::Query<ValueQuery>,
     state: web::Data<AppState<B>>,
 ) -> Result<impl Responder, AppError> {
     let pin_id = parse_pin_id(&req)?;
+    if let Some(samples) = query.samples {
+        let voted = state
+            .manager
+            .read_value_voted(pin_id, samples, query.interval_ms)
+            .await?;
+        return Ok(HttpResponse::Ok().reply_json(voted));
+    }
+
+    let value = if query.force {
+        state.manager.read_hardware_value(pin_id).await?
+    } else {
+        state.manager.read_value(pin_id).await?
+    };
+    if !query.verbose {
+        return Ok(HttpResponse::Ok().reply_json(value));
+    }
 
-    let value = state.manager.read_value(pin_id).await?;
+    let settings = state.manager.get_pin_settings(pin_id).await?;
+    let read_at_ms = state.manager.event_handler().now_ms();
 
-    Ok(web::Json(value))
+    Ok(HttpResponse::Ok().reply_json(VerboseValue {
+        pin_id,
+        value,
+        state: settings.state,
+        read_at_ms,
+    }))
 }
 
 async fn set_value<B: GpioBackend + 'static>(
@@ -246,6 +1473,88 @@ async fn set_value<B: GpioBackend + 'static>(
     Ok(HttpResponse::Ok())
 }
 
+async fn toggle_value<B: GpioBackend + 'static>(
+    req: HttpRequest,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let pin_id = parse_pin_id(&req)?;
+
+    let value = state.manager.toggle_value(pin_id).await?;
+
+    Ok(HttpResponse::Ok().reply_json(value))
+}
+
+async fn pulse_value<B: GpioBackend + 'static>(
+    req: HttpRequest,
+    query: web::Query<PulseQuery>,
+    body: web::Bytes,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let pin_id = parse_pin_id(&req)?;
+    let payload: PulsePayload = serde_json::from_slice(&body)
+        .map_err(|e| AppError::InvalidValue(format!("invalid pulse payload: {e}")))?;
+
+    if query.blocking {
+        // spawned so a request timeout can't cancel the pulse before it reverts
+        let manager = state.manager.clone();
+        actix_web::rt::spawn(RequestContext::carry(async move {
+            manager
+                .pulse(pin_id, payload.value, payload.duration_ms)
+                .await
+        }))
+        .await
+        .map_err(|e| AppError::Gpio(format!("pulse task failed: {e}")))??;
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    // reject bad requests up front since the spawned pulse can only log failures
+    check_value(Some(pin_id), payload.value.into())?;
+    check_pulse_duration(pin_id, payload.duration_ms)?;
+    state.manager.ensure_writable(pin_id).await?;
+
+    let manager = state.manager.clone();
+    actix_web::rt::spawn(RequestContext::carry(async move {
+        if let Err(e) = manager
+            .pulse(pin_id, payload.value, payload.duration_ms)
+            .await
+        {
+            warn!("pulse on pin {pin_id} failed: {e}");
+        }
+    }));
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+/// Long-polls for the next event of a pin, 204 when none arrives within
+/// `timeout_ms`. The wait has to end before the request timeout does.
+async fn next_event<B: GpioBackend + 'static>(
+    req: HttpRequest,
+    query: web::Query<EventPollQuery>,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let pin_id = parse_pin_id(&req)?;
+    let timeout_ms = query.timeout_ms.unwrap_or(DEFAULT_EVENT_POLL_MS);
+    if timeout_ms > MAX_EVENT_POLL_MS {
+        return Err(AppError::InvalidValue(format!(
+            "timeout_ms must be within 0 and {MAX_EVENT_POLL_MS}"
+        )));
+    }
+    let timeout = Duration::from_millis(timeout_ms);
+    if let Some(limit) = state.manager.config().http.request_timeout()
+        && timeout >= limit
+    {
+        return Err(AppError::InvalidValue(format!(
+            "timeout_ms must be below the {}s request timeout",
+            limit.as_secs()
+        )));
+    }
+
+    match state.manager.next_event(pin_id, timeout).await? {
+        Some(event) => Ok(HttpResponse::Ok().reply_json(event)),
+        None => Ok(HttpResponse::NoContent().finish()),
+    }
+}
+
 async fn get_last_event<B: GpioBackend + 'static>(
     req: HttpRequest,
     state: web::Data<AppState<B>>,
@@ -255,7 +1564,8 @@ async fn get_last_event<B: GpioBackend + 'static>(
     let last = state.manager.get_last_event(pin_id).await?;
 
     match last {
-        Some(event) => Ok(HttpResponse::Ok().json(event)),
+        Some(event) => Ok(HttpResponse::Ok().reply_json(event)),
+        None if RequestContext::envelope() => Ok(HttpResponse::Ok().reply_json(())),
         None => Ok(HttpResponse::Ok().finish()),
     }
 }
@@ -267,22 +1577,179 @@ async fn get_events<B: GpioBackend + 'static>(
 ) -> Result<impl Responder, AppError> {
     let pin_id = parse_pin_id(&req)?;
 
-    let events = state.manager.get_events(pin_id, query.limit).await?;
+    let events = state
+        .manager
+        .get_events(pin_id, query.limit, query.edge, query.since_ms)
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header((EVENT_SEQ_HEADER, state.manager.current_event_seq()))
+        .reply_json(events))
+}
+
+async fn get_all_events<B: GpioBackend + 'static>(
+    query: web::Query<EventsQuery>,
+    state: web::Data<AppState<B>>,
+) -> impl Responder {
+    let events = state
+        .manager
+        .get_all_events(query.limit, query.edge, query.since_ms);
+
+    HttpResponse::Ok()
+        .insert_header((EVENT_SEQ_HEADER, state.manager.current_event_seq()))
+        .reply_json(events)
+}
+
+async fn clear_events<B: GpioBackend + 'static>(
+    req: HttpRequest,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let pin_id = parse_pin_id(&req)?;
+
+    state.manager.clear_events(pin_id).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn get_stats<B: GpioBackend + 'static>(
+    req: HttpRequest,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let pin_id = parse_pin_id(&req)?;
+    let stats = state.manager.event_stats(pin_id).await?;
+
+    Ok(HttpResponse::Ok().reply_json(stats))
+}
+
+async fn reset_stats<B: GpioBackend + 'static>(
+    req: HttpRequest,
+    state: web::Data<AppState<B>>,
+) -> Result<impl Responder, AppError> {
+    let pin_id = parse_pin_id(&req)?;
+
+    state.manager.reset_event_stats(pin_id).await?;
 
-    Ok(web::Json(events))
+    Ok(HttpResponse::NoContent().finish())
 }
 
 async fn events_ws_all<B: GpioBackend + 'static>(
     req: HttpRequest,
     stream: web::Payload,
+    query: web::Query<WsEventsQuery>,
+    state: web::Data<AppState<B>>,
+) -> Result<HttpResponse, AppError> {
+    let manager = state.manager.clone();
+    let rx = manager.subscribe_events();
+    let replay = match query.replay {
+        Some(per_pin) => manager.recent_events(None, per_pin),
+        None => Vec::new(),
+    };
+    let (response, session, client_stream) = actix_ws::handle(&req, stream)
+        .map_err(|e| AppError::Gpio(format!("websocket error: {e}")))?;
+
+    actix_web::rt::spawn(async move {
+        handle_event_websocket(manager, session, client_stream, rx, None, replay).await;
+    });
+
+    Ok(response)
+}
+
+async fn snapshot_ws<B: GpioBackend + 'static>(
+    req: HttpRequest,
+    stream: web::Payload,
+    query: web::Query<SnapshotQuery>,
+    state: web::Data<AppState<B>>,
+) -> Result<HttpResponse, AppError> {
+    let interval_ms = query
+        .interval_ms
+        .unwrap_or(DEFAULT_SNAPSHOT_INTERVAL_MS)
+        .max(MIN_SNAPSHOT_INTERVAL_MS);
+    let manager = state.manager.clone();
+    let (response, session, client_stream) = actix_ws::handle(&req, stream)
+        .map_err(|e| AppError::Gpio(format!("websocket error: {e}")))?;
+
+    actix_web::rt::spawn(async move {
+        handle_snapshot_websocket(
+            manager,
+            session,
+            client_stream,
+            Duration::from_millis(interval_ms),
+        )
+        .await;
+    });
+
+    Ok(response)
+}
+
+async fn event_stream_stats<B: GpioBackend + 'static>(
+    state: web::Data<AppState<B>>,
+) -> impl Responder {
+    HttpResponse::Ok().reply_json(state.manager.event_stream_stats())
+}
+
+async fn events_sse_all<B: GpioBackend + 'static>(
+    query: web::Query<SseQuery>,
+    state: web::Data<AppState<B>>,
+) -> Result<HttpResponse, AppError> {
+    let pin_filter = query.pin;
+    if let Some(pin_id) = pin_filter {
+        state.manager.get_pin_info(pin_id).await?;
+    }
+
+    let manager = state.manager.clone();
+    let events =
+        BroadcastStream::new(state.manager.subscribe_events()).filter_map(
+            move |event| match event {
+                Ok(event) => {
+                    if pin_filter.map(|p| p == event.pin_id).unwrap_or(true) {
+                        serde_json::to_string(&event)
+                            .ok()
+                            .map(|text| format!("data: {text}\n\n"))
+                    } else {
+                        None
+                    }
+                }
+                Err(BroadcastStreamRecvError::Lagged(n)) => {
+                    manager.record_event_lag(n);
+                    Some(format!(
+                        "event: error\ndata: {}\n\n",
+                        AppError::Gpio(format!("event stream lagged by {n} messages"))
+                    ))
+                }
+            },
+        );
+    let heartbeats = IntervalStream::new(tokio::time::interval(SSE_HEARTBEAT_INTERVAL))
+        .map(|_| ": heartbeat\n\n".to_string());
+    let stream = events
+        .merge(heartbeats)
+        .map(|frame| Ok::<_, actix_web::Error>(web::Bytes::from(frame)));
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream))
+}
+
+async fn events_ws_pin<B: GpioBackend + 'static>(
+    req: HttpRequest,
+    stream: web::Payload,
+    query: web::Query<WsEventsQuery>,
     state: web::Data<AppState<B>>,
 ) -> Result<HttpResponse, AppError> {
-    let rx = state.manager.subscribe_events();
+    let pin_id = parse_pin_id(&req)?;
+    state.manager.get_pin_info(pin_id).await?;
+
+    let manager = state.manager.clone();
+    let rx = manager.subscribe_events();
+    let replay = match query.replay {
+        Some(per_pin) => manager.recent_events(Some(pin_id), per_pin),
+        None => Vec::new(),
+    };
     let (response, session, client_stream) = actix_ws::handle(&req, stream)
         .map_err(|e| AppError::Gpio(format!("websocket error: {e}")))?;
 
     actix_web::rt::spawn(async move {
-        handle_event_websocket(session, client_stream, rx, None).await;
+        handle_event_websocket(manager, session, client_stream, rx, Some(pin_id), replay).await;
     });
 
     Ok(response)
@@ -294,28 +1761,39 @@ fn parse_value_payload(body: &[u8]) -> Result<u8, AppError> {
     }
 
     match std::str::from_utf8(body) {
-        Ok(text) => text
-            .trim()
-            .parse::<u8>()
-            .map_err(|_| AppError::InvalidValue("value must be an integer".into())),
+        Ok(text) => parse_value(text),
         _ => Err(AppError::InvalidValue(
             "value payload must be valid UTF-8".into(),
         )),
     }
 }
 
-fn parse_pin_id(req: &HttpRequest) -> Result<u32, AppError> {
+fn parse_pin_id(req: &HttpRequest) -> Result<PinId, AppError> {
     let pin_id = req
         .match_info()
         .get("pin_id")
         .ok_or_else(|| AppError::InvalidValue("missing pin id".into()))?;
     let pin_id = pin_id
-        .parse::<u32>()
+        .parse::<PinId>()
         .map_err(|_| AppError::InvalidValue("invalid pin id".into()))?;
 
     Ok(pin_id)
 }
 
+fn parse_pin_list(pins: &str) -> Result<Vec<PinId>, AppError> {
+    pins.split(',')
+        .map(|p| {
+            p.trim()
+                .parse::<PinId>()
+                .map_err(|_| AppError::InvalidValue(format!("invalid pin id: {p}")))
+        })
+        .collect()
+}
+
+fn parse_capability_list(capabilities: &str) -> Result<Vec<GpioCapability>, AppError> {
+    capabilities.split(',').map(|c| c.trim().parse()).collect()
+}
+
 fn parse_settings_payload(body: &[u8], current: PinSettings) -> Result<PinSettings, AppError> {
     if body.is_empty() {
         return Err(AppError::InvalidValue("empty settings payload".into()));
@@ -323,9 +1801,23 @@ fn parse_settings_payload(body: &[u8], current: PinSettings) -> Result<PinSettin
 
     let payload: SettingsPayload = serde_json::from_slice(body)
         .map_err(|e| AppError::InvalidValue(format!("invalid settings payload: {e}")))?;
+
+    Ok(merge_settings(payload, current))
+}
+
+fn merge_settings(payload: SettingsPayload, current: PinSettings) -> PinSettings {
     let mut merged = current;
     if let Some(state) = payload.state {
+        if state != merged.state {
+            merged.bias = None;
+        }
         merged.state = state;
+        if state != GpioState::Pwm {
+            merged.pwm = None;
+        }
+        if !(state.is_writable() || state == GpioState::Pwm) {
+            merged.drive_strength_ma = None;
+        }
     }
     if let Some(edge) = payload.edge {
         merged.edge = edge;
@@ -333,7 +1825,22 @@ fn parse_settings_payload(body: &[u8], current: PinSettings) -> Result<PinSettin
     if let Some(debounce) = payload.debounce_ms {
         merged.debounce_ms = debounce;
     }
-    Ok(merged)
+    if let Some(software_debounce) = payload.software_debounce {
+        merged.software_debounce = software_debounce;
+    }
+    if let Some(coalesce_ms) = payload.coalesce_ms {
+        merged.coalesce_ms = coalesce_ms;
+    }
+    if let Some(pwm) = payload.pwm {
+        merged.pwm = Some(pwm);
+    }
+    if let Some(ma) = payload.drive_strength_ma {
+        merged.drive_strength_ma = Some(ma);
+    }
+    if let Some(bias) = payload.bias {
+        merged.bias = Some(bias);
+    }
+    merged
 }
 
 async fn method_not_allowed() -> HttpResponse {
@@ -0,0 +1,64 @@
+use std::net::TcpListener;
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::UnixListener;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::AppError;
+
+/// First fd passed by the service manager, see sd_listen_fds(3).
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// A listening socket handed over by systemd.
+#[derive(Debug)]
+pub enum ActivatedListener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+/// Fds passed to process `pid`, given the `LISTEN_PID` and `LISTEN_FDS`
+/// values of its environment. Empty when they were meant for another
+/// process or are missing.
+pub fn listen_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> Vec<RawFd> {
+    if listen_pid.and_then(|p| p.trim().parse::<u32>().ok()) != Some(pid) {
+        return Vec::new();
+    }
+    let count = listen_fds
+        .and_then(|n| n.trim().parse::<RawFd>().ok())
+        .unwrap_or(0)
+        .max(0);
+
+    (SD_LISTEN_FDS_START..SD_LISTEN_FDS_START.saturating_add(count)).collect()
+}
+
+/// Takes the sockets systemd passed to this process, empty when it was not
+/// socket activated. Only the first call takes them, later calls get none.
+pub fn activated_listeners() -> Result<Vec<ActivatedListener>, AppError> {
+    static TAKEN: AtomicBool = AtomicBool::new(false);
+    if TAKEN.swap(true, Ordering::AcqRel) {
+        return Ok(Vec::new());
+    }
+
+    let fds = listen_fds(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+    fds.into_iter()
+        .map(|fd| {
+            // SAFETY: systemd passed these fds to this process, nothing else
+            // owns them and the guard above makes sure they are taken once
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            let unix = UnixListener::from(fd);
+            if unix.local_addr().is_ok() {
+                return Ok(ActivatedListener::Unix(unix));
+            }
+            let tcp = TcpListener::from(OwnedFd::from(unix));
+            match tcp.local_addr() {
+                Ok(_) => Ok(ActivatedListener::Tcp(tcp)),
+                Err(e) => Err(AppError::Config(format!(
+                    "fd passed by systemd is not a tcp or unix socket: {e}"
+                ))),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,91 @@
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use actix_web::rt::{self, System};
+use log::warn;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::config::WebhookConfig;
+use crate::gpio::{EdgeEvent, EventCallbackHandler, EventHandler};
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Posts dispatched events to each webhook from a dedicated thread, so the
+/// delivery does not depend on the caller running inside an actix system.
+/// Receivers are subscribed before returning so no event is missed, and
+/// the thread ends once the event channel closes.
+pub(crate) fn spawn_delivery(webhooks: &[WebhookConfig], handler: &EventHandler) {
+    if webhooks.is_empty() {
+        return;
+    }
+
+    let subscriptions: Vec<_> = webhooks
+        .iter()
+        .map(|hook| (hook.clone(), handler.subscribe()))
+        .collect();
+    // a strong handle would keep the sender, and so this thread, alive forever
+    let handler = Arc::downgrade(handler);
+    let spawned = std::thread::Builder::new()
+        .name("gmgr-webhooks".into())
+        .spawn(move || {
+            System::new().block_on(async move {
+                let tasks: Vec<_> = subscriptions
+                    .into_iter()
+                    .map(|(hook, events)| rt::spawn(deliver(hook, events, handler.clone())))
+                    .collect();
+                for task in tasks {
+                    let _ = task.await;
+                }
+            })
+        });
+    if let Err(e) = spawned {
+        warn!("webhooks disabled, failed to spawn delivery thread: {e}");
+    }
+}
+
+async fn deliver(
+    hook: WebhookConfig,
+    mut events: broadcast::Receiver<EdgeEvent>,
+    handler: Weak<EventCallbackHandler>,
+) {
+    let client = awc::Client::builder().timeout(DELIVERY_TIMEOUT).finish();
+
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("webhook {} fell behind, skipped {skipped} events", hook.url);
+                if let Some(handler) = handler.upgrade() {
+                    handler.record_lag(skipped);
+                }
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        if !hook.matches(event.pin_id, event.edge) {
+            continue;
+        }
+
+        let mut backoff = INITIAL_BACKOFF;
+        for attempt in 1..=MAX_ATTEMPTS {
+            let error = match client.post(&hook.url).send_json(&event).await {
+                Ok(res) if res.status().is_success() => break,
+                Ok(res) => format!("status {}", res.status()),
+                Err(e) => e.to_string(),
+            };
+            if attempt == MAX_ATTEMPTS {
+                warn!(
+                    "dropping event {} for webhook {} after {MAX_ATTEMPTS} attempts: {error}",
+                    event.seq, hook.url
+                );
+                break;
+            }
+
+            rt::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+}
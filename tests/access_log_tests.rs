@@ -0,0 +1,73 @@
+#![cfg(feature = "json-logs")]
+
+use std::sync::{Arc, Mutex};
+
+use actix_web::{App, test, web};
+use gmgr::{AppConfig, AppState, GpioManager, GpioState, MockGpioBackend, PinId, PinSettings};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::Value;
+
+static ACCESS_LINES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct CaptureLogger;
+
+impl Log for CaptureLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        if record.target() == "gmgr::access" {
+            ACCESS_LINES.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[actix_rt::test]
+async fn value_write_is_logged_as_json() {
+    log::set_logger(&CaptureLogger).unwrap();
+    log::set_max_level(LevelFilter::Info);
+
+    let cfg = Arc::new(AppConfig::load_from_file("config.json").unwrap());
+    let manager = Arc::new(GpioManager::new(
+        cfg.clone(),
+        Arc::new(MockGpioBackend::default()),
+    ));
+    let output = PinSettings {
+        state: GpioState::PushPull,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(1), &output).await.unwrap();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&cfg.http.path[0]))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .insert_header(("X-Request-Id", "write-1"))
+        .set_payload("1")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert!(res.status().is_success());
+
+    let lines = ACCESS_LINES.lock().unwrap();
+    let entry: Value = lines
+        .iter()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .find(|entry| entry["method"] == "POST")
+        .unwrap();
+    assert_eq!(entry["request_id"], "write-1");
+    assert_eq!(entry["path"], "/api/v1/gpio/1/value");
+    assert_eq!(entry["pin_id"], 1);
+    assert_eq!(entry["status"], 200);
+    assert!(entry["latency_ms"].as_f64().unwrap() >= 0.0);
+}
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use actix_web::{App, test, web};
-use gmgr::{AppConfig, AppState, GpioManager, MockGpioBackend};
+use actix_web::{App, http::Method, test, web};
+use gmgr::{
+    AppConfig, AppState, CorsConfig, GpioCapability, GpioManager, MockGpioBackend, PinConfig,
+    PinId, RateLimitConfig,
+};
 use serde_json::Value;
 
 fn sample_config() -> AppConfig {
@@ -14,8 +17,11 @@ async fn list_gpios_returns_all() {
     let cfg = Arc::new(sample_config());
     let backend = Arc::new(MockGpioBackend::default());
     let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
-    let state = AppState { manager };
-    let scope_path = cfg.http.path.clone();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
 
     let app = test::init_service(
         App::new()
@@ -36,13 +42,1749 @@ async fn list_gpios_returns_all() {
     assert_eq!(cfg["line"], 2);
 }
 
+#[actix_rt::test]
+async fn export_csv_lists_every_pin() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_json(serde_json::json!({ "state": "push-pull" }))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .set_payload("1")
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios/export.csv")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/csv; charset=utf-8"
+    );
+    let body = test::read_body(resp).await;
+    let csv = std::str::from_utf8(&body).unwrap();
+
+    let rows: Vec<&str> = csv.split_terminator("\r\n").collect();
+    assert_eq!(
+        rows[0],
+        "pin_id,name,chip,line,state,edge,debounce_ms,software_debounce,coalesce_ms,bias,\
+         pwm_frequency_hz,pwm_duty_cycle,value"
+    );
+    assert_eq!(rows.len(), 4);
+    assert_eq!(
+        rows[1],
+        "1,LED 1,/dev/gpiochip0,2,push-pull,none,0,false,0,,,,1"
+    );
+    assert!(rows[2].starts_with("2,") && rows[2].ends_with(",disabled,none,0,false,0,,,,"));
+}
+
+#[actix_rt::test]
+async fn import_restores_exported_snapshot() {
+    let mut cfg = sample_config();
+    cfg.gpios
+        .get_mut(&PinId(42))
+        .unwrap()
+        .capabilities
+        .insert(GpioCapability::Pwm);
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    for (pin, settings) in [
+        (
+            1,
+            serde_json::json!({ "state": "push-pull", "bias": "disabled" }),
+        ),
+        (
+            2,
+            serde_json::json!({
+                "state": "pull-up", "edge": "both", "debounce_ms": 5,
+                "software_debounce": true, "coalesce_ms": 20
+            }),
+        ),
+        (
+            42,
+            serde_json::json!({
+                "state": "pwm", "pwm": { "frequency_hz": 50.0, "duty_cycle": 0.25 }
+            }),
+        ),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/gpio/{pin}/settings"))
+            .set_json(settings)
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .set_payload("1")
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios/export.csv")
+        .to_request();
+    let csv = test::call_and_read_body(&app, req).await;
+    let req = test::TestRequest::get().uri("/api/v1/gpios").to_request();
+    let before: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+
+    for pin in [1, 2, 42] {
+        let req = test::TestRequest::delete()
+            .uri(&format!("/api/v1/gpio/{pin}/settings"))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+
+    // importing twice leaves the same state
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/gpios/import")
+            .insert_header(("content-type", "text/csv"))
+            .set_payload(csv.clone())
+            .to_request();
+        let report: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(report["warnings"], serde_json::json!([]));
+        assert_eq!(report["results"]["2"]["ok"]["edge"], "both");
+    }
+
+    let req = test::TestRequest::get().uri("/api/v1/gpios").to_request();
+    let after: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(after, before);
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/value")
+        .to_request();
+    let value: u8 = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(value, 1);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpios/import")
+        .set_json(serde_json::json!([
+            { "pin_id": 1, "state": "disabled" },
+            { "pin_id": 7, "state": "push-pull", "value": 1 }
+        ]))
+        .to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(report["results"]["1"]["ok"]["state"], "disabled");
+    assert!(report["results"].get("7").is_none());
+    assert_eq!(
+        report["warnings"],
+        serde_json::json!(["pin 7 is not configured, skipped"])
+    );
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpios/import")
+        .insert_header(("content-type", "text/csv"))
+        .set_payload("pin_id,state\r\n1,sideways\r\n")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_rt::test]
+async fn api_served_under_every_base_path() {
+    let mut cfg = sample_config();
+    cfg.http.path = vec!["/api/v1".into(), "/api/v2".into()];
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg, backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scopes())
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    for base in ["/api/v1", "/api/v2"] {
+        let req = test::TestRequest::get()
+            .uri(&format!("{base}/gpio/1"))
+            .to_request();
+        let pin: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(pin["info"]["name"], "LED 1");
+
+        let req = test::TestRequest::get()
+            .uri(&format!("{base}/openapi.json"))
+            .to_request();
+        let doc: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(doc["servers"][0]["url"], base);
+    }
+
+    let req = test::TestRequest::get().uri("/api/v3/gpio/1").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+}
+
+#[actix_rt::test]
+async fn configure_sets_state_and_value_together() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(
+        cfg.clone(),
+        backend.clone(),
+    ));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/42")
+        .set_json(serde_json::json!({ "state": "open-drain", "bias": "pull-up", "value": 1 }))
+        .to_request();
+    let pin: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(pin["settings"]["state"], "open-drain");
+    assert_eq!(pin["settings"]["bias"], "pull-up");
+    assert_eq!(backend.physical_value(PinId(42)), Some(1));
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/42/value")
+        .to_request();
+    let value: u8 = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(value, 1);
+
+    // nothing is applied when the state cannot take a value
+    for body in [
+        serde_json::json!({ "state": "pull-up", "value": 0 }),
+        serde_json::json!({ "state": "push-pull", "value": 2 }),
+        serde_json::json!({ "state": "push-pull" }),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/gpio/42")
+            .set_json(body)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+    }
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/42/settings")
+        .to_request();
+    let settings: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(settings["state"], "open-drain");
+    assert_eq!(backend.physical_value(PinId(42)), Some(1));
+}
+
+#[actix_rt::test]
+async fn list_gpios_paginates_sorted_by_id() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios?offset=1&limit=1")
+        .to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 3);
+    assert_eq!(page["offset"], 1);
+    let ids: Vec<&String> = page["pins"].as_object().unwrap().keys().collect();
+    assert_eq!(ids, ["2"]);
+    assert_eq!(page["pins"]["2"]["settings"]["state"], "disabled");
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios?offset=1")
+        .to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    let ids: Vec<&String> = page["pins"].as_object().unwrap().keys().collect();
+    assert_eq!(ids, ["2", "42"]);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios?offset=5&limit=2")
+        .to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 3);
+    assert!(page["pins"].as_object().unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn list_gpios_filters_by_tag() {
+    let mut cfg = sample_config();
+    cfg.gpios.get_mut(&PinId(1)).unwrap().tags = vec!["relay".into()];
+    cfg.gpios.get_mut(&PinId(42)).unwrap().tags = vec!["relay".into(), "lab".into()];
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios?tag=relay")
+        .to_request();
+    let response: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    let mut ids: Vec<&str> = response.keys().map(String::as_str).collect();
+    ids.sort();
+    assert_eq!(ids, ["1", "42"]);
+    assert_eq!(
+        response["42"]["info"]["tags"],
+        serde_json::json!(["relay", "lab"])
+    );
+    // untagged pins leave the field out
+    let req = test::TestRequest::get().uri("/api/v1/gpios").to_request();
+    let response: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert!(response["2"]["info"].get("tags").is_none());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios?tag=relay,lab")
+        .to_request();
+    let response: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response.keys().collect::<Vec<_>>(), ["42"]);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios?tag=hvac")
+        .to_request();
+    let response: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert!(response.is_empty());
+}
+
+#[actix_rt::test]
+async fn list_gpios_filters_by_capability() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios?capability=open-drain")
+        .to_request();
+    let response: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response.keys().collect::<Vec<_>>(), ["42"]);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios?capability=push-pull")
+        .to_request();
+    let response: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response.len(), 2);
+    assert!(response.contains_key("1") && response.contains_key("42"));
+
+    // combined capabilities must all be supported
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios?capability=pull-up,push-pull&offset=0")
+        .to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 1);
+    assert!(page["pins"].get("42").is_some());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios?capability=teleport")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 400);
+}
+
+#[actix_rt::test]
+async fn openapi_document_describes_routes() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg, backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope("/custom/v2"))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/custom/v2/openapi.json")
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    let doc: Value = serde_json::from_slice(&body).unwrap();
+
+    assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+    assert_eq!(doc["servers"][0]["url"], "/custom/v2");
+    let settings = &doc["paths"]["/gpio/{pin_id}/settings"];
+    assert!(settings["get"].is_object());
+    assert!(settings["post"]["requestBody"].is_object());
+    for schema in ["PinSettings", "PinDescriptor", "EdgeEvent", "Error"] {
+        assert!(doc["components"]["schemas"][schema].is_object(), "{schema}");
+    }
+}
+
+#[actix_rt::test]
+async fn request_id_round_trips_into_errors() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/99")
+        .insert_header(("X-Request-Id", "client-abc-123"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.headers().get("x-request-id").unwrap(), "client-abc-123");
+    let body: Value = test::read_body_json(res).await;
+    assert_eq!(body["request_id"], "client-abc-123");
+    assert_eq!(body["code"], "pin_not_found");
+
+    // generated when missing, and unique per request
+    let req = test::TestRequest::get().uri("/api/v1/gpio/1").to_request();
+    let first = test::call_service(&app, req).await;
+    let req = test::TestRequest::get().uri("/api/v1/gpio/1").to_request();
+    let second = test::call_service(&app, req).await;
+    let first = first
+        .headers()
+        .get("x-request-id")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let second = second
+        .headers()
+        .get("x-request-id")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(!first.is_empty());
+    assert_ne!(first, second);
+}
+
+#[actix_rt::test]
+async fn writes_beyond_rate_limit_get_429() {
+    let mut cfg = sample_config();
+    cfg.http.rate_limit = Some(RateLimitConfig {
+        writes_per_second: 0.5,
+        burst: 2,
+    });
+    cfg.validate().unwrap();
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_json(serde_json::json!({"state": "push-pull"}))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let mut statuses = Vec::new();
+    for _ in 0..3 {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/gpio/1/value")
+            .set_payload("1")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        statuses.push(res.status().as_u16());
+        if res.status() == 429 {
+            let retry_after = res.headers().get("retry-after").unwrap();
+            assert_eq!(retry_after, "2");
+            let body: Value = test::read_body_json(res).await;
+            assert_eq!(body["code"], "rate_limited");
+        }
+    }
+    // the settings write used one of the two burst tokens
+    assert_eq!(statuses, [200, 429, 429]);
+
+    // reads are not limited
+    for _ in 0..5 {
+        let req = test::TestRequest::get()
+            .uri("/api/v1/gpio/1/value")
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 200);
+    }
+}
+
+#[actix_rt::test]
+async fn value_write_is_audited() {
+    let path = std::env::temp_dir().join(format!("gmgr-audit-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut cfg = sample_config();
+    cfg.auth.api_keys = vec!["other".into(), "secret".into()];
+    cfg.audit_log_path = Some(path.to_string_lossy().into_owned());
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .insert_header(("X-API-Key", "secret"))
+        .set_json(serde_json::json!({"state": "push-pull"}))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .insert_header(("X-API-Key", "secret"))
+        .insert_header(("X-Request-Id", "audit-1"))
+        .set_payload("1")
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let records: Vec<Value> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["op"], "set_settings");
+    assert_eq!(records[0]["old"]["state"], "disabled");
+    assert_eq!(records[0]["new"]["state"], "push-pull");
+
+    let write = &records[1];
+    assert_eq!(write["op"], "set_value");
+    assert_eq!(write["pin_id"], 1);
+    assert_eq!(write["old"], 0);
+    assert_eq!(write["new"], 1);
+    assert_eq!(write["request_id"], "audit-1");
+    assert_eq!(write["api_key"], "api_keys[1]");
+    assert!(write["timestamp_ms"].as_u64().unwrap() > 0);
+}
+
+#[actix_rt::test]
+async fn pulse_is_audited_under_its_request() {
+    let path = std::env::temp_dir().join(format!("gmgr-audit-pulse-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut cfg = sample_config();
+    cfg.auth.api_keys = vec!["secret".into()];
+    cfg.audit_log_path = Some(path.to_string_lossy().into_owned());
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .insert_header(("X-API-Key", "secret"))
+        .set_json(serde_json::json!({"state": "push-pull"}))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/pulse?blocking=true")
+        .insert_header(("X-API-Key", "secret"))
+        .insert_header(("X-Request-Id", "pulse-1"))
+        .set_payload(r#"{"value":1,"duration_ms":5}"#)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/pulse")
+        .insert_header(("X-API-Key", "secret"))
+        .insert_header(("X-Request-Id", "pulse-2"))
+        .set_payload(r#"{"value":1,"duration_ms":5}"#)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 202);
+    actix_rt::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    let records: Vec<Value> = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let _ = std::fs::remove_file(&path);
+    let pulses: Vec<&Value> = records.iter().filter(|r| r["op"] == "pulse").collect();
+    assert_eq!(pulses.len(), 2);
+    assert_eq!(pulses[0]["request_id"], "pulse-1");
+    assert_eq!(pulses[1]["request_id"], "pulse-2");
+    assert!(pulses.iter().all(|r| r["api_key"] == "api_keys[0]"));
+}
+
 #[actix_rt::test]
 async fn pin_not_found_returns_404() {
     let cfg = Arc::new(sample_config());
     let backend = Arc::new(MockGpioBackend::default());
     let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
-    let state = AppState { manager };
-    let scope_path = cfg.http.path.clone();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/999/info")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["pin_id"], 999);
+    assert_eq!(body["code"], "pin_not_found");
+}
+
+#[actix_rt::test]
+async fn info_reports_mock_backend() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/api/v1/info").to_request();
+    let info: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(info["backend"], "mock");
+    assert_eq!(info["libgpiod_version"], Value::Null);
+    assert_eq!(info["crate_version"], env!("CARGO_PKG_VERSION"));
+}
+
+#[actix_rt::test]
+async fn active_listing_only_has_enabled_pins() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios/active")
+        .to_request();
+    let pins: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert!(pins.is_empty());
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1")
+        .set_payload(r#"{"state":"push-pull","value":1}"#)
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios/active")
+        .to_request();
+    let pins: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    let ids: Vec<&str> = pins.keys().map(String::as_str).collect();
+    assert_eq!(ids, ["1"]);
+    assert_eq!(pins["1"]["settings"]["state"], "push-pull");
+    assert_eq!(pins["1"]["info"]["name"], "LED 1");
+    assert_eq!(pins["1"]["value"], 1);
+}
+
+#[actix_rt::test]
+async fn invalid_pin_id_returns_400() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/-1/info")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "invalid_value");
+    assert!(body.get("pin_id").is_none());
+}
+
+#[actix_rt::test]
+async fn value_payload_accepts_words_and_reports_range() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    for (payload, value) in [
+        ("true", "1"),
+        ("LOW", "0"),
+        ("High\n", "1"),
+        (r#""false""#, "0"),
+        (" 1 ", "1"),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/gpio/1/value")
+            .set_payload(payload)
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let req = test::TestRequest::get()
+            .uri("/api/v1/gpio/1/value")
+            .to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, value);
+    }
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .set_payload("300")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "invalid_value");
+    assert_eq!(body["value"], 300);
+    assert_eq!(body["allowed"], serde_json::json!({ "min": 0, "max": 1 }));
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .set_payload("on")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert!(body.get("value").is_none());
+
+    // values that fit the payload type are checked by the manager
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/pulse")
+        .set_payload(r#"{"value":2,"duration_ms":10}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["value"], 2);
+    assert_eq!(body["pin_id"], 1);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/pulse")
+        .set_payload(r#"{"value":1,"duration_ms":3600000}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["allowed"]["max"], 60_000);
+}
+
+#[actix_rt::test]
+async fn stale_settings_update_conflicts() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+    let etag = |resp: &actix_web::dev::ServiceResponse| {
+        resp.headers()
+            .get("etag")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    };
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/2/settings")
+        .to_request();
+    let read = etag(&test::call_service(&app, req).await);
+    assert_eq!(read, "\"0\"");
+
+    // another client updates the pin after the first one read it
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/2/settings")
+        .insert_header(("If-Match", read.as_str()))
+        .set_payload(r#"{"state":"pull-up"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let updated = etag(&resp);
+    assert_eq!(updated, "\"1\"");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/2/settings")
+        .insert_header(("If-Match", read.as_str()))
+        .set_payload(r#"{"state":"pull-down"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 409);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "conflict");
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/2/settings")
+        .to_request();
+    let settings: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(settings["state"], "pull-up");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/2/settings")
+        .insert_header(("If-Match", "W/\"1\""))
+        .set_payload(r#"{"state":"pull-down"}"#)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    for if_match in [Some(updated.as_str()), Some("*"), None] {
+        let mut req = test::TestRequest::post()
+            .uri("/api/v1/gpio/2/settings")
+            .set_payload(r#"{"edge":"both"}"#);
+        if let Some(if_match) = if_match {
+            req = req.insert_header(("If-Match", if_match));
+        }
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert!(resp.status().is_success());
+    }
+}
+
+#[actix_rt::test]
+async fn oversized_body_returns_413() {
+    let mut cfg = sample_config();
+    cfg.http.max_body_bytes = 64;
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let padding = " ".repeat(64);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(format!(r#"{{"state":"push-pull"}}{padding}"#))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 413);
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .set_payload(format!("1{padding}"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 413);
+}
+
+#[actix_rt::test]
+async fn busy_line_returns_409() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    backend
+        .set_foreign_consumer(PinId(1), Some("other-daemon"))
+        .unwrap();
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(
+        cfg.clone(),
+        backend.clone(),
+    ));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 409);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "line_busy");
+    assert_eq!(body["pin_id"], 1);
+    assert!(body["error"].as_str().unwrap().contains("other-daemon"));
+
+    backend.set_foreign_consumer(PinId(1), None).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+}
+
+#[actix_rt::test]
+async fn wrong_method_returns_405() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/info")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 405);
+}
+
+#[actix_rt::test]
+async fn set_state_and_value_happy_path() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .set_payload("1")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/value")
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "1");
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/value?force=true")
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "1");
+}
+
+#[actix_rt::test]
+async fn built_config_serves_api() {
+    let cfg = AppConfig::builder()
+        .add_pin(
+            1,
+            PinConfig {
+                name: "LED 1".into(),
+                chip: "/dev/gpiochip0".into(),
+                line: 2,
+                capabilities: [GpioCapability::PushPull].into(),
+                ..Default::default()
+            },
+        )
+        .add_pin(
+            2,
+            PinConfig {
+                name: "BUTTON 1".into(),
+                chip: "/dev/gpiochip0".into(),
+                line: 3,
+                capabilities: [GpioCapability::PullUp].into(),
+                ..Default::default()
+            },
+        )
+        .event_history_capacity(8)
+        .build()
+        .unwrap();
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/api/v1/gpios").to_request();
+    let response: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(response.len(), 2);
+    assert_eq!(response["2"]["info"]["name"], "BUTTON 1");
+
+    for (pin, state) in [(1, "push-pull"), (2, "pull-up")] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/gpio/{pin}/settings"))
+            .set_payload(format!(r#"{{"state":"{state}"}}"#))
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+    }
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .set_payload("1")
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios/values")
+        .to_request();
+    let values: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(values.len(), 2);
+    assert_eq!(values["1"]["ok"], 1);
+}
+
+#[actix_rt::test]
+async fn reject_value_when_not_output() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/2/value")
+        .set_payload("1")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_rt::test]
+async fn get_pin_info_happy_path() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/info")
+        .to_request();
+    let resp: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(resp["name"], "LED 1");
+    assert_eq!(resp["chip"], "/dev/gpiochip0");
+    assert_eq!(resp["line"], 2);
+}
+
+#[actix_rt::test]
+async fn get_pin_info_alias_happy_path() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/api/v1/gpio/1").to_request();
+    let resp: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(resp["settings"]["state"], "disabled");
+    let cfg = &resp["info"];
+    assert_eq!(cfg["name"], "LED 1");
+    assert_eq!(cfg["chip"], "/dev/gpiochip0");
+    assert_eq!(cfg["line"], 2);
+}
+
+#[actix_rt::test]
+async fn get_state_happy_path() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    // Default state is disabled
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/settings")
+        .to_request();
+    let settings: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(settings["state"], "disabled");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/settings")
+        .to_request();
+    let settings: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(settings["state"], "push-pull");
+
+    // the mock has no other consumers, so the line reports what was set
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/settings?source=hardware")
+        .to_request();
+    let settings: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(settings["state"], "push-pull");
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/settings?source=kernel")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_rt::test]
+async fn batch_settings_reports_every_problem() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpios/settings")
+        .set_payload(
+            r#"{"1":{"state":"push-pull"},"2":{"state":"push-pull"},"999":{"state":"floating"}}"#,
+        )
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "validation_error");
+    let errors = body["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0].as_str().unwrap().contains("not supported"));
+    assert!(errors[1].as_str().unwrap().contains("pin not found: 999"));
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/settings")
+        .to_request();
+    let settings: Value = test::call_and_read_body_json(&app, req).await;
+    assert_ne!(settings["state"], "push-pull");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpios/settings")
+        .set_payload(r#"{"1":{"state":"push-pull"},"2":{"state":"floating"}}"#)
+        .to_request();
+    let resp: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["1"]["ok"]["state"], "push-pull");
+    assert_eq!(resp["2"]["ok"]["state"], "floating");
+}
+
+#[actix_rt::test]
+async fn batch_values_returns_enabled_pins() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    for (pin, value) in [(1, "1"), (42, "0")] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/gpio/{pin}/settings"))
+            .set_payload(r#"{"state":"push-pull"}"#)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/gpio/{pin}/value"))
+            .set_payload(value)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios/values")
+        .to_request();
+    let resp: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp.len(), 2);
+    assert_eq!(resp["1"]["ok"], 1);
+    assert_eq!(resp["42"]["ok"], 0);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios/values?pins=42,999")
+        .to_request();
+    let resp: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp.len(), 2);
+    assert_eq!(resp["42"]["ok"], 0);
+    assert!(resp["999"]["error"].is_string());
+}
+
+#[actix_rt::test]
+async fn toggle_flips_output_value() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    for expected in ["1", "0"] {
+        let req = test::TestRequest::post()
+            .uri("/api/v1/gpio/1/toggle")
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        assert_eq!(body, expected);
+    }
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/2/settings")
+        .set_payload(r#"{"state":"pull-up"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/2/toggle")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 400);
+}
+
+#[actix_rt::test]
+async fn pulse_returns_to_baseline() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/pulse?blocking=true")
+        .set_payload(r#"{"value":1,"duration_ms":20}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/value")
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "0");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/pulse")
+        .set_payload(r#"{"value":1,"duration_ms":50}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 202);
+
+    actix_rt::time::sleep(std::time::Duration::from_millis(10)).await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/value")
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "1");
+
+    actix_rt::time::sleep(std::time::Duration::from_millis(100)).await;
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/value")
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "0");
+}
+
+#[actix_rt::test]
+async fn delete_settings_disables_pin() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::delete()
+        .uri("/api/v1/gpio/1/settings")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 204);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/settings")
+        .to_request();
+    let settings: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(settings["state"], "disabled");
+
+    let req = test::TestRequest::delete()
+        .uri("/api/v1/gpio/999/settings")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[cfg(feature = "metrics")]
+#[actix_rt::test]
+async fn metrics_report_events_and_writes() {
+    use gmgr::{EdgeDetect, EdgeEvent};
+
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let handler = manager.event_handler();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .set_payload("1")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    for _ in 0..2 {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(2),
+            edge: EdgeDetect::Rising,
+            timestamp_ms: 0,
+            seq: 0,
+            count: 1,
+        });
+    }
+
+    let req = test::TestRequest::get().uri("/api/v1/metrics").to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    let body = std::str::from_utf8(&body).unwrap();
+
+    assert!(body.contains(r#"gmgr_edge_events_total{edge="rising",pin_id="2"} 2"#));
+    assert!(body.contains(r#"gmgr_pin_value{pin_id="1"} 1"#));
+    assert!(body.contains("gmgr_set_settings_total 1"));
+}
+
+#[actix_rt::test]
+async fn health_and_readiness_probes() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.probe_services())
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/healthz").to_request();
+    let resp: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp["status"], "ok");
+
+    let req = test::TestRequest::get().uri("/readyz").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let req = test::TestRequest::get().uri("/api/v1/healthz").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+}
+
+#[actix_rt::test]
+async fn list_chips_returns_configured_chips() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get().uri("/api/v1/chips").to_request();
+    let chips: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(chips.len(), 2);
+    assert_eq!(chips[0]["path"], "/dev/gpiochip0");
+    assert_eq!(chips[0]["lines"][0]["offset"], 2);
+    assert_eq!(chips[0]["lines"][0]["used"], true);
+    assert_eq!(chips[0]["lines"][1]["offset"], 3);
+    assert_eq!(chips[0]["lines"][1]["used"], false);
+    assert_eq!(chips[1]["path"], "/dev/gpiochip1");
+    assert_eq!(chips[1]["num_lines"], 6);
+}
+
+#[actix_rt::test]
+async fn events_filtered_by_edge() {
+    use gmgr::{EdgeDetect, EdgeEvent};
+
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let handler = manager.event_handler();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    for (timestamp_ms, edge) in [
+        (10, EdgeDetect::Rising),
+        (20, EdgeDetect::Falling),
+        (30, EdgeDetect::Rising),
+        (40, EdgeDetect::Falling),
+    ] {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(2),
+            edge,
+            timestamp_ms,
+            seq: 0,
+            count: 1,
+        });
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/2/events?edge=rising")
+        .to_request();
+    let events: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    let timestamps: Vec<u64> = events
+        .iter()
+        .map(|e| {
+            assert_eq!(e["edge"], "rising");
+            e["timestamp_ms"].as_u64().unwrap()
+        })
+        .collect();
+    assert_eq!(timestamps, [10, 30]);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/2/events?edge=falling&limit=1")
+        .to_request();
+    let events: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["timestamp_ms"], 40);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/2/events")
+        .to_request();
+    let events: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(events.len(), 4);
+}
+
+#[actix_rt::test]
+async fn clear_events_empties_history() {
+    use gmgr::{EdgeDetect, EdgeEvent};
+
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let handler = manager.event_handler();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
 
     let app = test::init_service(
         App::new()
@@ -50,20 +1792,51 @@ async fn pin_not_found_returns_404() {
             .app_data(web::Data::new(state)),
     )
     .await;
+
+    for timestamp_ms in [10, 20] {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(2),
+            edge: EdgeDetect::Rising,
+            timestamp_ms,
+            seq: 0,
+            count: 1,
+        });
+    }
+
+    let req = test::TestRequest::delete()
+        .uri("/api/v1/gpio/2/events")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 204);
+
     let req = test::TestRequest::get()
-        .uri("/api/v1/gpio/999/info")
+        .uri("/api/v1/gpio/2/events")
+        .to_request();
+    let events: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert!(events.is_empty());
+
+    let req = test::TestRequest::delete()
+        .uri("/api/v1/gpio/999/events")
         .to_request();
     let resp = test::call_service(&app, req).await;
     assert_eq!(resp.status(), 404);
 }
 
 #[actix_rt::test]
-async fn wrong_method_returns_405() {
-    let cfg = Arc::new(sample_config());
+async fn event_stats_outlive_history_and_reset() {
+    use gmgr::{EdgeDetect, EdgeEvent};
+
+    let mut cfg = sample_config();
+    cfg.event_history_capacity = 2;
+    let cfg = Arc::new(cfg);
     let backend = Arc::new(MockGpioBackend::default());
     let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
-    let state = AppState { manager };
-    let scope_path = cfg.http.path.clone();
+    let handler = manager.event_handler();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
 
     let app = test::init_service(
         App::new()
@@ -72,20 +1845,226 @@ async fn wrong_method_returns_405() {
     )
     .await;
 
-    let req = test::TestRequest::post()
-        .uri("/api/v1/gpio/1/info")
+    let edges = [
+        EdgeDetect::Rising,
+        EdgeDetect::Falling,
+        EdgeDetect::Rising,
+        EdgeDetect::Falling,
+        EdgeDetect::Rising,
+    ];
+    for (i, edge) in edges.into_iter().enumerate() {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(2),
+            edge,
+            timestamp_ms: 10 * (i as u64 + 1),
+            seq: 0,
+            count: 1,
+        });
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/2/events")
+        .to_request();
+    let events: Vec<Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(events.len(), 2);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/2/stats")
+        .to_request();
+    let stats: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        stats,
+        serde_json::json!({ "rising_count": 3, "falling_count": 2, "last_event_ms": 50 })
+    );
+
+    let req = test::TestRequest::delete()
+        .uri("/api/v1/gpio/2/stats")
         .to_request();
     let resp = test::call_service(&app, req).await;
-    assert_eq!(resp.status(), 405);
+    assert_eq!(resp.status(), 204);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/2/stats")
+        .to_request();
+    let stats: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        stats,
+        serde_json::json!({ "rising_count": 0, "falling_count": 0, "last_event_ms": null })
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/999/stats")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
 }
 
 #[actix_rt::test]
-async fn set_state_and_value_happy_path() {
+async fn event_stream_stats_count_subscribers() {
+    use gmgr::{EdgeDetect, EdgeEvent};
+
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let handler = manager.event_handler();
+    let first = manager.subscribe_events();
+    let second = manager.subscribe_events();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    handler.dispatch(EdgeEvent {
+        pin_id: PinId(2),
+        edge: EdgeDetect::Rising,
+        timestamp_ms: 10,
+        seq: 0,
+        count: 1,
+    });
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios/events/stats")
+        .to_request();
+    let stats: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        stats,
+        serde_json::json!({ "subscribers": 2, "dispatched": 1, "lagged": 0 })
+    );
+
+    drop((first, second));
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios/events/stats")
+        .to_request();
+    let stats: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(stats["subscribers"], 0);
+}
+
+#[actix_rt::test]
+async fn next_event_long_polls() {
+    use gmgr::{EdgeDetect, EdgeEvent};
+
     let cfg = Arc::new(sample_config());
     let backend = Arc::new(MockGpioBackend::default());
     let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
-    let state = AppState { manager };
-    let scope_path = cfg.http.path.clone();
+    let handler = manager.event_handler();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    // an event of another pin does not end the poll
+    actix_rt::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        for (pin_id, timestamp_ms) in [(1, 10), (2, 20)] {
+            handler.dispatch(EdgeEvent {
+                pin_id: PinId(pin_id),
+                edge: EdgeDetect::Rising,
+                timestamp_ms,
+                seq: 0,
+                count: 1,
+            });
+        }
+    });
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/2/event/next?timeout_ms=2000")
+        .to_request();
+    let event: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(event["pin_id"], 2);
+    assert_eq!(event["timestamp_ms"], 20);
+
+    let started = std::time::Instant::now();
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/2/event/next?timeout_ms=100")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 204);
+    assert!(started.elapsed() >= std::time::Duration::from_millis(100));
+
+    for uri in [
+        "/api/v1/gpio/2/event/next?timeout_ms=30000",
+        "/api/v1/gpio/2/event/next?timeout_ms=soon",
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 400);
+    }
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/999/event/next?timeout_ms=10")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+}
+
+#[actix_rt::test]
+async fn api_key_required_when_configured() {
+    let mut cfg = sample_config();
+    cfg.auth.api_keys = vec!["secret".into()];
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.probe_services())
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/api/v1/gpios").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios")
+        .insert_header(("X-API-Key", "wrong"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios")
+        .insert_header(("X-API-Key", "secret"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let req = test::TestRequest::get().uri("/healthz").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+}
+
+#[actix_rt::test]
+async fn read_only_pin_rejects_writes() {
+    let mut cfg = sample_config();
+    cfg.gpios.get_mut(&PinId(42)).unwrap().read_only = true;
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
 
     let app = test::init_service(
         App::new()
@@ -95,33 +2074,94 @@ async fn set_state_and_value_happy_path() {
     .await;
 
     let req = test::TestRequest::post()
-        .uri("/api/v1/gpio/1/settings")
+        .uri("/api/v1/gpio/42/settings")
         .set_payload(r#"{"state":"push-pull"}"#)
         .to_request();
     let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/42/settings")
+        .set_payload(r#"{"state":"pull-up"}"#)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
     assert!(resp.status().is_success());
 
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/42/value")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    for uri in ["/api/v1/gpio/42/value", "/api/v1/gpio/42/toggle"] {
+        let req = test::TestRequest::post()
+            .uri(uri)
+            .set_payload("1")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 403);
+    }
+
+    let req = test::TestRequest::get().uri("/api/v1/gpios").to_request();
+    let pins: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(pins["42"]["info"]["read_only"], true);
+}
+
+#[actix_rt::test]
+async fn slow_request_times_out() {
+    let mut cfg = sample_config();
+    cfg.http.timeout = 1;
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
     let req = test::TestRequest::post()
-        .uri("/api/v1/gpio/1/value")
-        .set_payload("1")
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
         .to_request();
     let resp = test::call_service(&app, req).await;
     assert!(resp.status().is_success());
 
+    let started = std::time::Instant::now();
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/pulse?blocking=true")
+        .set_payload(r#"{"value":1,"duration_ms":1500}"#)
+        .to_request();
+    let err = test::try_call_service(&app, req).await.unwrap_err();
+    assert_eq!(err.as_response_error().status_code(), 504);
+    assert!(started.elapsed() < std::time::Duration::from_millis(1400));
+
+    // the pulse keeps running and still reverts
+    actix_rt::time::sleep(std::time::Duration::from_millis(800)).await;
     let req = test::TestRequest::get()
         .uri("/api/v1/gpio/1/value")
         .to_request();
     let body = test::call_and_read_body(&app, req).await;
-    assert_eq!(body, "1");
+    assert_eq!(body, "0");
 }
 
 #[actix_rt::test]
-async fn reject_value_when_not_output() {
+async fn pin_lookup_by_name() {
     let cfg = Arc::new(sample_config());
     let backend = Arc::new(MockGpioBackend::default());
     let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
-    let state = AppState { manager };
-    let scope_path = cfg.http.path.clone();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
 
     let app = test::init_service(
         App::new()
@@ -130,21 +2170,33 @@ async fn reject_value_when_not_output() {
     )
     .await;
 
-    let req = test::TestRequest::post()
-        .uri("/api/v1/gpio/2/value")
-        .set_payload("1")
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/by-name/LED%201")
+        .to_request();
+    let desc: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(desc["info"]["name"], "LED 1");
+    assert_eq!(desc["info"]["line"], 2);
+    assert_eq!(desc["settings"]["state"], "disabled");
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/by-name/LED%209")
         .to_request();
     let resp = test::call_service(&app, req).await;
-    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.status(), 404);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "pin_not_found");
 }
 
 #[actix_rt::test]
-async fn get_pin_info_happy_path() {
+async fn verbose_value_includes_metadata() {
     let cfg = Arc::new(sample_config());
     let backend = Arc::new(MockGpioBackend::default());
     let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
-    let state = AppState { manager };
-    let scope_path = cfg.http.path.clone();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
 
     let app = test::init_service(
         App::new()
@@ -153,48 +2205,168 @@ async fn get_pin_info_happy_path() {
     )
     .await;
 
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
     let req = test::TestRequest::get()
-        .uri("/api/v1/gpio/1/info")
+        .uri("/api/v1/gpio/1/value?verbose=false")
         .to_request();
-    let resp: Value = test::call_and_read_body_json(&app, req).await;
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "0");
 
-    assert_eq!(resp["name"], "LED 1");
-    assert_eq!(resp["chip"], "/dev/gpiochip0");
-    assert_eq!(resp["line"], 2);
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/value?verbose=true")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["pin_id"], 1);
+    assert_eq!(body["value"], 0);
+    assert_eq!(body["state"], "push-pull");
+    assert!(body["read_at_ms"].as_u64().unwrap() > 0);
 }
 
 #[actix_rt::test]
-async fn get_pin_info_alias_happy_path() {
-    let cfg = Arc::new(sample_config());
+async fn value_answers_bare_or_enveloped() {
+    for envelope in [false, true] {
+        let mut cfg = sample_config();
+        cfg.http.response_envelope = envelope;
+        let cfg = Arc::new(cfg);
+        let backend = Arc::new(MockGpioBackend::default());
+        let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+        let state = AppState {
+            manager,
+            config_path: None,
+        };
+        let scope_path = cfg.http.path[0].clone();
+
+        let app = test::init_service(
+            App::new()
+                .service(state.api_scope(&scope_path))
+                .app_data(web::Data::new(state)),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/gpio/1/settings")
+            .set_payload(r#"{"state":"push-pull"}"#)
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        let req = test::TestRequest::post()
+            .uri("/api/v1/gpio/1/value")
+            .set_payload("1")
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/gpio/1/value")
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        if envelope {
+            assert_eq!(body, serde_json::json!({ "data": 1, "error": null }));
+        } else {
+            assert_eq!(body, 1);
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/gpio/2/value")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 400);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "invalid_state");
+        assert_eq!(body.get("data"), envelope.then_some(&Value::Null));
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/gpio/1/event")
+            .to_request();
+        let body = test::call_and_read_body(&app, req).await;
+        if envelope {
+            let body: Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body, serde_json::json!({ "data": null, "error": null }));
+        } else {
+            assert!(body.is_empty());
+        }
+
+        let req = test::TestRequest::get()
+            .uri("/api/v1/openapi.json")
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&app, req).await;
+        let document = if envelope { &body["data"] } else { &body };
+        assert!(document["openapi"].is_string());
+    }
+}
+
+#[actix_rt::test]
+async fn cors_preflight_allows_configured_origin() {
+    let mut cfg = sample_config();
+    cfg.http.cors = Some(CorsConfig {
+        allowed_origins: vec!["https://dashboard.local".into()],
+        allow_credentials: true,
+    });
+    cfg.validate().unwrap();
+    let cfg = Arc::new(cfg);
     let backend = Arc::new(MockGpioBackend::default());
     let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
-    let state = AppState { manager };
-    let scope_path = cfg.http.path.clone();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
 
     let app = test::init_service(
         App::new()
+            .wrap(cfg.http.cors.as_ref().unwrap().middleware())
             .service(state.api_scope(&scope_path))
             .app_data(web::Data::new(state)),
     )
     .await;
 
-    let req = test::TestRequest::get().uri("/api/v1/gpio/1").to_request();
-    let resp: Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::default()
+        .method(Method::OPTIONS)
+        .uri("/api/v1/gpios")
+        .insert_header(("Origin", "https://dashboard.local"))
+        .insert_header(("Access-Control-Request-Method", "GET"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let headers = resp.headers();
+    assert_eq!(
+        headers.get("access-control-allow-origin").unwrap(),
+        "https://dashboard.local"
+    );
+    assert_eq!(
+        headers.get("access-control-allow-credentials").unwrap(),
+        "true"
+    );
 
-    assert_eq!(resp["settings"]["state"], "disabled");
-    let cfg = &resp["info"];
-    assert_eq!(cfg["name"], "LED 1");
-    assert_eq!(cfg["chip"], "/dev/gpiochip0");
-    assert_eq!(cfg["line"], 2);
+    let req = test::TestRequest::default()
+        .method(Method::OPTIONS)
+        .uri("/api/v1/gpios")
+        .insert_header(("Origin", "https://evil.example"))
+        .insert_header(("Access-Control-Request-Method", "GET"))
+        .to_request();
+    let resp = test::try_call_service(&app, req).await;
+    let allowed = resp
+        .ok()
+        .and_then(|r| r.headers().get("access-control-allow-origin").cloned());
+    assert!(allowed.is_none());
 }
 
 #[actix_rt::test]
-async fn get_state_happy_path() {
-    let cfg = Arc::new(sample_config());
+async fn reload_applies_pin_table_changes() {
+    let path = std::env::temp_dir().join(format!("gmgr-reload-{}.json", std::process::id()));
+    std::fs::copy("config.json", &path).unwrap();
+    let cfg = Arc::new(AppConfig::load_from_file(&path).unwrap());
     let backend = Arc::new(MockGpioBackend::default());
     let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
-    let state = AppState { manager };
-    let scope_path = cfg.http.path.clone();
+    let state = AppState {
+        manager,
+        config_path: Some(path.clone()),
+    };
+    let scope_path = cfg.http.path[0].clone();
 
     let app = test::init_service(
         App::new()
@@ -203,23 +2375,186 @@ async fn get_state_happy_path() {
     )
     .await;
 
-    // Default state is disabled
-    let req = test::TestRequest::get()
+    let req = test::TestRequest::post()
         .uri("/api/v1/gpio/1/settings")
+        .set_payload(r#"{"state":"push-pull"}"#)
         .to_request();
-    let settings: Value = test::call_and_read_body_json(&app, req).await;
-    assert_eq!(settings["state"], "disabled");
+    assert!(test::call_service(&app, req).await.status().is_success());
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .set_payload("1")
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    let mut json: Value =
+        serde_json::from_str(&std::fs::read_to_string("config.json").unwrap()).unwrap();
+    let gpios = json["gpios"].as_object_mut().unwrap();
+    gpios.remove("42");
+    gpios["2"]["name"] = "BUTTON 2".into();
+    gpios["1"]["tags"] = serde_json::json!(["relay"]);
+    gpios.insert(
+        "7".into(),
+        serde_json::json!({
+            "name": "LED 7",
+            "chip": "/dev/gpiochip0",
+            "line": 9,
+            "capabilities": ["push-pull"],
+            "initial_state": "push-pull"
+        }),
+    );
+    std::fs::write(&path, json.to_string()).unwrap();
 
     let req = test::TestRequest::post()
-        .uri("/api/v1/gpio/1/settings")
-        .set_payload(r#"{"state":"push-pull"}"#)
+        .uri("/api/v1/admin/reload")
+        .to_request();
+    let summary: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(summary["added"], serde_json::json!([7]));
+    assert_eq!(summary["removed"], serde_json::json!([42]));
+    assert_eq!(summary["changed"], serde_json::json!([2]));
+
+    let req = test::TestRequest::get().uri("/api/v1/gpios").to_request();
+    let pins: HashMap<String, Value> = test::call_and_read_body_json(&app, req).await;
+    let mut ids: Vec<&str> = pins.keys().map(String::as_str).collect();
+    ids.sort_unstable();
+    assert_eq!(ids, ["1", "2", "7"]);
+    assert_eq!(pins["7"]["settings"]["state"], "push-pull");
+    assert_eq!(pins["2"]["info"]["name"], "BUTTON 2");
+    assert_eq!(pins["1"]["info"]["tags"], serde_json::json!(["relay"]));
+
+    // unchanged and retagged pins keep their state and value
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/1/value")
+        .to_request();
+    assert_eq!(test::call_and_read_body(&app, req).await, "1");
+
+    json["http"]["path"] = "/api/v2".into();
+    std::fs::write(&path, json.to_string()).unwrap();
+    let req = test::TestRequest::post()
+        .uri("/api/v1/admin/reload")
         .to_request();
     let resp = test::call_service(&app, req).await;
-    assert!(resp.status().is_success());
+    assert_eq!(resp.status(), 400);
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpio/7/info")
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[actix_rt::test]
+async fn panic_halts_writes_until_resume() {
+    let mut cfg = sample_config();
+    cfg.gpios.get_mut(&PinId(42)).unwrap().safe_value = Some(1);
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    for (pin_id, settings, value) in [
+        (1, r#"{"state":"push-pull"}"#, Some("1")),
+        (2, r#"{"state":"pull-up","edge":"both"}"#, None),
+        (42, r#"{"state":"push-pull"}"#, Some("0")),
+    ] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/api/v1/gpio/{pin_id}/settings"))
+            .set_payload(settings)
+            .to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+        if let Some(value) = value {
+            let req = test::TestRequest::post()
+                .uri(&format!("/api/v1/gpio/{pin_id}/value"))
+                .set_payload(value)
+                .to_request();
+            assert!(test::call_service(&app, req).await.status().is_success());
+        }
+    }
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/admin/panic")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["halted"], true);
 
+    for (pin_id, value) in [(1, "0"), (42, "1")] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/v1/gpio/{pin_id}/value"))
+            .to_request();
+        assert_eq!(test::call_and_read_body(&app, req).await, value);
+    }
     let req = test::TestRequest::get()
-        .uri("/api/v1/gpio/1/settings")
+        .uri("/api/v1/gpio/2/settings")
         .to_request();
     let settings: Value = test::call_and_read_body_json(&app, req).await;
-    assert_eq!(settings["state"], "push-pull");
+    assert_eq!(settings["edge"], "none");
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .set_payload("1")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 423);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "locked");
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/42/settings")
+        .set_payload(r#"{"state":"open-drain"}"#)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 423);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/admin/resume")
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["halted"], false);
+
+    let req = test::TestRequest::post()
+        .uri("/api/v1/gpio/1/value")
+        .set_payload("1")
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+}
+
+// SIGHUP runs the same `reload_config` as the endpoint, signals themselves
+// are not delivered in tests
+#[cfg(unix)]
+#[actix_rt::test]
+async fn reload_config_keeps_serving_on_failure() {
+    let path = std::env::temp_dir().join(format!("gmgr-sighup-{}.json", std::process::id()));
+    std::fs::copy("config.json", &path).unwrap();
+    let cfg = Arc::new(AppConfig::load_from_file(&path).unwrap());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg, backend));
+    let state = AppState {
+        manager: manager.clone(),
+        config_path: Some(path.clone()),
+    };
+
+    let summary = state.reload_config().await.unwrap();
+    assert_eq!(summary.to_string(), "added [], removed [], changed []");
+
+    std::fs::write(&path, "{ not json").unwrap();
+    assert!(state.reload_config().await.is_err());
+    assert_eq!(manager.list_pins().await.len(), 3);
+
+    let mut json: Value =
+        serde_json::from_str(&std::fs::read_to_string("config.json").unwrap()).unwrap();
+    json["gpios"].as_object_mut().unwrap().remove("2");
+    std::fs::write(&path, json.to_string()).unwrap();
+    let summary = state.reload_config().await.unwrap();
+    assert_eq!(summary.removed, [PinId(2)]);
+    assert_eq!(summary.to_string(), "added [], removed [2], changed []");
+
+    std::fs::remove_file(&path).unwrap();
 }
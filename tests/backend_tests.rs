@@ -0,0 +1,443 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use gmgr::{
+    AppConfig, Bias, EdgeDetect, GpioBackend, GpioCapability, GpioManager, GpioState, MockClock,
+    MockGpioBackend, PinConfig, PinId, PinSettings, RetryPolicy, SimulateConfig,
+};
+
+fn sample_pin() -> PinConfig {
+    PinConfig {
+        name: "General IO 1".into(),
+        chip: "/dev/gpiochip1".into(),
+        line: 5,
+        capabilities: HashSet::from([GpioCapability::PushPull, GpioCapability::PullUp]),
+        ..Default::default()
+    }
+}
+
+#[actix_rt::test]
+async fn mock_settings_keyed_by_pin_id() {
+    let backend = MockGpioBackend::default();
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Both,
+        debounce_ms: 5,
+        ..Default::default()
+    };
+
+    backend
+        .set_settings(PinId(42), &sample_pin(), &settings, None)
+        .await
+        .unwrap();
+
+    let read = backend.get_settings(PinId(42)).await.unwrap();
+    assert_eq!(read.state, GpioState::PullUp);
+    assert_eq!(read.edge, EdgeDetect::Both);
+    assert_eq!(read.debounce_ms, 5);
+
+    let other = backend.get_settings(PinId(5)).await.unwrap();
+    assert_eq!(other.state, GpioState::Disabled);
+}
+
+/// Drives a backend purely through the trait, as the manager does.
+async fn write_then_read<B: GpioBackend>(backend: &B, values: &[(PinId, u8)]) -> Vec<u8> {
+    backend.set_values(values).await.unwrap();
+    let mut read = Vec::new();
+    for (pin_id, _) in values {
+        read.push(backend.read_value(*pin_id).await.unwrap());
+    }
+    read
+}
+
+#[actix_rt::test]
+async fn async_backend_round_trips_values() {
+    let backend = Arc::new(MockGpioBackend::default());
+    let settings = PinSettings {
+        state: GpioState::PushPull,
+        ..Default::default()
+    };
+    for pin_id in [PinId(1), PinId(2)] {
+        backend
+            .set_settings(pin_id, &sample_pin(), &settings, None)
+            .await
+            .unwrap();
+    }
+
+    // backend futures must be Send to run on the multi-threaded runtime
+    let spawned = Arc::clone(&backend);
+    let read = tokio::spawn(async move {
+        write_then_read(spawned.as_ref(), &[(PinId(1), 1), (PinId(2), 0)]).await
+    })
+    .await
+    .unwrap();
+    assert_eq!(read, vec![1, 0]);
+
+    backend.write_value(PinId(2), 1).await.unwrap();
+    assert_eq!(
+        write_then_read(backend.as_ref(), &[(PinId(1), 0)]).await,
+        vec![0]
+    );
+    assert_eq!(backend.read_value(PinId(2)).await.unwrap(), 1);
+}
+
+#[actix_rt::test]
+async fn batch_reads_return_each_pins_value() {
+    let backend = MockGpioBackend::default();
+    let settings = PinSettings {
+        state: GpioState::PushPull,
+        ..Default::default()
+    };
+    for pin_id in [PinId(1), PinId(2), PinId(3)] {
+        backend
+            .set_settings(pin_id, &sample_pin(), &settings, None)
+            .await
+            .unwrap();
+    }
+    backend
+        .set_values(&[(PinId(1), 1), (PinId(2), 0), (PinId(3), 1)])
+        .await
+        .unwrap();
+
+    let read = backend
+        .read_values(&[PinId(3), PinId(2), PinId(1)])
+        .await
+        .unwrap();
+    assert_eq!(read, vec![(PinId(3), 1), (PinId(2), 0), (PinId(1), 1)]);
+
+    assert!(backend.read_values(&[PinId(1), PinId(9)]).await.is_err());
+}
+
+#[actix_rt::test]
+async fn mock_echoes_drive_strength() {
+    let backend = MockGpioBackend::default();
+    let settings = PinSettings {
+        state: GpioState::PushPull,
+        drive_strength_ma: Some(12),
+        ..Default::default()
+    };
+
+    backend
+        .set_settings(PinId(42), &sample_pin(), &settings, None)
+        .await
+        .unwrap();
+
+    let read = backend.get_settings(PinId(42)).await.unwrap();
+    assert_eq!(read.drive_strength_ma, Some(12));
+    let json = serde_json::to_value(&read).unwrap();
+    assert_eq!(json["drive_strength_ma"], 12);
+}
+
+#[actix_rt::test]
+async fn output_reads_use_last_written_value() {
+    let backend = MockGpioBackend::default();
+    let settings = PinSettings {
+        state: GpioState::PushPull,
+        ..Default::default()
+    };
+    backend
+        .set_settings(PinId(42), &sample_pin(), &settings, None)
+        .await
+        .unwrap();
+
+    backend.write_value(PinId(42), 1).await.unwrap();
+    assert_eq!(backend.read_value(PinId(42)).await.unwrap(), 1);
+    assert_eq!(
+        backend.read_values(&[PinId(42)]).await.unwrap(),
+        vec![(PinId(42), 1)]
+    );
+    assert_eq!(backend.line_reads(), 0);
+
+    assert_eq!(backend.read_hardware_value(PinId(42)).await.unwrap(), 1);
+    assert_eq!(backend.line_reads(), 1);
+
+    // reconfiguring forgets the written value
+    backend
+        .set_settings(PinId(42), &sample_pin(), &settings, None)
+        .await
+        .unwrap();
+    assert_eq!(backend.read_value(PinId(42)).await.unwrap(), 1);
+    assert_eq!(backend.line_reads(), 2);
+}
+
+#[actix_rt::test]
+async fn open_drain_release_follows_external_pull() {
+    let backend = MockGpioBackend::default();
+    let open_drain = PinSettings {
+        state: GpioState::OpenDrain,
+        ..Default::default()
+    };
+    backend
+        .set_settings(PinId(42), &sample_pin(), &open_drain, None)
+        .await
+        .unwrap();
+
+    // driven low reads low whatever the pull
+    backend.set_external_pull(PinId(42), Some(1)).unwrap();
+    backend.write_value(PinId(42), 0).await.unwrap();
+    assert_eq!(backend.read_value(PinId(42)).await.unwrap(), 0);
+
+    // released, the line follows the resistor
+    backend.write_value(PinId(42), 1).await.unwrap();
+    assert_eq!(backend.read_value(PinId(42)).await.unwrap(), 1);
+    backend.set_external_pull(PinId(42), Some(0)).unwrap();
+    assert_eq!(backend.read_value(PinId(42)).await.unwrap(), 0);
+    assert_eq!(backend.physical_value(PinId(42)), Some(0));
+
+    // without a resistor the bias pulls it
+    backend.set_external_pull(PinId(42), None).unwrap();
+    let biased = PinSettings {
+        bias: Some(Bias::PullUp),
+        ..open_drain
+    };
+    backend
+        .set_settings(PinId(42), &sample_pin(), &biased, None)
+        .await
+        .unwrap();
+    backend.write_value(PinId(42), 1).await.unwrap();
+    assert_eq!(backend.read_value(PinId(42)).await.unwrap(), 1);
+}
+
+#[actix_rt::test]
+async fn open_source_drives_high_only() {
+    let backend = MockGpioBackend::default();
+    let settings = PinSettings {
+        state: GpioState::OpenSource,
+        ..Default::default()
+    };
+    backend
+        .set_settings(PinId(42), &sample_pin(), &settings, None)
+        .await
+        .unwrap();
+    backend.set_external_pull(PinId(42), Some(1)).unwrap();
+
+    backend.write_value(PinId(42), 1).await.unwrap();
+    assert_eq!(backend.read_value(PinId(42)).await.unwrap(), 1);
+    // released to a pull-up it still reads high
+    backend.write_value(PinId(42), 0).await.unwrap();
+    assert_eq!(backend.read_value(PinId(42)).await.unwrap(), 1);
+    backend.set_external_pull(PinId(42), Some(0)).unwrap();
+    assert_eq!(backend.read_value(PinId(42)).await.unwrap(), 0);
+}
+
+#[actix_rt::test]
+async fn active_low_inverts_physical_level_and_edges() {
+    let backend = MockGpioBackend::default();
+    let cfg = Arc::new(AppConfig::load_from_file("config.json").unwrap());
+    let manager = GpioManager::new(cfg, Arc::new(MockGpioBackend::default()));
+    let mut events = manager.subscribe_events();
+
+    let pin = PinConfig {
+        active_low: true,
+        ..sample_pin()
+    };
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Both,
+        debounce_ms: 0,
+        ..Default::default()
+    };
+    backend
+        .set_settings(PinId(42), &pin, &settings, Some(manager.event_handler()))
+        .await
+        .unwrap();
+
+    backend.set_input_level(PinId(42), 0).unwrap();
+    assert_eq!(backend.physical_value(PinId(42)), Some(0));
+    assert_eq!(backend.read_value(PinId(42)).await.unwrap(), 1);
+    assert_eq!(events.try_recv().unwrap().edge, EdgeDetect::Rising);
+
+    backend.set_input_level(PinId(42), 1).unwrap();
+    assert_eq!(backend.physical_value(PinId(42)), Some(1));
+    assert_eq!(backend.read_value(PinId(42)).await.unwrap(), 0);
+    assert_eq!(events.try_recv().unwrap().edge, EdgeDetect::Falling);
+}
+
+#[actix_rt::test]
+async fn input_level_changes_dispatch_configured_edges() {
+    let backend = Arc::new(MockGpioBackend::default());
+    let cfg = Arc::new(AppConfig::load_from_file("config.json").unwrap());
+    let manager = GpioManager::new(cfg, backend.clone());
+    let mut events = manager.subscribe_events();
+
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Rising,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(2), &settings).await.unwrap();
+
+    backend.set_input_level(PinId(2), 1).unwrap();
+    assert_eq!(manager.read_value(PinId(2)).await.unwrap(), 1);
+    let event = events.try_recv().unwrap();
+    assert_eq!((event.pin_id, event.edge), (PinId(2), EdgeDetect::Rising));
+
+    // falling edges are not configured
+    backend.set_input_level(PinId(2), 0).unwrap();
+    assert_eq!(manager.read_value(PinId(2)).await.unwrap(), 0);
+    assert!(events.try_recv().is_err());
+
+    assert!(backend.set_input_level(PinId(2), 2).is_err());
+    let output = PinSettings {
+        state: GpioState::PushPull,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(1), &output).await.unwrap();
+    assert!(backend.set_input_level(PinId(1), 1).is_err());
+}
+
+#[actix_rt::test]
+async fn simulation_dispatches_alternating_edges() {
+    let backend = MockGpioBackend::default();
+    let cfg = Arc::new(AppConfig::load_from_file("config.json").unwrap());
+    let manager = GpioManager::new(cfg, Arc::new(MockGpioBackend::default()));
+    let mut events = manager.subscribe_events();
+
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Both,
+        debounce_ms: 0,
+        ..Default::default()
+    };
+    backend
+        .set_settings(
+            PinId(2),
+            &sample_pin(),
+            &settings,
+            Some(manager.event_handler()),
+        )
+        .await
+        .unwrap();
+    backend.start_simulation(PinId(2), 10).unwrap();
+
+    for expected in [EdgeDetect::Rising, EdgeDetect::Falling, EdgeDetect::Rising] {
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.pin_id, PinId(2));
+        assert_eq!(event.edge, expected);
+    }
+}
+
+#[actix_rt::test]
+async fn configured_simulation_follows_pattern() {
+    let backend = MockGpioBackend::default();
+    let cfg = Arc::new(AppConfig::load_from_file("config.json").unwrap());
+    let manager = GpioManager::new(cfg, Arc::new(MockGpioBackend::default()));
+    let mut events = manager.subscribe_events();
+
+    let pin = PinConfig {
+        simulate: Some(SimulateConfig {
+            period_ms: 10,
+            pattern: vec![1, 1, 0],
+        }),
+        ..sample_pin()
+    };
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Rising,
+        debounce_ms: 0,
+        ..Default::default()
+    };
+    backend
+        .set_settings(PinId(42), &pin, &settings, Some(manager.event_handler()))
+        .await
+        .unwrap();
+
+    let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.pin_id, PinId(42));
+    assert_eq!(event.edge, EdgeDetect::Rising);
+
+    // disabling the pin stops the simulation
+    backend
+        .set_settings(PinId(42), &pin, &PinSettings::default(), None)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(backend.physical_value(PinId(42)), Some(0));
+}
+
+#[actix_rt::test]
+async fn mock_clock_stamps_edges_and_drives_debounce() {
+    let clock = Arc::new(MockClock::new(1_000));
+    let backend = MockGpioBackend::with_clock(clock.clone());
+    let cfg = Arc::new(AppConfig::load_from_file("config.json").unwrap());
+    let manager = GpioManager::new(cfg, Arc::new(MockGpioBackend::default()));
+    let mut events = manager.subscribe_events();
+
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Both,
+        debounce_ms: 50,
+        ..Default::default()
+    };
+    backend
+        .set_settings(
+            PinId(42),
+            &sample_pin(),
+            &settings,
+            Some(manager.event_handler()),
+        )
+        .await
+        .unwrap();
+
+    backend.set_input_level(PinId(42), 1).unwrap();
+    assert_eq!(events.try_recv().unwrap().timestamp_ms, 1_000);
+
+    // inside the debounce window
+    clock.advance(10);
+    backend.set_input_level(PinId(42), 0).unwrap();
+    assert!(events.try_recv().is_err());
+
+    clock.set(2_000);
+    backend.set_input_level(PinId(42), 1).unwrap();
+    let event = events.try_recv().unwrap();
+    assert_eq!(event.edge, EdgeDetect::Rising);
+    assert_eq!(event.timestamp_ms, 2_000);
+}
+
+#[test]
+fn retry_policy_retries_only_transient_failures() {
+    let policy = RetryPolicy {
+        attempts: 3,
+        delay: Duration::from_millis(1),
+    };
+    let transient = |e: &&str| *e == "EAGAIN";
+
+    // fails once, then succeeds on the second attempt
+    let calls = Cell::new(0);
+    let res = policy.run("request lines", transient, || {
+        calls.set(calls.get() + 1);
+        if calls.get() == 1 {
+            Err("EAGAIN")
+        } else {
+            Ok(7)
+        }
+    });
+    assert_eq!(res, Ok(7));
+    assert_eq!(calls.get(), 2);
+
+    // other errors are returned right away
+    calls.set(0);
+    let res: Result<(), _> = policy.run("request lines", transient, || {
+        calls.set(calls.get() + 1);
+        Err("EBUSY")
+    });
+    assert_eq!(res, Err("EBUSY"));
+    assert_eq!(calls.get(), 1);
+
+    // transient errors give up after the configured attempts
+    calls.set(0);
+    let res: Result<(), _> = policy.run("request lines", transient, || {
+        calls.set(calls.get() + 1);
+        Err("EAGAIN")
+    });
+    assert_eq!(res, Err("EAGAIN"));
+    assert_eq!(calls.get(), 3);
+}
@@ -0,0 +1,323 @@
+use gmgr::{AppConfig, AppError, EdgeDetect, EventClockSource, GpioState, PinConfig, PinId};
+
+#[test]
+fn yaml_and_json_configs_are_equivalent() {
+    let json = AppConfig::load_from_file("config.json").unwrap();
+    let yaml = AppConfig::load_from_file("tests/fixtures/config.yaml").unwrap();
+
+    assert_eq!(json, yaml);
+}
+
+#[test]
+fn invalid_yaml_is_config_error() {
+    let path = std::env::temp_dir().join("gmgr-invalid-config.yml");
+    std::fs::write(&path, "http: [unterminated").unwrap();
+
+    let err = AppConfig::load_from_file(&path).unwrap_err();
+    assert!(err.to_string().contains("invalid config yaml"));
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn toml_string_keys_map_to_pin_ids() {
+    let path = std::env::temp_dir().join("gmgr-pin-keys.toml");
+    std::fs::write(
+        &path,
+        r#"
+broadcast_capacity = 16
+event_history_capacity = 8
+
+[http]
+host = "localhost:8080"
+path = "/api/v1"
+timeout = 30
+
+[gpios."42"]
+name = "General IO 1"
+chip = "/dev/gpiochip1"
+line = 5
+capabilities = ["push-pull"]
+"#,
+    )
+    .unwrap();
+
+    let cfg = AppConfig::load_from_file(&path).unwrap();
+    assert_eq!(cfg.gpios[&PinId(42)].name, "General IO 1");
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn toml_round_trip() {
+    let json = AppConfig::load_from_file("config.json").unwrap();
+    let path = std::env::temp_dir().join("gmgr-round-trip.toml");
+    std::fs::write(&path, toml::to_string(&json).unwrap()).unwrap();
+
+    let toml = AppConfig::load_from_file(&path).unwrap();
+    assert_eq!(json, toml);
+
+    let _ = std::fs::remove_file(&path);
+}
+
+// env vars are process-global, so every override case lives in one test
+#[test]
+fn env_overrides_http_config() {
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+
+    unsafe {
+        std::env::set_var("GMGR_HTTP_HOST", "0.0.0.0:9000");
+        std::env::set_var("GMGR_HTTP_PORT", "9090");
+        std::env::set_var("GMGR_HTTP_UNIX_SOCKET", "/tmp/gmgr-test.sock");
+        std::env::set_var("GMGR_HTTP_PATH", "/api/test, /api/next");
+    }
+    cfg.apply_env_overrides().unwrap();
+
+    assert_eq!(cfg.http.host.as_deref(), Some("0.0.0.0:9090"));
+    assert_eq!(cfg.http.unix_socket.as_deref(), Some("/tmp/gmgr-test.sock"));
+    assert_eq!(cfg.http.path, ["/api/test", "/api/next"]);
+
+    unsafe {
+        std::env::set_var("GMGR_HTTP_PORT", "not-a-port");
+    }
+    let err = cfg.apply_env_overrides().unwrap_err();
+    assert!(err.to_string().contains("GMGR_HTTP_PORT"));
+
+    unsafe {
+        std::env::remove_var("GMGR_HTTP_HOST");
+        std::env::remove_var("GMGR_HTTP_PORT");
+        std::env::remove_var("GMGR_HTTP_UNIX_SOCKET");
+        std::env::remove_var("GMGR_HTTP_PATH");
+    }
+}
+
+#[test]
+fn duplicate_chip_line_is_rejected() {
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+    let mut dup = cfg.gpios[&PinId(1)].clone();
+    dup.name = "LED 1 alias".into();
+    cfg.gpios.insert(PinId(7), dup);
+
+    let err = cfg.validate().unwrap_err();
+    assert!(err.to_string().contains("[1, 7]"));
+}
+
+#[test]
+fn empty_capabilities_are_rejected() {
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+    cfg.gpios.get_mut(&PinId(2)).unwrap().capabilities.clear();
+
+    let err = cfg.validate().unwrap_err();
+    assert!(err.to_string().contains("pin 2 has no capabilities"));
+}
+
+#[test]
+fn libgpiod_event_settings_default_and_validate() {
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+    assert_eq!(cfg.libgpiod_event_buffer, 64);
+    assert_eq!(cfg.libgpiod_wait_timeout_ms, 10);
+    assert_eq!(cfg.libgpiod_event_clock, EventClockSource::Realtime);
+    assert_eq!(
+        serde_json::from_str::<EventClockSource>("\"hte\"").unwrap(),
+        EventClockSource::Hte
+    );
+    assert!(serde_json::from_str::<EventClockSource>("\"boottime\"").is_err());
+
+    cfg.libgpiod_event_buffer = 0;
+    let err = cfg.validate().unwrap_err();
+    assert!(err.to_string().contains("libgpiod_event_buffer"));
+
+    cfg.libgpiod_event_buffer = 64;
+    assert_eq!(cfg.libgpiod_retry_attempts, 3);
+    assert_eq!(cfg.libgpiod_retry_delay_ms, 10);
+    cfg.libgpiod_retry_attempts = 0;
+    let err = cfg.validate().unwrap_err();
+    assert!(err.to_string().contains("libgpiod_retry_attempts"));
+
+    cfg.libgpiod_retry_attempts = 3;
+    assert_eq!(cfg.consumer_label(), "gmgr");
+    cfg.consumer_label = Some("gmgr-door".into());
+    assert_eq!(cfg.consumer_label(), "gmgr-door");
+    assert!(cfg.validate().is_ok());
+    cfg.consumer_label = Some("x".repeat(32));
+    let err = cfg.validate().unwrap_err();
+    assert!(err.to_string().contains("consumer_label"));
+}
+
+#[cfg(not(feature = "tls"))]
+#[test]
+fn tls_requires_tls_feature() {
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+    cfg.http.tls = Some(gmgr::TlsConfig {
+        cert_path: "tests/fixtures/tls/cert.pem".into(),
+        key_path: "tests/fixtures/tls/key.pem".into(),
+    });
+
+    let err = cfg.validate().unwrap_err();
+    assert!(err.to_string().contains("tls feature"));
+}
+
+#[test]
+fn http_timeout_is_in_seconds() {
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+    assert_eq!(
+        cfg.http.request_timeout(),
+        Some(std::time::Duration::from_secs(30))
+    );
+
+    cfg.http.timeout = 0;
+    assert_eq!(cfg.http.request_timeout(), None);
+}
+
+#[test]
+fn pin_id_keeps_integer_wire_format() {
+    assert_eq!(serde_json::to_value(PinId(7)).unwrap(), 7);
+    assert_eq!(serde_json::from_str::<PinId>("7").unwrap(), PinId(7));
+    assert_eq!("42".parse::<PinId>().unwrap(), PinId(42));
+    assert!("led".parse::<PinId>().is_err());
+
+    let cfg = AppConfig::load_from_file("config.json").unwrap();
+    let json = serde_json::to_value(&cfg).unwrap();
+    assert!(json["gpios"]["1"].is_object());
+}
+
+#[test]
+fn state_and_edge_strings_match_wire_format() {
+    assert_eq!(GpioState::PushPull.to_string(), "push-pull");
+    assert_eq!(
+        "push-pull".parse::<GpioState>().unwrap(),
+        GpioState::PushPull
+    );
+    assert_eq!(EdgeDetect::Falling.to_string(), "falling");
+    assert_eq!("both".parse::<EdgeDetect>().unwrap(), EdgeDetect::Both);
+    assert!("PushPull".parse::<GpioState>().is_err());
+    assert!("up".parse::<EdgeDetect>().is_err());
+
+    for state in [GpioState::OpenDrain, GpioState::PullDown, GpioState::Pwm] {
+        let wire = serde_json::to_value(state).unwrap();
+        assert_eq!(wire, state.to_string());
+        assert_eq!(state.to_string().parse::<GpioState>().unwrap(), state);
+    }
+}
+
+#[test]
+fn builder_validates_like_loaded_files() {
+    let cfg = AppConfig::builder().broadcast_capacity(16).build().unwrap();
+    assert_eq!(cfg.broadcast_capacity, 16);
+    assert_eq!(cfg.http.path, ["/api/v1"]);
+    assert!(cfg.gpios.is_empty());
+
+    let err = AppConfig::builder()
+        .add_pin(3, PinConfig::default())
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("pin 3 has no capabilities"));
+
+    assert!(AppConfig::builder().broadcast_capacity(0).build().is_err());
+}
+
+#[test]
+fn safe_value_must_be_binary() {
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+    cfg.gpios.get_mut(&PinId(1)).unwrap().safe_value = Some(2);
+
+    let err = cfg.validate().unwrap_err();
+    assert!(err.to_string().contains("pin 1 safe_value"));
+
+    let pin: gmgr::PinConfig = serde_json::from_str(
+        r#"{"name":"LED","chip":"/dev/gpiochip0","line":2,"capabilities":["push-pull"],"safe_state":1}"#,
+    )
+    .unwrap();
+    assert_eq!(pin.safe_value, Some(1));
+}
+
+#[test]
+fn cors_origins_must_be_urls() {
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+    cfg.http.cors = Some(gmgr::CorsConfig {
+        allowed_origins: vec!["*".into(), "dashboard".into()],
+        allow_credentials: false,
+    });
+
+    let err = cfg.validate().unwrap_err();
+    assert!(err.to_string().contains("invalid cors origin: dashboard"));
+}
+
+#[cfg(not(feature = "mqtt"))]
+#[test]
+fn mqtt_requires_mqtt_feature() {
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+    cfg.mqtt = Some(gmgr::MqttConfig {
+        broker: "localhost:1883".into(),
+        base_topic: "gmgr".into(),
+        client_id: "gmgr".into(),
+    });
+
+    let err = cfg.validate().unwrap_err();
+    assert!(err.to_string().contains("mqtt feature"));
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_socket_mode_is_applied() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::temp_dir().join(format!("gmgr-mode-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let _listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+    cfg.http.unix_socket = Some(path.to_string_lossy().into_owned());
+    cfg.http.unix_socket_mode = Some("0640".into());
+    cfg.http.apply_socket_mode().unwrap();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o7777, 0o640);
+    std::fs::remove_file(&path).unwrap();
+
+    let err = cfg.http.apply_socket_mode().unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("failed to set unix socket mode 640")
+    );
+
+    cfg.http.unix_socket_mode = Some("rw-rw----".into());
+    let err = cfg.validate().unwrap_err();
+    assert!(err.to_string().contains("invalid unix_socket_mode"));
+}
+
+#[test]
+fn http_path_takes_one_or_many() {
+    let json = std::fs::read_to_string("config.json").unwrap();
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["http"]["path"] = serde_json::json!(["/api/v1", "/api/v2"]);
+    let cfg: AppConfig = serde_json::from_value(value.clone()).unwrap();
+    assert_eq!(cfg.http.path, ["/api/v1", "/api/v2"]);
+    cfg.validate().unwrap();
+
+    value["http"]["path"] = serde_json::json!(["/api/v1", "/api/v1/"]);
+    let cfg: AppConfig = serde_json::from_value(value.clone()).unwrap();
+    assert!(cfg.validate().unwrap_err().to_string().contains("distinct"));
+
+    value["http"]["path"] = serde_json::json!([]);
+    let cfg: AppConfig = serde_json::from_value(value).unwrap();
+    assert!(cfg.validate().is_err());
+}
+
+#[test]
+fn validate_reports_every_problem() {
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+    let led = cfg.gpios.get_mut(&PinId(1)).unwrap();
+    led.initial_value = Some(2);
+    led.initial_state = Some(GpioState::PullUp);
+    cfg.gpios.get_mut(&PinId(2)).unwrap().capabilities.clear();
+    cfg.broadcast_capacity = 0;
+
+    let AppError::Validation(errors) = cfg.validate().unwrap_err() else {
+        panic!("expected a validation error");
+    };
+    assert_eq!(errors.len(), 4);
+    assert!(errors[0].contains("pin 1 initial_value must be 0 or 1"));
+    assert!(errors[1].contains("pin 1 initial_state pull-up is not one of its capabilities"));
+    assert!(errors[2].contains("pin 2 has no capabilities"));
+    assert!(errors[3].contains("broadcast_capacity"));
+}
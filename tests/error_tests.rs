@@ -0,0 +1,110 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use actix_web::{ResponseError, body::to_bytes};
+use gmgr::{AppError, PinId};
+use serde_json::Value;
+
+async fn error_body(err: AppError) -> (u16, Value) {
+    let resp = err.error_response();
+    let status = resp.status().as_u16();
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[actix_rt::test]
+async fn error_codes_are_stable() {
+    let cases = [
+        (AppError::NotFoundPin(PinId(7)), 404, "pin_not_found"),
+        (AppError::NotFoundPinName("x".into()), 404, "pin_not_found"),
+        (AppError::InvalidState("x".into()), 400, "invalid_state"),
+        (
+            AppError::InvalidPinState {
+                pin_id: PinId(7),
+                reason: "x".into(),
+            },
+            400,
+            "invalid_state",
+        ),
+        (AppError::InvalidValue("x".into()), 400, "invalid_value"),
+        (
+            AppError::ValueOutOfRange {
+                pin_id: None,
+                value: 2,
+                min: 0,
+                max: 1,
+            },
+            400,
+            "invalid_value",
+        ),
+        (
+            AppError::PermissionDenied("x".into()),
+            403,
+            "permission_denied",
+        ),
+        (
+            AppError::Validation(vec!["x".into()]),
+            400,
+            "validation_error",
+        ),
+        (AppError::Config("x".into()), 500, "config_error"),
+        (AppError::Gpio("x".into()), 500, "gpio_error"),
+        (AppError::Unavailable("x".into()), 503, "unavailable"),
+        (AppError::Timeout("x".into()), 504, "timeout"),
+        (
+            AppError::LineBusy {
+                pin_id: PinId(7),
+                consumer: "x".into(),
+            },
+            409,
+            "line_busy",
+        ),
+        (AppError::Conflict("x".into()), 409, "conflict"),
+        (AppError::Locked("x".into()), 423, "locked"),
+        (
+            AppError::RateLimited(Duration::from_secs(1)),
+            429,
+            "rate_limited",
+        ),
+    ];
+
+    for (err, status, code) in cases {
+        let message = err.to_string();
+        let (actual_status, body) = error_body(err).await;
+        assert_eq!(actual_status, status);
+        assert_eq!(body["code"], code);
+        assert_eq!(body["error"], message);
+    }
+}
+
+#[actix_rt::test]
+async fn pin_errors_carry_pin_id() {
+    let (_, body) = error_body(AppError::NotFoundPin(PinId(42))).await;
+    assert_eq!(body["pin_id"], 42);
+
+    let (_, body) = error_body(AppError::InvalidPinState {
+        pin_id: PinId(2),
+        reason: "state not supported".into(),
+    })
+    .await;
+    assert_eq!(body["pin_id"], 2);
+
+    let (_, body) = error_body(AppError::Gpio("x".into())).await;
+    assert!(body.get("pin_id").is_none());
+}
+
+#[actix_rt::test]
+async fn poisoned_lock_is_unavailable() {
+    let lock = Arc::new(RwLock::new(0));
+    let poisoner = lock.clone();
+    let _ = std::thread::spawn(move || {
+        let _guard = poisoner.write().unwrap();
+        panic!("poison the lock");
+    })
+    .join();
+
+    let err: AppError = lock.read().unwrap_err().into();
+    let (status, body) = error_body(err).await;
+    assert_eq!(status, 503);
+    assert_eq!(body["code"], "unavailable");
+}
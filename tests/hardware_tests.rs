@@ -0,0 +1,318 @@
+#![cfg(feature = "hardware-gpio")]
+
+// These tests need a real or simulated (gpio-sim) chip with at least two free
+// lines, run them with `cargo test -- --ignored` and GMGR_TEST_CHIP set.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use gmgr::{
+    AppConfig, AppError, Bias, EdgeDetect, EventClockSource, GpioBackend, GpioCapability,
+    GpioManager, GpioState, LibgpiodBackend, PinConfig, PinId, PinSettings, RetryPolicy,
+};
+
+fn test_chip() -> String {
+    std::env::var("GMGR_TEST_CHIP").unwrap_or_else(|_| "/dev/gpiochip0".into())
+}
+
+fn test_pin(line: u32) -> PinConfig {
+    PinConfig {
+        name: format!("test line {line}"),
+        chip: test_chip(),
+        line,
+        capabilities: HashSet::from([GpioCapability::PushPull]),
+        ..Default::default()
+    }
+}
+
+#[actix_rt::test]
+#[ignore = "requires a gpio chip"]
+async fn chip_opened_once_for_shared_pins() {
+    let backend = LibgpiodBackend::new(
+        64,
+        Duration::from_millis(10),
+        EventClockSource::Realtime,
+        RetryPolicy::default(),
+    )
+    .unwrap();
+    let settings = PinSettings {
+        state: GpioState::PushPull,
+        ..Default::default()
+    };
+
+    backend
+        .set_settings(PinId(1), &test_pin(0), &settings, None)
+        .await
+        .unwrap();
+    backend
+        .set_settings(PinId(2), &test_pin(1), &settings, None)
+        .await
+        .unwrap();
+
+    assert_eq!(backend.opened_chips(), 1);
+}
+
+#[actix_rt::test]
+#[ignore = "requires a gpio chip"]
+async fn hardware_settings_match_applied_settings() {
+    let backend = LibgpiodBackend::new(
+        64,
+        Duration::from_millis(10),
+        EventClockSource::Realtime,
+        RetryPolicy::default(),
+    )
+    .unwrap();
+    let settings = PinSettings {
+        state: GpioState::PullDown,
+        edge: EdgeDetect::Rising,
+        debounce_ms: 5,
+        ..Default::default()
+    };
+
+    backend
+        .set_settings(PinId(1), &test_pin(0), &settings, None)
+        .await
+        .unwrap();
+
+    let read = backend.read_hardware_settings(PinId(1)).await.unwrap();
+    assert_eq!(read.state, GpioState::PullDown);
+    assert_eq!(read.edge, EdgeDetect::Rising);
+    assert_eq!(read.debounce_ms, 5);
+
+    let unused = backend.read_hardware_settings(PinId(2)).await.unwrap();
+    assert_eq!(unused.state, GpioState::Disabled);
+}
+
+#[actix_rt::test]
+#[ignore = "requires a gpio chip"]
+async fn output_bias_is_applied() {
+    let backend = LibgpiodBackend::new(
+        64,
+        Duration::from_millis(10),
+        EventClockSource::Realtime,
+        RetryPolicy::default(),
+    )
+    .unwrap();
+    let settings = PinSettings {
+        state: GpioState::OpenDrain,
+        bias: Some(Bias::PullUp),
+        ..Default::default()
+    };
+
+    backend
+        .set_settings(PinId(1), &test_pin(0), &settings, None)
+        .await
+        .unwrap();
+
+    let read = backend.read_hardware_settings(PinId(1)).await.unwrap();
+    assert_eq!(read.state, GpioState::OpenDrain);
+    assert_eq!(read.bias, Some(Bias::PullUp));
+}
+
+#[actix_rt::test]
+#[ignore = "requires a gpio chip"]
+async fn floating_bias_is_as_is_unless_disabled() {
+    let backend = LibgpiodBackend::new(
+        64,
+        Duration::from_millis(10),
+        EventClockSource::Realtime,
+        RetryPolicy::default(),
+    )
+    .unwrap();
+    let chip = libgpiod::chip::Chip::open(&test_chip()).unwrap();
+    let pin = PinConfig {
+        capabilities: HashSet::from([GpioCapability::Floating]),
+        ..test_pin(0)
+    };
+
+    let floating = PinSettings {
+        state: GpioState::Floating,
+        ..Default::default()
+    };
+    backend
+        .set_settings(PinId(1), &pin, &floating, None)
+        .await
+        .unwrap();
+    assert_eq!(chip.line_info(0).unwrap().bias().unwrap(), None);
+    let read = backend.read_hardware_settings(PinId(1)).await.unwrap();
+    assert_eq!((read.state, read.bias), (GpioState::Floating, None));
+
+    let disabled = PinSettings {
+        bias: Some(Bias::Disabled),
+        ..floating
+    };
+    backend
+        .set_settings(PinId(1), &pin, &disabled, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        chip.line_info(0).unwrap().bias().unwrap(),
+        Some(libgpiod::line::Bias::Disabled)
+    );
+    let read = backend.read_hardware_settings(PinId(1)).await.unwrap();
+    assert_eq!(
+        (read.state, read.bias),
+        (GpioState::Floating, Some(Bias::Disabled))
+    );
+}
+
+#[test]
+fn event_buffer_and_timeout_respected() {
+    let backend = LibgpiodBackend::new(
+        256,
+        Duration::from_millis(50),
+        EventClockSource::Realtime,
+        RetryPolicy::default(),
+    )
+    .unwrap();
+    assert_eq!(backend.event_buffer_capacity(), 256);
+    assert_eq!(backend.event_wait_timeout(), Duration::from_millis(50));
+
+    assert!(
+        LibgpiodBackend::new(
+            0,
+            Duration::from_millis(10),
+            EventClockSource::Realtime,
+            RetryPolicy::default()
+        )
+        .is_err()
+    );
+}
+
+#[actix_rt::test]
+#[ignore = "requires a gpio chip"]
+async fn busy_line_names_its_consumer() {
+    let chip = libgpiod::chip::Chip::open(&test_chip()).unwrap();
+    let mut line_cfg = libgpiod::line::Config::new().unwrap();
+    line_cfg
+        .add_line_settings(&[0], libgpiod::line::Settings::new().unwrap())
+        .unwrap();
+    let mut req_cfg = libgpiod::request::Config::new().unwrap();
+    req_cfg.set_consumer("other-daemon").unwrap();
+    let _held = chip.request_lines(Some(&req_cfg), &line_cfg).unwrap();
+
+    let backend = LibgpiodBackend::new(
+        64,
+        Duration::from_millis(10),
+        EventClockSource::Realtime,
+        RetryPolicy::default(),
+    )
+    .unwrap();
+    let settings = PinSettings {
+        state: GpioState::PushPull,
+        ..Default::default()
+    };
+    let err = backend
+        .set_settings(PinId(1), &test_pin(0), &settings, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        AppError::LineBusy { pin_id: PinId(1), ref consumer } if consumer == "other-daemon"
+    ));
+}
+
+#[actix_rt::test]
+#[ignore = "requires a gpio chip"]
+async fn lines_requested_under_consumer_label() {
+    let backend = LibgpiodBackend::new(
+        64,
+        Duration::from_millis(10),
+        EventClockSource::Realtime,
+        RetryPolicy::default(),
+    )
+    .unwrap()
+    .with_consumer("gmgr-door");
+    let settings = PinSettings {
+        state: GpioState::PushPull,
+        ..Default::default()
+    };
+    backend
+        .set_settings(PinId(1), &test_pin(0), &settings, None)
+        .await
+        .unwrap();
+
+    let chip = libgpiod::chip::Chip::open(&test_chip()).unwrap();
+    let info = chip.line_info(0).unwrap();
+    assert_eq!(info.consumer().unwrap(), "gmgr-door");
+}
+
+#[actix_rt::test]
+#[ignore = "requires a gpio chip"]
+async fn event_clock_applied_to_edge_lines() {
+    let backend = LibgpiodBackend::new(
+        64,
+        Duration::from_millis(10),
+        EventClockSource::Monotonic,
+        RetryPolicy::default(),
+    )
+    .unwrap();
+    assert_eq!(backend.event_clock(), EventClockSource::Monotonic);
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Both,
+        ..Default::default()
+    };
+
+    backend
+        .set_settings(PinId(1), &test_pin(0), &settings, None)
+        .await
+        .unwrap();
+
+    let chip = libgpiod::chip::Chip::open(&test_chip()).unwrap();
+    let info = chip.line_info(0).unwrap();
+    assert_eq!(
+        info.event_clock().unwrap(),
+        libgpiod::line::EventClock::Monotonic
+    );
+}
+
+#[actix_rt::test]
+#[ignore = "requires a gpio chip"]
+async fn value_reads_not_starved_by_edge_pins() {
+    let lines: u32 = std::env::var("GMGR_TEST_LINES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(16);
+
+    let mut config = AppConfig::load_from_file("config.json").unwrap();
+    config.gpios = (0..lines)
+        .map(|line| {
+            let mut pin = test_pin(line);
+            pin.capabilities = HashSet::from([GpioCapability::PullUp]);
+            (PinId(line), pin)
+        })
+        .collect();
+    let backend = Arc::new(
+        LibgpiodBackend::new(
+            64,
+            Duration::from_millis(10),
+            EventClockSource::Realtime,
+            RetryPolicy::default(),
+        )
+        .unwrap(),
+    );
+    let manager = GpioManager::new(Arc::new(config), backend);
+
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Both,
+        ..Default::default()
+    };
+    for pin_id in 0..lines {
+        manager
+            .set_pin_settings(PinId(pin_id), &settings)
+            .await
+            .unwrap();
+    }
+
+    let started = Instant::now();
+    for _ in 0..100 {
+        for pin_id in 0..lines {
+            manager.read_value(PinId(pin_id)).await.unwrap();
+        }
+    }
+    // a listener holding the line lock through its wait would cost ~10ms per read
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
@@ -0,0 +1,800 @@
+use std::sync::Arc;
+
+use gmgr::{
+    AppConfig, AppError, Bias, EdgeDetect, EdgeEvent, GpioBackend, GpioManager, GpioState,
+    MAX_DEBOUNCE_MS, MAX_VOTE_SAMPLES, MockGpioBackend, PinId, PinSettings, PwmConfig,
+};
+
+fn sample_config() -> AppConfig {
+    AppConfig::load_from_file("config.json").unwrap()
+}
+
+#[actix_rt::test]
+async fn initialize_applies_initial_state_and_value() {
+    let mut cfg = sample_config();
+    let led = cfg.gpios.get_mut(&PinId(1)).unwrap();
+    led.initial_state = Some(GpioState::PushPull);
+    led.initial_value = Some(1);
+    // unsupported initial state is logged and skipped
+    cfg.gpios.get_mut(&PinId(2)).unwrap().initial_state = Some(GpioState::PushPull);
+
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(cfg), backend);
+    manager.initialize().await;
+
+    let settings = manager.get_pin_settings(PinId(1)).await.unwrap();
+    assert_eq!(settings.state, GpioState::PushPull);
+    assert_eq!(manager.read_value(PinId(1)).await.unwrap(), 1);
+
+    let settings = manager.get_pin_settings(PinId(2)).await.unwrap();
+    assert_eq!(settings.state, GpioState::Disabled);
+}
+
+#[actix_rt::test]
+async fn write_values_updates_all_pins_or_none() {
+    let mut cfg = sample_config();
+    let mut extra = cfg.gpios[&PinId(42)].clone();
+    extra.line = 6;
+    cfg.gpios.insert(PinId(43), extra);
+
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(cfg), backend);
+    for pin_id in [1, 42, 43] {
+        let settings = PinSettings {
+            state: GpioState::PushPull,
+            ..Default::default()
+        };
+        manager
+            .set_pin_settings(PinId(pin_id), &settings)
+            .await
+            .unwrap();
+    }
+
+    manager
+        .write_values(vec![(PinId(1), 1), (PinId(42), 1), (PinId(43), 1)])
+        .await
+        .unwrap();
+    for pin_id in [1, 42, 43] {
+        assert_eq!(manager.read_value(PinId(pin_id)).await.unwrap(), 1);
+    }
+
+    // pin 2 is not an output, so nothing is written
+    let err = manager
+        .write_values(vec![(PinId(1), 0), (PinId(2), 0)])
+        .await;
+    assert!(err.is_err());
+    assert_eq!(manager.read_value(PinId(1)).await.unwrap(), 1);
+
+    // a pin given twice ends at its last value
+    manager
+        .write_values(vec![(PinId(42), 0), (PinId(1), 0), (PinId(42), 1)])
+        .await
+        .unwrap();
+    assert_eq!(manager.read_value(PinId(1)).await.unwrap(), 0);
+    assert_eq!(manager.read_value(PinId(42)).await.unwrap(), 1);
+}
+
+#[test]
+fn pin_settings_validate_rejects_inconsistent_combinations() {
+    let pwm = PwmConfig {
+        frequency_hz: 100.0,
+        duty_cycle: 0.5,
+    };
+    let invalid = [
+        (GpioState::Error, EdgeDetect::None, 0, None),
+        (GpioState::Disabled, EdgeDetect::Rising, 0, None),
+        (GpioState::Disabled, EdgeDetect::None, 5, None),
+        (GpioState::PullUp, EdgeDetect::None, 5, None),
+        (GpioState::PushPull, EdgeDetect::Both, 0, None),
+        (GpioState::Pwm, EdgeDetect::None, 0, None),
+        (GpioState::PushPull, EdgeDetect::None, 0, Some(pwm)),
+        (
+            GpioState::Pwm,
+            EdgeDetect::None,
+            0,
+            Some(PwmConfig {
+                frequency_hz: 100.0,
+                duty_cycle: 1.5,
+            }),
+        ),
+    ];
+    for (state, edge, debounce_ms, pwm) in invalid {
+        let settings = PinSettings {
+            state,
+            edge,
+            debounce_ms,
+            pwm,
+            ..Default::default()
+        };
+        assert!(
+            settings.validate().is_err(),
+            "{settings:?} should be invalid"
+        );
+    }
+
+    let valid = [
+        (GpioState::Disabled, EdgeDetect::None, 0, None),
+        (GpioState::PushPull, EdgeDetect::None, 0, None),
+        (GpioState::PullDown, EdgeDetect::Falling, 10, None),
+        (GpioState::Pwm, EdgeDetect::None, 0, Some(pwm)),
+    ];
+    for (state, edge, debounce_ms, pwm) in valid {
+        let settings = PinSettings {
+            state,
+            edge,
+            debounce_ms,
+            pwm,
+            ..Default::default()
+        };
+        settings.validate().unwrap();
+    }
+}
+
+#[test]
+fn drive_strength_only_on_outputs() {
+    let mut settings = PinSettings {
+        state: GpioState::PullUp,
+        drive_strength_ma: Some(8),
+        ..Default::default()
+    };
+    assert!(matches!(
+        settings.validate(),
+        Err(AppError::InvalidState(_))
+    ));
+
+    settings.state = GpioState::OpenDrain;
+    settings.validate().unwrap();
+
+    settings.drive_strength_ma = Some(0);
+    assert!(matches!(
+        settings.validate(),
+        Err(AppError::InvalidValue(_))
+    ));
+}
+
+#[actix_rt::test]
+async fn open_drain_takes_pull_up_bias() {
+    let manager = GpioManager::new(
+        Arc::new(sample_config()),
+        Arc::new(MockGpioBackend::default()),
+    );
+    let settings = PinSettings {
+        state: GpioState::OpenDrain,
+        bias: Some(Bias::PullUp),
+        ..Default::default()
+    };
+
+    manager
+        .set_pin_settings(PinId(42), &settings)
+        .await
+        .unwrap();
+    let stored = manager.get_pin_settings(PinId(42)).await.unwrap();
+    assert_eq!(stored.bias, Some(Bias::PullUp));
+    assert_eq!(serde_json::to_value(&stored).unwrap()["bias"], "pull-up");
+
+    let conflicting = [
+        (GpioState::OpenDrain, Bias::PullDown),
+        (GpioState::OpenSource, Bias::PullUp),
+        (GpioState::PushPull, Bias::PullUp),
+        (GpioState::PullUp, Bias::PullDown),
+        (GpioState::Floating, Bias::PullUp),
+        (GpioState::Disabled, Bias::Disabled),
+    ];
+    for (state, bias) in conflicting {
+        let settings = PinSettings {
+            state,
+            bias: Some(bias),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err(), "{state} with {bias}");
+    }
+}
+
+#[actix_rt::test]
+async fn floating_keeps_explicit_bias_disable() {
+    let manager = GpioManager::new(
+        Arc::new(sample_config()),
+        Arc::new(MockGpioBackend::default()),
+    );
+
+    // plain floating leaves the pull as-is and reports no bias
+    let floating = PinSettings {
+        state: GpioState::Floating,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(2), &floating).await.unwrap();
+    let stored = manager.get_pin_settings(PinId(2)).await.unwrap();
+    assert_eq!(stored.bias, None);
+
+    let disabled = PinSettings {
+        bias: Some(Bias::Disabled),
+        ..floating
+    };
+    manager.set_pin_settings(PinId(2), &disabled).await.unwrap();
+    let stored = manager.get_pin_settings(PinId(2)).await.unwrap();
+    assert_eq!(stored.bias, Some(Bias::Disabled));
+    assert_eq!(serde_json::to_value(&stored).unwrap()["bias"], "disabled");
+}
+
+#[actix_rt::test]
+async fn invalid_settings_name_the_pin() {
+    let manager = GpioManager::new(
+        Arc::new(sample_config()),
+        Arc::new(MockGpioBackend::default()),
+    );
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        debounce_ms: 5,
+        ..Default::default()
+    };
+
+    let err = manager
+        .set_pin_settings(PinId(2), &settings)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        AppError::InvalidPinState {
+            pin_id: PinId(2),
+            ..
+        }
+    ));
+}
+
+#[actix_rt::test]
+async fn debounce_bounded_and_input_only() {
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(sample_config()), backend.clone());
+
+    let mut settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Both,
+        debounce_ms: MAX_DEBOUNCE_MS + 1,
+        ..Default::default()
+    };
+    let err = manager
+        .set_pin_settings(PinId(2), &settings)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::InvalidValue(_)));
+    settings.debounce_ms = MAX_DEBOUNCE_MS;
+    manager.set_pin_settings(PinId(2), &settings).await.unwrap();
+
+    let output = PinSettings {
+        state: GpioState::PushPull,
+        debounce_ms: 10,
+        ..Default::default()
+    };
+    let err = manager
+        .set_pin_settings(PinId(1), &output)
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("cannot set debounce on push-pull pin")
+    );
+    // the backends apply the same rule when called directly
+    let err = backend
+        .set_settings(PinId(1), &sample_config().gpios[&PinId(1)], &output, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::InvalidState(_)));
+}
+
+#[actix_rt::test]
+async fn pwm_toggles_mock_value() {
+    let mut cfg = sample_config();
+    cfg.gpios
+        .get_mut(&PinId(42))
+        .unwrap()
+        .capabilities
+        .insert(GpioState::Pwm);
+
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(cfg), backend);
+
+    // pwm needs both the state and its parameters
+    let settings = PinSettings {
+        state: GpioState::Pwm,
+        ..Default::default()
+    };
+    assert!(
+        manager
+            .set_pin_settings(PinId(42), &settings)
+            .await
+            .is_err()
+    );
+    // and only output-capable pins can run it
+    let settings = PinSettings {
+        state: GpioState::Pwm,
+        pwm: Some(PwmConfig {
+            frequency_hz: 100.0,
+            duty_cycle: 0.5,
+        }),
+        ..Default::default()
+    };
+    assert!(manager.set_pin_settings(PinId(2), &settings).await.is_err());
+
+    manager
+        .set_pin_settings(PinId(42), &settings)
+        .await
+        .unwrap();
+
+    let mut seen = [false; 2];
+    for _ in 0..200 {
+        seen[manager.read_value(PinId(42)).await.unwrap() as usize] = true;
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    assert_eq!(seen, [true, true]);
+
+    manager
+        .set_pin_settings(PinId(42), &PinSettings::default())
+        .await
+        .unwrap();
+}
+
+#[actix_rt::test]
+async fn halt_stops_pwm_on_a_supported_output_state() {
+    let mut cfg = sample_config();
+    cfg.gpios.get_mut(&PinId(42)).unwrap().capabilities =
+        [GpioState::Pwm, GpioState::OpenDrain].into_iter().collect();
+
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(cfg), backend);
+    let settings = PinSettings {
+        state: GpioState::Pwm,
+        pwm: Some(PwmConfig {
+            frequency_hz: 100.0,
+            duty_cycle: 0.5,
+        }),
+        ..Default::default()
+    };
+    manager
+        .set_pin_settings(PinId(42), &settings)
+        .await
+        .unwrap();
+
+    manager.halt().await;
+    let stopped = manager.get_pin_settings(PinId(42)).await.unwrap();
+    assert_eq!(stopped.state, GpioState::OpenDrain);
+    assert!(stopped.pwm.is_none());
+}
+
+#[actix_rt::test]
+async fn software_debounce_drops_bursts() {
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(sample_config()), backend);
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Both,
+        debounce_ms: 50,
+        software_debounce: true,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(2), &settings).await.unwrap();
+
+    let handler = manager.event_handler();
+    for timestamp_ms in [1000, 1005, 1020, 1049, 1050, 1070, 1200] {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(2),
+            edge: EdgeDetect::Rising,
+            timestamp_ms,
+            seq: 0,
+            count: 1,
+        });
+    }
+
+    let timestamps: Vec<u64> = manager
+        .get_events(PinId(2), None, None, None)
+        .await
+        .unwrap()
+        .iter()
+        .map(|e| e.timestamp_ms)
+        .collect();
+    assert_eq!(timestamps, [1000, 1050, 1200]);
+}
+
+#[actix_rt::test]
+async fn coalescing_merges_same_direction_bursts() {
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(sample_config()), backend);
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Both,
+        coalesce_ms: 50,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(2), &settings).await.unwrap();
+    let mut events = manager.subscribe_events();
+
+    let handler = manager.event_handler();
+    for (timestamp_ms, edge) in [
+        (1000, EdgeDetect::Rising),
+        (1005, EdgeDetect::Rising),
+        (1020, EdgeDetect::Rising),
+        (1049, EdgeDetect::Rising),
+        (1060, EdgeDetect::Falling),
+        (1070, EdgeDetect::Rising),
+        (1200, EdgeDetect::Rising),
+    ] {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(2),
+            edge,
+            timestamp_ms,
+            seq: 0,
+            count: 1,
+        });
+    }
+
+    let history: Vec<(u64, u32)> = manager
+        .get_events(PinId(2), None, None, None)
+        .await
+        .unwrap()
+        .iter()
+        .map(|e| (e.timestamp_ms, e.count))
+        .collect();
+    assert_eq!(history, [(1000, 4), (1060, 1), (1070, 1), (1200, 1)]);
+    assert_eq!(manager.event_stats(PinId(2)).await.unwrap().rising_count, 6);
+
+    // subscribers get the first edge of a burst as it happens, then updates
+    // of the same event until the final count
+    let received: Vec<(u64, u64, u32)> = std::iter::from_fn(|| events.try_recv().ok())
+        .map(|e| (e.timestamp_ms, e.seq, e.count))
+        .collect();
+    let burst = received[0].1;
+    assert_eq!(
+        received[..4],
+        [
+            (1000, burst, 1),
+            (1000, burst, 2),
+            (1000, burst, 3),
+            (1000, burst, 4)
+        ]
+    );
+    assert_eq!(
+        received[4..].iter().map(|e| (e.0, e.2)).collect::<Vec<_>>(),
+        [(1060, 1), (1070, 1), (1200, 1)]
+    );
+
+    let invalid = PinSettings {
+        state: GpioState::PullUp,
+        coalesce_ms: 50,
+        ..Default::default()
+    };
+    assert!(manager.set_pin_settings(PinId(2), &invalid).await.is_err());
+}
+
+#[actix_rt::test]
+async fn events_since_timestamp() {
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(sample_config()), backend);
+
+    let handler = manager.event_handler();
+    for (timestamp_ms, edge) in [
+        (100, EdgeDetect::Rising),
+        (100, EdgeDetect::Falling),
+        (200, EdgeDetect::Rising),
+        (300, EdgeDetect::Falling),
+    ] {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(2),
+            edge,
+            timestamp_ms,
+            seq: 0,
+            count: 1,
+        });
+    }
+
+    let timestamps =
+        |events: Vec<EdgeEvent>| -> Vec<u64> { events.iter().map(|e| e.timestamp_ms).collect() };
+
+    let events = manager
+        .get_events(PinId(2), None, None, Some(100))
+        .await
+        .unwrap();
+    assert_eq!(timestamps(events), [200, 300]);
+
+    let events = manager
+        .get_events(PinId(2), Some(1), None, Some(100))
+        .await
+        .unwrap();
+    assert_eq!(timestamps(events), [300]);
+
+    let events = manager
+        .get_events(PinId(2), None, None, Some(300))
+        .await
+        .unwrap();
+    assert!(events.is_empty());
+}
+
+#[actix_rt::test]
+async fn events_of_all_pins_merge_by_time() {
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(sample_config()), backend);
+
+    let handler = manager.event_handler();
+    for (pin_id, timestamp_ms) in [(2, 300), (1, 100), (2, 200), (1, 200), (1, 400)] {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(pin_id),
+            edge: EdgeDetect::Rising,
+            timestamp_ms,
+            seq: 0,
+            count: 1,
+        });
+    }
+
+    let merged = |events: Vec<EdgeEvent>| -> Vec<(u32, u64)> {
+        events
+            .iter()
+            .map(|e| (e.pin_id.0, e.timestamp_ms))
+            .collect()
+    };
+
+    // equal timestamps keep dispatch order
+    let events = manager.get_all_events(None, None, None);
+    assert_eq!(
+        merged(events),
+        [(1, 100), (2, 200), (1, 200), (2, 300), (1, 400)]
+    );
+
+    let events = manager.get_all_events(Some(2), None, None);
+    assert_eq!(merged(events), [(2, 300), (1, 400)]);
+
+    let events = manager.get_all_events(None, None, Some(200));
+    assert_eq!(merged(events), [(2, 300), (1, 400)]);
+}
+
+#[actix_rt::test]
+async fn event_seq_increases_across_pins() {
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(sample_config()), backend);
+    let mut rx = manager.subscribe_events();
+    assert_eq!(manager.current_event_seq(), 0);
+
+    let handler = manager.event_handler();
+    for pin_id in [2, 42, 2, 1] {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(pin_id),
+            edge: EdgeDetect::Rising,
+            timestamp_ms: 0,
+            seq: 0,
+            count: 1,
+        });
+    }
+
+    let mut seqs = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        seqs.push(event.seq);
+    }
+    assert_eq!(seqs, [1, 2, 3, 4]);
+    assert_eq!(manager.current_event_seq(), 4);
+
+    let history: Vec<u64> = manager
+        .get_events(PinId(2), None, None, None)
+        .await
+        .unwrap()
+        .iter()
+        .map(|e| e.seq)
+        .collect();
+    assert_eq!(history, [1, 3]);
+}
+
+#[actix_rt::test]
+async fn history_capacity_per_pin() {
+    let mut config = sample_config();
+    config.event_history_capacity = 2;
+    config.gpios.get_mut(&PinId(2)).unwrap().history_capacity = Some(5);
+    config.gpios.get_mut(&PinId(42)).unwrap().history_capacity = Some(0);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(config), backend);
+    let mut rx = manager.subscribe_events();
+
+    let handler = manager.event_handler();
+    for timestamp_ms in 0..8 {
+        for pin_id in [1, 2, 42] {
+            handler.dispatch(EdgeEvent {
+                pin_id: PinId(pin_id),
+                edge: EdgeDetect::Rising,
+                timestamp_ms,
+                seq: 0,
+                count: 1,
+            });
+        }
+    }
+
+    let kept = |pin_id| {
+        let manager = &manager;
+        async move {
+            manager
+                .get_events(PinId(pin_id), None, None, None)
+                .await
+                .unwrap()
+                .iter()
+                .map(|e| e.timestamp_ms)
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(kept(1).await, [6, 7]);
+    assert_eq!(kept(2).await, [3, 4, 5, 6, 7]);
+    assert!(kept(42).await.is_empty());
+
+    // pins without history are still broadcast
+    let mut broadcast = 0;
+    while let Ok(event) = rx.try_recv() {
+        broadcast += usize::from(event.pin_id == PinId(42));
+    }
+    assert_eq!(broadcast, 8);
+}
+
+#[actix_rt::test]
+async fn event_history_restored_from_log() {
+    let path = std::env::temp_dir().join(format!("gmgr-events-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut config = sample_config();
+    config.event_history_capacity = 2;
+    config.event_log_path = Some(path.to_string_lossy().into_owned());
+    let config = Arc::new(config);
+
+    let manager = GpioManager::new(config.clone(), Arc::new(MockGpioBackend::default()));
+    let handler = manager.event_handler();
+    for (pin_id, timestamp_ms) in [(2, 10), (2, 20), (42, 30), (2, 40)] {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(pin_id),
+            edge: EdgeDetect::Falling,
+            timestamp_ms,
+            seq: 0,
+            count: 1,
+        });
+    }
+    drop(handler);
+    drop(manager);
+
+    let manager = GpioManager::new(config, Arc::new(MockGpioBackend::default()));
+    let restored: Vec<(u64, u64)> = manager
+        .get_events(PinId(2), None, None, None)
+        .await
+        .unwrap()
+        .iter()
+        .map(|e| (e.timestamp_ms, e.seq))
+        .collect();
+    assert_eq!(restored, [(20, 2), (40, 4)]);
+    assert_eq!(
+        manager
+            .get_events(PinId(42), None, None, None)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(manager.current_event_seq(), 4);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[actix_rt::test]
+async fn shutdown_disables_all_pins() {
+    let mut config = sample_config();
+    config.gpios.get_mut(&PinId(1)).unwrap().safe_value = Some(0);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(config), backend);
+
+    let output = PinSettings {
+        state: GpioState::PushPull,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(1), &output).await.unwrap();
+    manager.write_value(PinId(1), 1).await.unwrap();
+    let input = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Both,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(2), &input).await.unwrap();
+    manager.set_pin_settings(PinId(42), &output).await.unwrap();
+
+    manager.shutdown().await;
+
+    for pin_id in [1, 2, 42] {
+        let settings = manager.get_pin_settings(PinId(pin_id)).await.unwrap();
+        assert_eq!(settings.state, GpioState::Disabled);
+        assert_eq!(settings.edge, EdgeDetect::None);
+    }
+}
+
+#[test]
+fn duplicate_pin_names_are_ambiguous() {
+    let mut config = sample_config();
+    config.gpios.get_mut(&PinId(42)).unwrap().name = "LED 1".into();
+    let manager = GpioManager::new(Arc::new(config), Arc::new(MockGpioBackend::default()));
+
+    let err = manager.pin_id_by_name("LED 1").unwrap_err();
+    assert!(err.to_string().contains("[1, 42]"));
+    assert_eq!(manager.pin_id_by_name("BUTTON 1").unwrap(), PinId(2));
+}
+
+#[actix_rt::test]
+async fn drop_applies_safe_values() {
+    let mut config = sample_config();
+    config.gpios.get_mut(&PinId(1)).unwrap().safe_value = Some(0);
+    config.gpios.get_mut(&PinId(42)).unwrap().safe_value = Some(1);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(config), backend.clone());
+
+    let output = PinSettings {
+        state: GpioState::PushPull,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(1), &output).await.unwrap();
+    manager.write_value(PinId(1), 1).await.unwrap();
+    // pin 42 stays disabled, so its safe value is not driven
+    drop(manager);
+
+    assert_eq!(backend.physical_value(PinId(1)), Some(0));
+    assert_eq!(backend.physical_value(PinId(42)), None);
+}
+
+#[actix_rt::test]
+async fn injected_events_land_in_history() {
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(sample_config()), backend.clone());
+
+    // no handler is registered until edge detection is enabled
+    assert!(
+        backend
+            .inject_event(PinId(2), EdgeDetect::Rising, 10)
+            .is_err()
+    );
+
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Both,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(2), &settings).await.unwrap();
+    backend
+        .inject_event(PinId(2), EdgeDetect::Rising, 10)
+        .unwrap();
+    backend
+        .inject_event(PinId(2), EdgeDetect::Falling, 20)
+        .unwrap();
+
+    let events = manager
+        .get_events(PinId(2), None, None, None)
+        .await
+        .unwrap();
+    let edges: Vec<_> = events.iter().map(|e| (e.edge, e.timestamp_ms)).collect();
+    assert_eq!(
+        edges,
+        vec![(EdgeDetect::Rising, 10), (EdgeDetect::Falling, 20)]
+    );
+}
+
+#[actix_rt::test]
+async fn voted_read_picks_majority() {
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = GpioManager::new(Arc::new(sample_config()), backend.clone());
+    let input = PinSettings {
+        state: GpioState::PullDown,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(2), &input).await.unwrap();
+
+    backend
+        .queue_input_levels(PinId(2), &[1, 0, 1, 1, 0])
+        .unwrap();
+    let voted = manager.read_value_voted(PinId(2), 5, 1).await.unwrap();
+    assert_eq!(voted.value, 1);
+    assert_eq!(voted.samples, [1, 0, 1, 1, 0]);
+
+    backend.queue_input_levels(PinId(2), &[0, 1, 0]).unwrap();
+    let voted = manager.read_value_voted(PinId(2), 3, 0).await.unwrap();
+    assert_eq!(voted.value, 0);
+
+    let err = manager
+        .read_value_voted(PinId(2), MAX_VOTE_SAMPLES + 1, 0)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::ValueOutOfRange { max, .. } if max == MAX_VOTE_SAMPLES as i64));
+    assert!(manager.read_value_voted(PinId(2), 0, 0).await.is_err());
+}
@@ -0,0 +1,151 @@
+#![cfg(feature = "mqtt")]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::BytesMut;
+use gmgr::{
+    AppConfig, EdgeDetect, EdgeEvent, GpioManager, GpioState, MockGpioBackend, MqttConfig, PinId,
+    PinSettings, spawn_mqtt_bridge,
+};
+use rumqttc::{
+    ConnAck, ConnectReturnCode, Packet, PubAck, Publish, QoS, SubAck, SubscribeReasonCode,
+};
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+const MAX_PACKET: usize = 64 * 1024;
+
+/// Accepts one client, acks its packets, reports what it publishes and
+/// subscribes to, and forwards injected publishes to it.
+async fn start_broker() -> (
+    u16,
+    mpsc::UnboundedReceiver<Packet>,
+    mpsc::UnboundedSender<Publish>,
+) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (seen_tx, seen_rx) = mpsc::unbounded_channel();
+    let (inject_tx, mut inject_rx) = mpsc::unbounded_channel::<Publish>();
+
+    actix_rt::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = BytesMut::new();
+        loop {
+            let mut out = BytesMut::new();
+            tokio::select! {
+                read = stream.read_buf(&mut buf) => {
+                    if read.unwrap_or(0) == 0 {
+                        break;
+                    }
+                    while let Ok(packet) = Packet::read(&mut buf, MAX_PACKET) {
+                        let reply = match &packet {
+                            Packet::Connect(_) => {
+                                Some(Packet::ConnAck(ConnAck::new(ConnectReturnCode::Success, false)))
+                            }
+                            Packet::Subscribe(sub) => Some(Packet::SubAck(SubAck::new(
+                                sub.pkid,
+                                vec![SubscribeReasonCode::Success(QoS::AtLeastOnce)],
+                            ))),
+                            Packet::Publish(publish) if publish.qos != QoS::AtMostOnce => {
+                                Some(Packet::PubAck(PubAck::new(publish.pkid)))
+                            }
+                            Packet::PingReq => Some(Packet::PingResp),
+                            _ => None,
+                        };
+                        if let Some(reply) = reply {
+                            reply.write(&mut out, MAX_PACKET).unwrap();
+                        }
+                        let _ = seen_tx.send(packet);
+                    }
+                }
+                Some(publish) = inject_rx.recv() => {
+                    Packet::Publish(publish).write(&mut out, MAX_PACKET).unwrap();
+                }
+            }
+            stream.write_all(&out).await.unwrap();
+        }
+    });
+
+    (port, seen_rx, inject_tx)
+}
+
+async fn next_matching(
+    seen: &mut mpsc::UnboundedReceiver<Packet>,
+    matches: impl Fn(&Packet) -> bool,
+) -> Packet {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let packet = seen.recv().await.unwrap();
+            if matches(&packet) {
+                return packet;
+            }
+        }
+    })
+    .await
+    .unwrap()
+}
+
+#[actix_rt::test]
+async fn bridge_publishes_events_and_applies_sets() {
+    let (port, mut seen, inject) = start_broker().await;
+    let cfg = Arc::new(AppConfig::load_from_file("config.json").unwrap());
+    let manager = Arc::new(GpioManager::new(cfg, Arc::new(MockGpioBackend::default())));
+    let output = PinSettings {
+        state: GpioState::PushPull,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(1), &output).await.unwrap();
+
+    let mqtt = MqttConfig {
+        broker: format!("127.0.0.1:{port}"),
+        base_topic: "gmgr".into(),
+        client_id: "gmgr-test".into(),
+    };
+    spawn_mqtt_bridge(manager.clone(), &mqtt).unwrap();
+
+    let subscribe = next_matching(&mut seen, |p| matches!(p, Packet::Subscribe(_))).await;
+    let Packet::Subscribe(subscribe) = subscribe else {
+        unreachable!()
+    };
+    assert_eq!(subscribe.filters[0].path, "gmgr/+/set");
+
+    manager.event_handler().dispatch(EdgeEvent {
+        pin_id: PinId(2),
+        edge: EdgeDetect::Rising,
+        timestamp_ms: 10,
+        seq: 0,
+        count: 1,
+    });
+    let publish = next_matching(&mut seen, |p| matches!(p, Packet::Publish(_))).await;
+    let Packet::Publish(publish) = publish else {
+        unreachable!()
+    };
+    assert_eq!(publish.topic, "gmgr/2/event");
+    let event: Value = serde_json::from_slice(&publish.payload).unwrap();
+    assert_eq!(event["edge"], "rising");
+    assert_eq!(event["seq"], 1);
+
+    inject
+        .send(Publish::new("gmgr/1/set", QoS::AtMostOnce, "1"))
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while manager.read_value(PinId(1)).await.unwrap() != 1 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[test]
+fn mqtt_broker_port_defaults_to_1883() {
+    let mqtt = MqttConfig {
+        broker: "broker.local".into(),
+        base_topic: "gmgr".into(),
+        client_id: "gmgr".into(),
+    };
+    assert_eq!(mqtt.broker_addr().unwrap(), ("broker.local", 1883));
+}
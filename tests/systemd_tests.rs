@@ -0,0 +1,22 @@
+#![cfg(feature = "systemd")]
+
+use gmgr::{activated_listeners, listen_fds};
+
+#[test]
+fn listen_fds_only_for_this_process() {
+    assert_eq!(listen_fds(Some("42"), Some("2"), 42), [3, 4]);
+    assert_eq!(listen_fds(Some("42"), Some("1"), 42), [3]);
+
+    assert!(listen_fds(Some("41"), Some("2"), 42).is_empty());
+    assert!(listen_fds(None, Some("2"), 42).is_empty());
+    assert!(listen_fds(Some("42"), None, 42).is_empty());
+    assert!(listen_fds(Some("42"), Some("0"), 42).is_empty());
+    assert!(listen_fds(Some("42"), Some("-1"), 42).is_empty());
+    assert!(listen_fds(Some("pid"), Some("1"), 42).is_empty());
+}
+
+// the test harness is not socket activated, so nothing is taken
+#[test]
+fn no_listeners_without_activation() {
+    assert!(activated_listeners().unwrap().is_empty());
+}
@@ -0,0 +1,32 @@
+#![cfg(feature = "tls")]
+
+use actix_web::{App, HttpResponse, HttpServer, web};
+use gmgr::TlsConfig;
+
+fn fixture_tls() -> TlsConfig {
+    TlsConfig {
+        cert_path: "tests/fixtures/tls/cert.pem".into(),
+        key_path: "tests/fixtures/tls/key.pem".into(),
+    }
+}
+
+#[actix_rt::test]
+async fn self_signed_cert_binds() {
+    let tls_config = fixture_tls().server_config().unwrap();
+
+    let server = HttpServer::new(|| App::new().route("/", web::get().to(HttpResponse::Ok)))
+        .bind_rustls_0_23("127.0.0.1:0", tls_config)
+        .unwrap();
+    assert_eq!(server.addrs().len(), 1);
+}
+
+#[test]
+fn missing_key_is_config_error() {
+    let tls = TlsConfig {
+        key_path: "tests/fixtures/tls/missing.pem".into(),
+        ..fixture_tls()
+    };
+
+    let err = tls.server_config().unwrap_err();
+    assert!(err.to_string().contains("failed to load tls key"));
+}
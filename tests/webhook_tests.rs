@@ -0,0 +1,116 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use actix_web::{App, HttpResponse, HttpServer, web};
+use gmgr::{AppConfig, EdgeDetect, EdgeEvent, GpioManager, MockGpioBackend, PinId, WebhookConfig};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+/// Records posted bodies, answering 500 to the first `failures` requests.
+fn start_receiver(failures: usize) -> (SocketAddr, mpsc::UnboundedReceiver<Value>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let server = HttpServer::new(move || {
+        let tx = tx.clone();
+        let attempts = attempts.clone();
+        App::new().route(
+            "/hook",
+            web::post().to(move |body: web::Json<Value>| {
+                let tx = tx.clone();
+                let attempts = attempts.clone();
+                async move {
+                    if attempts.fetch_add(1, Ordering::SeqCst) < failures {
+                        return HttpResponse::InternalServerError().finish();
+                    }
+                    let _ = tx.send(body.into_inner());
+                    HttpResponse::Ok().finish()
+                }
+            }),
+        )
+    })
+    .workers(1)
+    .bind("127.0.0.1:0")
+    .unwrap();
+    let addr = server.addrs()[0];
+    actix_rt::spawn(server.run());
+
+    (addr, rx)
+}
+
+fn manager_with_webhook(hook: WebhookConfig) -> GpioManager<MockGpioBackend> {
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+    cfg.webhooks = vec![hook];
+    cfg.validate().unwrap();
+
+    GpioManager::new(Arc::new(cfg), Arc::new(MockGpioBackend::default()))
+}
+
+fn edge(pin_id: u32, edge: EdgeDetect, timestamp_ms: u64) -> EdgeEvent {
+    EdgeEvent {
+        pin_id: PinId(pin_id),
+        edge,
+        timestamp_ms,
+        seq: 0,
+        count: 1,
+    }
+}
+
+#[actix_rt::test]
+async fn matching_events_are_posted() {
+    let (addr, mut received) = start_receiver(0);
+    let manager = manager_with_webhook(WebhookConfig {
+        url: format!("http://{addr}/hook"),
+        pin_filter: Some(vec![PinId(2)]),
+        edge: Some(EdgeDetect::Rising),
+    });
+    let handler = manager.event_handler();
+
+    handler.dispatch(edge(42, EdgeDetect::Rising, 10));
+    handler.dispatch(edge(2, EdgeDetect::Falling, 20));
+    handler.dispatch(edge(2, EdgeDetect::Rising, 30));
+
+    let body = tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(body["pin_id"], 2);
+    assert_eq!(body["edge"], "rising");
+    assert_eq!(body["timestamp_ms"], 30);
+    assert_eq!(body["seq"], 3);
+}
+
+#[actix_rt::test]
+async fn failed_deliveries_are_retried() {
+    let (addr, mut received) = start_receiver(2);
+    let manager = manager_with_webhook(WebhookConfig {
+        url: format!("http://{addr}/hook"),
+        pin_filter: None,
+        edge: None,
+    });
+
+    manager
+        .event_handler()
+        .dispatch(edge(1, EdgeDetect::Falling, 10));
+
+    let body = tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(body["pin_id"], 1);
+    assert_eq!(body["edge"], "falling");
+}
+
+#[test]
+fn webhook_url_must_be_http() {
+    let mut cfg = AppConfig::load_from_file("config.json").unwrap();
+    cfg.webhooks = vec![WebhookConfig {
+        url: "ftp://example.com/hook".into(),
+        pin_filter: None,
+        edge: None,
+    }];
+
+    let err = cfg.validate().unwrap_err();
+    assert!(err.to_string().contains("invalid webhook url"));
+}
@@ -0,0 +1,520 @@
+use std::future::poll_fn;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::body::MessageBody;
+use actix_web::{App, HttpServer, test, web};
+use futures_util::{SinkExt, StreamExt};
+use gmgr::{
+    AppConfig, AppState, EdgeDetect, EdgeEvent, GpioManager, GpioState, LagPolicy, MockGpioBackend,
+    PinId, PinSettings,
+};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+fn sample_config() -> AppConfig {
+    AppConfig::load_from_file("config.json").unwrap()
+}
+
+fn start_server(state: AppState<MockGpioBackend>, scope_path: String) -> SocketAddr {
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .service(state.api_scope(&scope_path))
+    })
+    .workers(1)
+    .bind("127.0.0.1:0")
+    .unwrap();
+    let addr = server.addrs()[0];
+    actix_rt::spawn(server.run());
+
+    addr
+}
+
+#[actix_rt::test]
+async fn pin_websocket_only_streams_subscribed_pin() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let handler = manager.event_handler();
+    let addr = start_server(
+        AppState {
+            manager,
+            config_path: None,
+        },
+        cfg.http.path[0].clone(),
+    );
+
+    let (mut ws, _) =
+        tokio_tungstenite::connect_async(format!("ws://{addr}/api/v1/gpio/2/events/ws"))
+            .await
+            .unwrap();
+
+    for (pin_id, edge) in [(1, EdgeDetect::Rising), (2, EdgeDetect::Falling)] {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(pin_id),
+            edge,
+            timestamp_ms: 0,
+            seq: 0,
+            count: 1,
+        });
+    }
+
+    let msg = tokio::time::timeout(Duration::from_secs(1), ws.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let Message::Text(text) = msg else {
+        panic!("unexpected message: {msg:?}");
+    };
+    let event: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(event["pin_id"], 2);
+    assert_eq!(event["edge"], "falling");
+
+    let next = tokio::time::timeout(Duration::from_millis(100), ws.next()).await;
+    assert!(next.is_err());
+}
+
+#[actix_rt::test]
+async fn pin_websocket_unknown_pin_returns_404() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let addr = start_server(
+        AppState {
+            manager,
+            config_path: None,
+        },
+        cfg.http.path[0].clone(),
+    );
+
+    let err = tokio_tungstenite::connect_async(format!("ws://{addr}/api/v1/gpio/999/events/ws"))
+        .await
+        .unwrap_err();
+    let tokio_tungstenite::tungstenite::Error::Http(resp) = err else {
+        panic!("unexpected error: {err}");
+    };
+    assert_eq!(resp.status(), 404);
+}
+
+#[actix_rt::test]
+async fn sse_streams_event_frames() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let handler = manager.event_handler();
+    let state = AppState {
+        manager,
+        config_path: None,
+    };
+    let scope_path = cfg.http.path[0].clone();
+
+    let app = test::init_service(
+        App::new()
+            .service(state.api_scope(&scope_path))
+            .app_data(web::Data::new(state)),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/v1/gpios/events/sse?pin=2")
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+
+    for pin_id in [1, 2] {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(pin_id),
+            edge: EdgeDetect::Rising,
+            timestamp_ms: 0,
+            seq: 0,
+            count: 1,
+        });
+    }
+
+    let mut body = resp.into_body();
+    let event = loop {
+        let chunk = tokio::time::timeout(
+            Duration::from_secs(1),
+            poll_fn(|cx| Pin::new(&mut body).poll_next(cx)),
+        )
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+        let frame = String::from_utf8(chunk.to_vec()).unwrap();
+        if let Some(data) = frame.strip_prefix("data: ") {
+            break serde_json::from_str::<Value>(data.trim()).unwrap();
+        }
+        assert!(frame.starts_with(':'));
+    };
+    assert_eq!(event["pin_id"], 2);
+    assert_eq!(event["edge"], "rising");
+}
+
+#[actix_rt::test]
+async fn websocket_commands_drive_pins() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(
+        cfg.clone(),
+        backend.clone(),
+    ));
+    let addr = start_server(
+        AppState {
+            manager: manager.clone(),
+            config_path: None,
+        },
+        cfg.http.path[0].clone(),
+    );
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/api/v1/gpios/events"))
+        .await
+        .unwrap();
+
+    async fn send(ws: &mut WsStream, text: &str) {
+        ws.send(Message::text(text)).await.unwrap();
+    }
+    async fn recv(ws: &mut WsStream) -> Value {
+        let msg = tokio::time::timeout(Duration::from_secs(1), ws.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        serde_json::from_str(msg.to_text().unwrap()).unwrap()
+    }
+
+    send(
+        &mut ws,
+        r#"{"id":1,"cmd":"set_settings","pin_id":1,"state":"push-pull"}"#,
+    )
+    .await;
+    let reply = recv(&mut ws).await;
+    assert_eq!(reply["id"], 1);
+    assert_eq!(reply["ok"]["state"], "push-pull");
+
+    // an input edge lands on the same socket as the command replies
+    let settings = PinSettings {
+        state: GpioState::PullUp,
+        edge: EdgeDetect::Both,
+        debounce_ms: 0,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(2), &settings).await.unwrap();
+
+    send(
+        &mut ws,
+        r#"{"id":"a","cmd":"set_value","pin_id":1,"value":1}"#,
+    )
+    .await;
+    backend.set_input_level(PinId(2), 1).unwrap();
+    let mut frames = [recv(&mut ws).await, recv(&mut ws).await];
+    frames.sort_by_key(|f| f.get("id").is_some());
+    assert_eq!(frames[0]["pin_id"], 2);
+    assert_eq!(frames[0]["edge"], "rising");
+    assert_eq!(frames[1]["id"], "a");
+    assert_eq!(frames[1]["ok"], 1);
+
+    send(&mut ws, r#"{"id":2,"cmd":"get_value","pin_id":1}"#).await;
+    let reply = recv(&mut ws).await;
+    assert_eq!(reply["ok"], 1);
+
+    send(&mut ws, r#"{"id":3,"cmd":"explode"}"#).await;
+    let reply = recv(&mut ws).await;
+    assert_eq!(reply["id"], 3);
+    assert!(reply["error"].as_str().unwrap().contains("invalid command"));
+
+    send(&mut ws, "not json").await;
+    let reply = recv(&mut ws).await;
+    assert!(reply["error"].is_string());
+
+    send(&mut ws, r#"{"id":4,"cmd":"get_value","pin_id":1}"#).await;
+    let reply = recv(&mut ws).await;
+    assert_eq!(reply["id"], 4);
+}
+
+#[actix_rt::test]
+async fn lag_policy_decides_slow_client_fate() {
+    for policy in [LagPolicy::DropOldest, LagPolicy::Disconnect] {
+        let mut cfg = sample_config();
+        cfg.broadcast_capacity = 2;
+        cfg.lag_policy = policy;
+        let cfg = Arc::new(cfg);
+        let backend = Arc::new(MockGpioBackend::default());
+        let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+        let handler = manager.event_handler();
+        let addr = start_server(
+            AppState {
+                manager,
+                config_path: None,
+            },
+            cfg.http.path[0].clone(),
+        );
+
+        let (mut ws, _) =
+            tokio_tungstenite::connect_async(format!("ws://{addr}/api/v1/gpios/events"))
+                .await
+                .unwrap();
+
+        // far more events than the channel holds, faster than the socket drains
+        let dispatch = |count: u64, pin_id: u32| {
+            for timestamp_ms in 0..count {
+                handler.dispatch(EdgeEvent {
+                    pin_id: PinId(pin_id),
+                    edge: EdgeDetect::Rising,
+                    timestamp_ms,
+                    seq: 0,
+                    count: 1,
+                });
+            }
+        };
+        dispatch(1000, 1);
+
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(1), ws.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            match msg {
+                Message::Text(text) if text.contains("lagged") => {
+                    assert_eq!(policy, LagPolicy::DropOldest);
+                    break;
+                }
+                Message::Text(_) => {}
+                Message::Close(Some(frame)) => {
+                    assert_eq!(policy, LagPolicy::Disconnect);
+                    assert_eq!(u16::from(frame.code), 1013);
+                    break;
+                }
+                other => panic!("unexpected message: {other:?}"),
+            }
+        }
+
+        if policy == LagPolicy::DropOldest {
+            // the client keeps receiving once it has caught up
+            dispatch(1, 2);
+            loop {
+                let msg = tokio::time::timeout(Duration::from_secs(1), ws.next())
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .unwrap();
+                let Message::Text(text) = msg else {
+                    panic!("unexpected message: {msg:?}");
+                };
+                if let Ok(event) = serde_json::from_str::<Value>(&text)
+                    && event["pin_id"] == 2
+                {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn idle_websocket_is_pinged_and_closed_without_pongs() {
+    let mut cfg = sample_config();
+    cfg.ws_ping_interval_ms = 50;
+    cfg.ws_pong_timeout_ms = 100;
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let addr = start_server(
+        AppState {
+            manager,
+            config_path: None,
+        },
+        cfg.http.path[0].clone(),
+    );
+    let url = format!("ws://{addr}/api/v1/gpio/2/events/ws");
+
+    // reading lets tungstenite answer each ping, so the socket stays open
+    let (mut ws, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+    for _ in 0..4 {
+        let msg = tokio::time::timeout(Duration::from_secs(1), ws.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert!(
+            matches!(msg, Message::Ping(_)),
+            "unexpected message: {msg:?}"
+        );
+    }
+
+    // a client that never reads never pongs
+    let (mut ws, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    loop {
+        let msg = tokio::time::timeout(Duration::from_secs(1), ws.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        match msg {
+            Message::Ping(_) => {}
+            Message::Close(Some(frame)) => {
+                assert_eq!(u16::from(frame.code), 1001);
+                break;
+            }
+            other => panic!("unexpected message: {other:?}"),
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn idle_websocket_is_closed_after_timeout() {
+    let mut cfg = sample_config();
+    cfg.ws_ping_interval_ms = 0;
+    cfg.http.ws_idle_timeout_ms = 200;
+    let cfg = Arc::new(cfg);
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let addr = start_server(
+        AppState {
+            manager,
+            config_path: None,
+        },
+        cfg.http.path[0].clone(),
+    );
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/api/v1/gpios/events"))
+        .await
+        .unwrap();
+    let connected = std::time::Instant::now();
+
+    // activity pushes the deadline back
+    tokio::time::sleep(Duration::from_millis(120)).await;
+    ws.send(Message::Ping(Vec::new().into())).await.unwrap();
+    let msg = tokio::time::timeout(Duration::from_secs(1), ws.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert!(
+        matches!(msg, Message::Pong(_)),
+        "unexpected message: {msg:?}"
+    );
+
+    let msg = tokio::time::timeout(Duration::from_secs(1), ws.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let Message::Close(Some(frame)) = msg else {
+        panic!("unexpected message: {msg:?}");
+    };
+    assert_eq!(u16::from(frame.code), 1001);
+    assert_eq!(frame.reason, "idle timeout");
+    assert!(connected.elapsed() >= Duration::from_millis(320));
+}
+
+#[actix_rt::test]
+async fn websocket_replays_history_before_live_events() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let handler = manager.event_handler();
+    let addr = start_server(
+        AppState {
+            manager,
+            config_path: None,
+        },
+        cfg.http.path[0].clone(),
+    );
+
+    for (pin_id, timestamp_ms) in [(2, 10), (1, 20), (2, 30), (2, 40), (42, 50)] {
+        handler.dispatch(EdgeEvent {
+            pin_id: PinId(pin_id),
+            edge: EdgeDetect::Rising,
+            timestamp_ms,
+            seq: 0,
+            count: 1,
+        });
+    }
+
+    let (mut ws, _) =
+        tokio_tungstenite::connect_async(format!("ws://{addr}/api/v1/gpios/events?replay=2"))
+            .await
+            .unwrap();
+    handler.dispatch(EdgeEvent {
+        pin_id: PinId(1),
+        edge: EdgeDetect::Falling,
+        timestamp_ms: 60,
+        seq: 0,
+        count: 1,
+    });
+
+    let mut received = Vec::new();
+    while received.len() < 5 {
+        let msg = tokio::time::timeout(Duration::from_secs(1), ws.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        if let Message::Text(text) = msg {
+            let event: Value = serde_json::from_str(&text).unwrap();
+            received.push(event["timestamp_ms"].as_u64().unwrap());
+        }
+    }
+    // the oldest event of pin 2 is past the replay, the live one comes last
+    assert_eq!(received, [20, 30, 40, 50, 60]);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), ws.next())
+            .await
+            .is_err()
+    );
+}
+
+#[actix_rt::test]
+async fn snapshot_websocket_pushes_enabled_values() {
+    let cfg = Arc::new(sample_config());
+    let backend = Arc::new(MockGpioBackend::default());
+    let manager = Arc::new(GpioManager::<MockGpioBackend>::new(cfg.clone(), backend));
+    let output = PinSettings {
+        state: GpioState::PushPull,
+        ..Default::default()
+    };
+    manager.set_pin_settings(PinId(1), &output).await.unwrap();
+    manager.write_value(PinId(1), 1).await.unwrap();
+    let addr = start_server(
+        AppState {
+            manager,
+            config_path: None,
+        },
+        cfg.http.path[0].clone(),
+    );
+
+    // clamped up to the minimum interval
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!(
+        "ws://{addr}/api/v1/gpios/snapshot/ws?interval_ms=1"
+    ))
+    .await
+    .unwrap();
+
+    for _ in 0..2 {
+        let msg = tokio::time::timeout(Duration::from_secs(1), ws.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let Message::Text(text) = msg else {
+            panic!("unexpected message: {msg:?}");
+        };
+        let snapshot: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(snapshot, serde_json::json!({ "1": { "ok": 1 } }));
+    }
+}